@@ -4,9 +4,10 @@
 from __future__ import annotations
 
 from datetime import date
-from typing import List, Optional
+from enum import Enum
+from typing import List, Optional, Union
 
-from pydantic import BaseModel, ConfigDict, RootModel
+from pydantic import BaseModel, ConfigDict, RootModel, conint
 
 
 class Version(BaseModel):
@@ -19,6 +20,33 @@ class Version(BaseModel):
     subnets: Optional[List[str]] = None
 
 
+class HealthCheck(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    name: str
+    query: str
+    min: Optional[float] = None
+    max: Optional[float] = None
+
+
+class Canary(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    subnets: conint(ge=1)
+    bake_time: Optional[str] = None
+
+
+class Blackout(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    start: date
+    end: date
+    reason: Optional[str] = None
+
+
 class Stage(BaseModel):
     model_config = ConfigDict(
         extra='forbid',
@@ -27,6 +55,41 @@ class Stage(BaseModel):
     bake_time: Optional[str] = None
     update_unassigned_nodes: Optional[bool] = None
     wait_for_next_week: Optional[bool] = None
+    health_checks: Optional[List[HealthCheck]] = None
+    not_before: Optional[date] = None
+    blackouts: Optional[List[Blackout]] = None
+    canary: Optional[Canary] = None
+
+
+class Assignment(Enum):
+    unassigned = 'unassigned'
+    assigned = 'assigned'
+    all = 'all'
+
+
+class Owner(Enum):
+    dfinity = 'dfinity'
+    others = 'others'
+    all = 'all'
+
+
+class HostosStage(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    assignment: Optional[Assignment] = None
+    owner: Optional[Owner] = None
+    nodes: Union[int, str]
+    bake_time: Optional[str] = None
+
+
+class HostosRollout(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    pause: Optional[bool] = None
+    version: str
+    stages: List[HostosStage]
 
 
 class Release(BaseModel):
@@ -37,6 +100,25 @@ class Release(BaseModel):
     versions: List[Version]
 
 
+class Weekday(Enum):
+    Mon = 'Mon'
+    Tue = 'Tue'
+    Wed = 'Wed'
+    Thu = 'Thu'
+    Fri = 'Fri'
+    Sat = 'Sat'
+    Sun = 'Sun'
+
+
+class RolloutWindow(BaseModel):
+    model_config = ConfigDict(
+        extra='forbid',
+    )
+    weekdays: Optional[List[Weekday]] = None
+    start: str
+    end: str
+
+
 class Rollout(BaseModel):
     model_config = ConfigDict(
         extra='forbid',
@@ -44,6 +126,11 @@ class Rollout(BaseModel):
     pause: Optional[bool] = None
     skip_days: Optional[List[date]] = None
     stages: List[Stage]
+    rollback_on_health_failure: Optional[bool] = None
+    windows: Optional[List[RolloutWindow]] = None
+    time_zone: Optional[str] = None
+    holiday_calendars: Optional[List[str]] = None
+    max_subnets_per_day: Optional[conint(ge=1)] = None
 
 
 class ReleaseIndex(BaseModel):
@@ -52,6 +139,7 @@ class ReleaseIndex(BaseModel):
     )
     rollout: Rollout
     releases: List[Release]
+    hostos_rollout: Optional[HostosRollout] = None
 
 
 class Model(RootModel[ReleaseIndex]):
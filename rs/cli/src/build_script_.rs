@@ -21,7 +21,8 @@ fn main() {
         );
     }
 
-    generate_completions()
+    generate_completions();
+    generate_backend_client();
 }
 
 fn generate_completions() {
@@ -61,3 +62,94 @@ fn generate_completions() {
         }
     }
 }
+
+/// Generates the methods of `DashboardBackendClient` from the OpenAPI spec of
+/// the management backend, so the client can't drift from the server routes.
+fn generate_backend_client() {
+    let outdir = env::var_os("OUT_DIR").expect("OUT_DIR var not set, cannot generate the backend client");
+    let spec = serde_json::from_str::<serde_json::Value>(
+        &ic_management_backend::endpoints::openapi::spec()
+            .to_json()
+            .expect("failed to serialize the backend OpenAPI spec"),
+    )
+    .expect("failed to parse the backend OpenAPI spec");
+
+    let mut methods = Vec::new();
+    for (path, item) in spec["paths"].as_object().expect("spec has no paths") {
+        for (method, operation) in item.as_object().expect("invalid path item") {
+            methods.push(backend_client_method(path, method, operation));
+        }
+    }
+
+    let client = format!("impl DashboardBackendClient {{\n{}}}\n", methods.join("\n"));
+    fs::write(PathBuf::from(outdir).join("backend_client.rs"), client).expect("failed to write the backend client");
+}
+
+fn backend_client_method(path: &str, method: &str, operation: &serde_json::Value) -> String {
+    let operation_id = operation["operationId"].as_str().expect("operation without operationId");
+
+    let mut args = vec!["&self".to_string()];
+    let mut query = Vec::new();
+    for param in operation["parameters"].as_array().into_iter().flatten() {
+        let name = param["name"].as_str().expect("parameter without name");
+        args.push(format!("{name}: impl std::fmt::Display"));
+        if param["in"] == "query" {
+            query.push(format!("(\"{name}\", {name}.to_string())"));
+        }
+    }
+    let body = &operation["requestBody"]["content"]["application/json"]["schema"];
+    if !body.is_null() {
+        args.push(format!("request: {}", backend_client_type(body)));
+    }
+
+    let response = operation["responses"]["200"]["content"]
+        .as_object()
+        .and_then(|content| content.values().next())
+        .map(|media| backend_client_type(&media["schema"]))
+        .unwrap_or_else(|| "serde_json::Value".to_string());
+
+    let relative_path = path.trim_start_matches('/');
+    let url = if relative_path.contains('{') {
+        format!("&format!(\"{relative_path}\")")
+    } else {
+        format!("\"{relative_path}\"")
+    };
+
+    let mut code = format!(
+        "    /// `{} {path}`\n    pub async fn {operation_id}({}) -> anyhow::Result<{response}> {{\n",
+        method.to_uppercase(),
+        args.join(", ")
+    );
+    code += &format!("        reqwest::Client::new()\n            .{method}(self.url.join({url}).map_err(|e| anyhow::anyhow!(e))?)\n");
+    if !query.is_empty() {
+        code += &format!("            .query(&[{}])\n", query.join(", "));
+    }
+    if !body.is_null() {
+        code += "            .json(&request)\n";
+    }
+    code += "            .rest_send()\n            .await\n    }\n";
+    code
+}
+
+fn backend_client_type(schema: &serde_json::Value) -> String {
+    let inner = if let Some(reference) = schema["$ref"].as_str() {
+        reference.rsplit('/').next().unwrap_or_default().to_string()
+    } else if let Some([item]) = schema["allOf"].as_array().map(Vec::as_slice) {
+        backend_client_type(item)
+    } else {
+        match schema["type"].as_str() {
+            Some("string") => "String".to_string(),
+            Some("boolean") => "bool".to_string(),
+            Some("integer") if schema["minimum"].as_u64() == Some(0) => "u64".to_string(),
+            Some("integer") => "i64".to_string(),
+            Some("number") => "f64".to_string(),
+            Some("array") => format!("Vec<{}>", backend_client_type(&schema["items"])),
+            _ => "serde_json::Value".to_string(),
+        }
+    };
+    if schema["nullable"].as_bool().unwrap_or_default() {
+        format!("Option<{inner}>")
+    } else {
+        inner
+    }
+}
@@ -333,7 +333,8 @@ pub mod version {
 }
 
 pub mod hostos {
-    #[derive(ValueEnum, Copy, Clone, Debug, Ord, Eq, PartialEq, PartialOrd, Parser, Default)]
+    #[derive(ValueEnum, Copy, Clone, Debug, Ord, Eq, PartialEq, PartialOrd, Parser, Default, Deserialize)]
+    #[serde(rename_all = "lowercase")]
     pub enum NodeOwner {
         Dfinity,
         Others,
@@ -351,7 +352,8 @@ pub mod hostos {
         }
     }
 
-    #[derive(ValueEnum, Copy, Clone, Debug, Ord, Eq, PartialEq, PartialOrd, Default)]
+    #[derive(ValueEnum, Copy, Clone, Debug, Ord, Eq, PartialEq, PartialOrd, Default, Deserialize)]
+    #[serde(rename_all = "lowercase")]
     pub enum NodeAssignment {
         Unassigned,
         Assigned,
@@ -372,6 +374,7 @@ pub mod hostos {
     use super::*;
     use clap::ValueEnum;
     use ic_base_types::PrincipalId;
+    use serde::Deserialize;
 
     #[derive(Parser, Clone)]
     pub struct Cmd {
@@ -452,6 +455,7 @@ pub mod nodes {
 }
 
 pub mod proposals {
+    use chrono::NaiveDate;
     use clap::ValueEnum;
     use ic_nns_governance::pb::v1::{ProposalStatus as ProposalStatusUpstream, Topic as TopicUpstream};
 
@@ -535,6 +539,29 @@ pub mod proposals {
             /// Proposal ID
             proposal_id: u64,
         },
+
+        /// Get the recorded history of topology, version and HostOS proposals
+        History {
+            /// Only include proposals that touched this subnet
+            #[clap(long)]
+            subnet: Option<PrincipalId>,
+
+            /// Only include proposals that touched this node
+            #[clap(long)]
+            node: Option<PrincipalId>,
+
+            /// Only include proposals that touched nodes of this node provider
+            #[clap(long)]
+            provider: Option<PrincipalId>,
+
+            /// Only include proposals submitted on or after this date (YYYY-MM-DD, UTC)
+            #[clap(long)]
+            since: Option<NaiveDate>,
+
+            /// Only include proposals submitted on or before this date (YYYY-MM-DD, UTC)
+            #[clap(long)]
+            until: Option<NaiveDate>,
+        },
     }
 
     #[derive(ValueEnum, Clone, Debug)]
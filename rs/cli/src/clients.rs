@@ -1,9 +1,11 @@
 use async_trait::async_trait;
 use decentralization::SubnetChangeResponse;
-use ic_base_types::PrincipalId;
 use ic_management_types::{
-    requests::{MembershipReplaceRequest, NodesRemoveRequest, NodesRemoveResponse, SubnetCreateRequest, SubnetResizeRequest},
-    Artifact, Network, NetworkError, Release, TopologyProposal,
+    requests::{
+        MembershipReplaceRequest, NodesRemoveRequest, NodesRemoveResponse, ProposalHistoryQuery, SubnetCreateRequest, SubnetResizeRequest,
+        SubnetWhatIfRequest,
+    },
+    Network, NetworkError, ProposalHistoryEntry, Release, TopologyChangeProposal,
 };
 use log::error;
 use serde::de::DeserializeOwned;
@@ -36,69 +38,12 @@ impl DashboardBackendClient {
             url: reqwest::Url::parse(&url).unwrap(),
         }
     }
-
-    pub async fn subnet_pending_action(&self, subnet: PrincipalId) -> anyhow::Result<Option<TopologyProposal>> {
-        reqwest::Client::new()
-            .get(
-                self.url
-                    .join(&format!("subnet/{subnet}/pending_action"))
-                    .map_err(|e| anyhow::anyhow!(e))?,
-            )
-            .rest_send()
-            .await
-    }
-
-    pub async fn membership_replace(&self, request: MembershipReplaceRequest) -> anyhow::Result<SubnetChangeResponse> {
-        reqwest::Client::new()
-            .post(self.url.join("subnet/membership/replace").map_err(|e| anyhow::anyhow!(e))?)
-            .json(&request)
-            .rest_send()
-            .await
-    }
-
-    pub async fn subnet_resize(&self, request: SubnetResizeRequest) -> anyhow::Result<SubnetChangeResponse> {
-        reqwest::Client::new()
-            .post(self.url.join("subnet/membership/resize").map_err(|e| anyhow::anyhow!(e))?)
-            .json(&request)
-            .rest_send()
-            .await
-    }
-
-    pub async fn subnet_create(&self, request: SubnetCreateRequest) -> anyhow::Result<SubnetChangeResponse> {
-        reqwest::Client::new()
-            .post(self.url.join("subnet/create").map_err(|e| anyhow::anyhow!(e))?)
-            .json(&request)
-            .rest_send()
-            .await
-    }
-
-    pub async fn get_retireable_versions(&self, release_artifact: &Artifact) -> anyhow::Result<Vec<Release>> {
-        reqwest::Client::new()
-            .get(
-                self.url
-                    .join(&format!("release/retireable/{}", release_artifact))
-                    .map_err(|e| anyhow::anyhow!(e))?,
-            )
-            .rest_send()
-            .await
-    }
-
-    pub async fn get_nns_replica_version(&self) -> anyhow::Result<String> {
-        reqwest::Client::new()
-            .get(self.url.join("release/versions/nns").map_err(|e| anyhow::anyhow!(e))?)
-            .rest_send()
-            .await
-    }
-
-    pub async fn remove_nodes(&self, request: NodesRemoveRequest) -> anyhow::Result<NodesRemoveResponse> {
-        reqwest::Client::new()
-            .post(self.url.join("nodes/remove").map_err(|e| anyhow::anyhow!(e))?)
-            .json(&request)
-            .rest_send()
-            .await
-    }
 }
 
+// The endpoint methods are generated by the build script from the OpenAPI spec
+// of the backend, see `generate_backend_client`.
+include!(concat!(env!("OUT_DIR"), "/backend_client.rs"));
+
 #[async_trait]
 trait RESTRequestBuilder {
     async fn rest_send<T: DeserializeOwned>(self) -> anyhow::Result<T>;
@@ -1,4 +1,5 @@
 use crate::ic_admin::IcAdminWrapper;
+use chrono::NaiveTime;
 use clap::{error::ErrorKind, CommandFactory, Parser};
 use dialoguer::Confirm;
 use dotenv::dotenv;
@@ -10,7 +11,7 @@ use ic_base_types::CanisterId;
 use ic_canisters::governance::{governance_canister_version, GovernanceCanisterWrapper};
 use ic_canisters::CanisterClient;
 use ic_management_backend::endpoints;
-use ic_management_types::requests::NodesRemoveRequest;
+use ic_management_types::requests::{NodesRemoveRequest, ProposalHistoryQuery};
 use ic_management_types::{Artifact, MinNakamotoCoefficients, NodeFeature};
 use ic_nns_common::pb::v1::ProposalId;
 use ic_nns_governance::pb::v1::ListProposalInfo;
@@ -76,9 +77,17 @@ async fn async_main() -> Result<(), anyhow::Error> {
     thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async move {
-            endpoints::run_backend(&target_network_backend, "127.0.0.1", backend_port, true, Some(tx))
-                .await
-                .expect("failed")
+            endpoints::run_backend(
+                &target_network_backend,
+                Default::default(),
+                None,
+                "127.0.0.1",
+                backend_port,
+                true,
+                Some(tx),
+            )
+            .await
+            .expect("failed")
         });
     });
 
@@ -465,6 +474,23 @@ async fn async_main() -> Result<(), anyhow::Error> {
                     println!("{}", proposal);
                     Ok(())
                 }
+                cli::proposals::Commands::History {
+                    subnet,
+                    node,
+                    provider,
+                    since,
+                    until,
+                } => {
+                    runner_instance
+                        .proposal_history(ProposalHistoryQuery {
+                            subnet: *subnet,
+                            node: *node,
+                            provider: *provider,
+                            since: since.map(|d| d.and_time(NaiveTime::MIN).and_utc().timestamp() as u64),
+                            until: until.map(|d| d.and_hms_opt(23, 59, 59).expect("valid time").and_utc().timestamp() as u64),
+                        })
+                        .await
+                }
             },
         }
     })
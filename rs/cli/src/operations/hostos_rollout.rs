@@ -6,6 +6,7 @@ use ic_management_backend::health::{self, HealthStatusQuerier};
 use ic_management_backend::proposal::ProposalAgent;
 use ic_management_types::{Network, Node, Status, Subnet, UpdateNodesHostosVersionsProposal};
 use log::{debug, info};
+use serde::{Deserialize, Deserializer};
 use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 use crate::cli::hostos::{NodeAssignment, NodeOwner};
@@ -86,6 +87,22 @@ impl FromStr for NumberOfNodes {
     }
 }
 
+/// Accepts both numbers (`10`) and strings (`"10"`, `"10%"`)
+impl<'de> Deserialize<'de> for NumberOfNodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Absolute(i32),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Absolute(number) => Ok(NumberOfNodes::Absolute(number)),
+            Raw::Text(text) => NumberOfNodes::from_str(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
 impl Default for NumberOfNodes {
     fn default() -> Self {
         NumberOfNodes::Percentage(100)
@@ -202,6 +219,37 @@ impl HostosRollout {
             version: rollout_version.to_string(),
         }
     }
+    /// How many more nodes of each subnet, or of the unassigned nodes (keyed by
+    /// `None`), have to run the rollout version for `update_group` to be completed.
+    /// Nodes are taken from subnets the same way as in [`HostosRollout::execute`].
+    pub fn nodes_missing(&self, update_group: NodeGroupUpdate) -> BTreeMap<Option<PrincipalId>, usize> {
+        let nodes_in_group = self
+            .grouped_nodes
+            .iter()
+            .filter(|(NodeGroup { assignment, owner }, _)| {
+                (update_group.node_group.assignment == NodeAssignment::All || update_group.node_group.assignment == *assignment)
+                    && (update_group.node_group.owner == NodeOwner::All || update_group.node_group.owner == *owner)
+            })
+            .flat_map(|(_, nodes)| nodes)
+            .fold(BTreeMap::new(), |mut acc: BTreeMap<Option<PrincipalId>, Vec<&Node>>, node| {
+                acc.entry(node.subnet_id).or_default().push(node);
+                acc
+            });
+
+        nodes_in_group
+            .into_iter()
+            .map(|(subnet_id, nodes)| {
+                let group_size = match subnet_id.and_then(|id| self.subnets.get(&id)) {
+                    Some(subnet) => subnet.nodes.len(),
+                    None => nodes.len(),
+                };
+                let target = update_group.nodes_to_take(group_size).min(nodes.len());
+                let updated = nodes.iter().filter(|n| n.hostos_version == self.version).count();
+                (subnet_id, target.saturating_sub(updated))
+            })
+            .collect()
+    }
+
     async fn nodes_different_version(&self, nodes: Vec<Node>) -> Option<Vec<Node>> {
         let nodes_different_version = nodes.iter().filter(|n| n.hostos_version != self.version).cloned().collect::<Vec<_>>();
 
@@ -625,6 +673,57 @@ pub mod test {
         assert_eq!(results, want, "the first unassigned_dfinity_node should be updated");
     }
 
+    #[tokio::test]
+    async fn test_hostos_rollout_nodes_missing() {
+        let version_one = "ec140b74dc4fef2f4bee3fad936e315380fa5af3".to_string();
+        let version_two = "e268b9807f1ab4ae65d7b29fe70a3b358d014d6a".to_string();
+
+        let subnet_id = PrincipalId::new_subnet_test_id(0);
+        let union: BTreeMap<PrincipalId, Node> = gen_test_nodes(Some(subnet_id), 10, 0, version_one.clone(), true)
+            .into_iter()
+            .chain(gen_test_nodes(Some(subnet_id), 10, 10, version_two.clone(), false))
+            .chain(gen_test_nodes(None, 10, 20, version_one.clone(), true))
+            .chain(gen_test_nodes(None, 10, 30, version_two.clone(), true))
+            .collect();
+        let subnet = BTreeMap::from([(
+            subnet_id,
+            Subnet {
+                principal: subnet_id,
+                nodes: union.values().filter(|n| n.subnet_id.is_some()).cloned().collect(),
+                ..Default::default()
+            },
+        )]);
+
+        let network = Network::new("mainnet", &vec![]).await.unwrap();
+        let hostos_rollout = HostosRollout::new(
+            union,
+            subnet,
+            &network,
+            ProposalAgent::new(network.get_nns_urls()),
+            version_two.as_str(),
+            &None,
+        );
+
+        assert_eq!(
+            hostos_rollout.nodes_missing(NodeGroupUpdate::new(Some(Unassigned), Some(Dfinity), NumberOfNodes::Percentage(50))),
+            BTreeMap::from([(None, 0)]),
+            "half of the unassigned nodes are already updated"
+        );
+        assert_eq!(
+            hostos_rollout.nodes_missing(NodeGroupUpdate::new(Some(Unassigned), Some(Dfinity), NumberOfNodes::Percentage(80))),
+            BTreeMap::from([(None, 6)])
+        );
+        assert_eq!(
+            hostos_rollout.nodes_missing(NodeGroupUpdate::new(Some(Assigned), Some(Dfinity), NumberOfNodes::Percentage(30))),
+            BTreeMap::from([(Some(subnet_id), 6)]),
+            "assigned nodes are counted per subnet"
+        );
+        assert_eq!(
+            hostos_rollout.nodes_missing(NodeGroupUpdate::new_all(NodeAssignment::All, NodeOwner::All)),
+            BTreeMap::from([(None, 10), (Some(subnet_id), 10)])
+        );
+    }
+
     fn gen_test_nodes(
         subnet_id: Option<PrincipalId>,
         num_nodes: u64,
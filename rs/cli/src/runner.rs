@@ -8,7 +8,7 @@ use ic_base_types::PrincipalId;
 use ic_management_backend::proposal::ProposalAgent;
 use ic_management_backend::public_dashboard::query_ic_dashboard_list;
 use ic_management_backend::registry::{self, RegistryState};
-use ic_management_types::requests::NodesRemoveRequest;
+use ic_management_types::requests::{NodesRemoveRequest, ProposalHistoryQuery};
 use ic_management_types::{Artifact, Network, Node, NodeFeature, NodeProvidersResponse};
 use itertools::Itertools;
 use log::{info, warn};
@@ -107,12 +107,14 @@ impl Runner {
         }
         println!("{}", subnet_creation_data);
 
-        let replica_version = replica_version.unwrap_or(
-            self.dashboard_backend_client
+        let replica_version = match replica_version {
+            Some(replica_version) => replica_version,
+            None => self
+                .dashboard_backend_client
                 .get_nns_replica_version()
-                .await
-                .expect("Failed to get a GuestOS version of the NNS subnet"),
-        );
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Failed to get a GuestOS version of the NNS subnet"))?,
+        };
 
         self.ic_admin
             .propose_run(
@@ -436,4 +438,10 @@ impl Runner {
             .await?;
         Ok(())
     }
+
+    pub async fn proposal_history(&self, query: ProposalHistoryQuery) -> anyhow::Result<()> {
+        let history = self.dashboard_backend_client.proposal_history(query).await?;
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        Ok(())
+    }
 }
@@ -8,18 +8,25 @@ use std::fmt::{Display, Formatter};
 use ic_base_types::PrincipalId;
 use ic_management_types::NodeFeature;
 use serde::{self, Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, Deserialize, Serialize, Default)]
+#[derive(Clone, Debug, Deserialize, Serialize, Default, ToSchema)]
 pub struct SubnetChangeResponse {
+    #[schema(value_type = Vec<String>)]
     pub added: Vec<PrincipalId>,
+    #[schema(value_type = Vec<String>)]
     pub removed: Vec<PrincipalId>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
     pub subnet_id: Option<PrincipalId>,
+    #[schema(value_type = Object)]
     pub score_before: nakamoto::NakamotoScore,
+    #[schema(value_type = Object)]
     pub score_after: nakamoto::NakamotoScore,
     pub motivation: Option<String>,
     pub comment: Option<String>,
     pub run_log: Option<Vec<String>>,
+    #[schema(value_type = Object)]
     pub feature_diff: BTreeMap<NodeFeature, FeatureDiff>,
     pub proposal_id: Option<u64>,
 }
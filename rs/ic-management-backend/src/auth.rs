@@ -0,0 +1,291 @@
+//! Optional authentication of the backend clients, and the audit trail of the
+//! plans they request.
+//!
+//! Without an [`AuthConfig`] every client is allowed to do everything, which is
+//! what the `dre` CLI relies on when it runs the backend on localhost.
+use actix_tls::accept::rustls_0_22::{reexports::ServerConfig, TlsStream};
+use actix_web::dev::{Extensions, ServiceRequest};
+use actix_web::{http::header, FromRequest, HttpMessage, HttpRequest};
+use log::{info, warn};
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::convert::Infallible;
+use std::fs::File;
+use std::future::{ready, Ready};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Endpoints that compute plans for proposals, as opposed to only reading the state.
+const PLANNER_PATHS: &[&str] = &[
+    "/subnet/membership/replace",
+    "/subnet/membership/resize",
+    "/subnet/create",
+    "/nodes/remove",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, strum_macros::Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Role {
+    /// Can query the state of the network
+    ReadOnly,
+    /// Can additionally request plans for proposals
+    Planner,
+}
+
+impl Role {
+    pub fn required_for(path: &str) -> Self {
+        if PLANNER_PATHS.contains(&path) {
+            Role::Planner
+        } else {
+            Role::ReadOnly
+        }
+    }
+}
+
+/// The authenticated client of a request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+}
+
+impl Identity {
+    /// Used when authentication is disabled.
+    pub fn anonymous() -> Self {
+        Self {
+            name: "anonymous".to_string(),
+            role: Role::Planner,
+        }
+    }
+}
+
+impl FromRequest for Identity {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(Ok(req.extensions().get::<Identity>().cloned().unwrap_or_else(Identity::anonymous)))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Credential {
+    name: String,
+    role: Role,
+    /// Hex encoded SHA-256 of the bearer token, or of the DER encoded client certificate
+    sha256: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with the server certificate chain
+    pub cert: PathBuf,
+    /// PEM file with the server private key
+    pub key: PathBuf,
+    /// PEM file with the CAs that client certificates must be signed by. Enables mTLS.
+    pub client_ca: Option<PathBuf>,
+}
+
+/// Who may access the backend, loaded from a YAML file:
+///
+/// ```yaml
+/// tokens:    # Authorization: Bearer <token>, sha256 of `echo -n <token> | sha256sum`
+///   - { name: alice, role: planner, sha256: 2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae }
+/// clients:   # mTLS, sha256 of the DER encoded client certificate
+///   - { name: dashboard, role: read-only, sha256: fcde2b2edba56bf408601fb721fe9b5c338d10ee429ea04fae5511b68fbf8fb9 }
+/// tls:
+///   cert: /etc/backend/tls.crt
+///   key: /etc/backend/tls.key
+///   client_ca: /etc/backend/ca.crt
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    tokens: Vec<Credential>,
+    #[serde(default)]
+    clients: Vec<Credential>,
+    pub tls: Option<TlsConfig>,
+}
+
+/// SHA-256 of the client certificate of a TLS connection.
+#[derive(Clone)]
+struct ClientCertificate(String);
+
+impl AuthConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).map_err(|e| anyhow::anyhow!("Couldn't open auth config {}: {}", path.display(), e))?;
+        let config: Self = serde_yaml::from_reader(file).map_err(|e| anyhow::anyhow!("Invalid auth config {}: {}", path.display(), e))?;
+        if !config.clients.is_empty() && config.tls.as_ref().and_then(|tls| tls.client_ca.as_ref()).is_none() {
+            return Err(anyhow::anyhow!(
+                "Client certificates in {} need `tls.client_ca` to be set",
+                path.display()
+            ));
+        }
+        Ok(config)
+    }
+
+    fn identify(&self, req: &ServiceRequest) -> Option<Identity> {
+        let credential = if let Some(token) = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+        {
+            let hash = sha256_hex(token.trim().as_bytes());
+            self.tokens.iter().find(|c| c.sha256.eq_ignore_ascii_case(&hash))
+        } else if let Some(ClientCertificate(hash)) = req.conn_data::<ClientCertificate>() {
+            self.clients.iter().find(|c| c.sha256.eq_ignore_ascii_case(hash))
+        } else {
+            None
+        };
+        credential.map(|c| Identity {
+            name: c.name.clone(),
+            role: c.role,
+        })
+    }
+
+    /// Finds out who sent the request, and whether they may access the requested path.
+    pub fn authenticate(&self, req: &ServiceRequest) -> Result<Identity, actix_web::Error> {
+        // The percent-decoded path that the router matches, rather than the raw one,
+        // as otherwise `/subnet/%63reate` would be routed to `/subnet/create` unchecked
+        let required = Role::required_for(req.match_info().as_str());
+        match self.identify(req) {
+            Some(identity) if identity.role >= required => Ok(identity),
+            Some(identity) => {
+                warn!(target: "audit", "{} ({}) denied access to {}", identity.name, identity.role, req.path());
+                Err(actix_web::error::ErrorForbidden(format!("{} role required", required)))
+            }
+            None => {
+                warn!(target: "audit", "unauthenticated client {:?} denied access to {}", req.peer_addr(), req.path());
+                Err(actix_web::error::ErrorUnauthorized("missing or unknown credentials"))
+            }
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn server_config(&self) -> anyhow::Result<ServerConfig> {
+        let builder = ServerConfig::builder();
+        let builder = match &self.client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(client_ca)? {
+                    roots.add(cert)?;
+                }
+                // Clients without a certificate can still authenticate with a bearer token
+                builder.with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).allow_unauthenticated().build()?)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&self.key)?))?
+            .ok_or_else(|| anyhow::anyhow!("No private key found in {}", self.key.display()))?;
+        Ok(builder.with_single_cert(load_certs(&self.cert)?, key)?)
+    }
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<rustls::pki_types::CertificateDer<'static>>> {
+    let file = File::open(path).map_err(|e| anyhow::anyhow!("Couldn't open {}: {}", path.display(), e))?;
+    Ok(rustls_pemfile::certs(&mut BufReader::new(file)).collect::<Result<Vec<_>, _>>()?)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Makes the client certificate of a TLS connection available to [`AuthConfig::authenticate`].
+pub fn on_connect(conn: &dyn Any, ext: &mut Extensions) {
+    if let Some(tls) = conn.downcast_ref::<TlsStream<actix_web::rt::net::TcpStream>>() {
+        if let Some(cert) = tls.get_ref().1.peer_certificates().and_then(|certs| certs.first()) {
+            ext.insert(ClientCertificate(sha256_hex(cert)));
+        }
+    }
+}
+
+/// Records who requested which plan.
+pub fn audit<T: Serialize>(identity: &Identity, plan: &str, request: &T) {
+    info!(
+        target: "audit",
+        "{} ({}) requested {}: {}",
+        identity.name,
+        identity.role,
+        plan,
+        serde_json::to_string(request).unwrap_or_default()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{init_service, try_call_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    fn config() -> AuthConfig {
+        serde_yaml::from_str(&format!(
+            r#"
+tokens:
+  - {{ name: alice, role: planner, sha256: {} }}
+  - {{ name: bob, role: read-only, sha256: {} }}
+"#,
+            sha256_hex(b"alice-token"),
+            sha256_hex(b"bob-token"),
+        ))
+        .unwrap()
+    }
+
+    async fn status(token: Option<&str>, path: &str) -> u16 {
+        let config = Arc::new(config());
+        let app = init_service(
+            App::new()
+                .wrap_fn(move |req, srv| {
+                    use actix_web::dev::Service;
+                    let res = config.authenticate(&req).map(|identity| {
+                        req.extensions_mut().insert(identity);
+                        srv.call(req)
+                    });
+                    async move { res?.await }
+                })
+                .default_service(web::to(|identity: Identity| async move { HttpResponse::Ok().body(identity.name) })),
+        )
+        .await;
+        let mut req = TestRequest::post().uri(path);
+        if let Some(token) = token {
+            req = req.insert_header((header::AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        match try_call_service(&app, req.to_request()).await {
+            Ok(res) => res.status().as_u16(),
+            Err(e) => e.as_response_error().status_code().as_u16(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn roles() {
+        assert_eq!(status(None, "/subnets").await, 401);
+        assert_eq!(status(Some("mallory-token"), "/subnets").await, 401);
+        assert_eq!(status(Some("bob-token"), "/subnets").await, 200);
+        assert_eq!(status(Some("bob-token"), "/subnet/create").await, 403);
+        assert_eq!(status(Some("alice-token"), "/subnet/create").await, 200);
+    }
+
+    #[actix_rt::test]
+    async fn percent_encoded_paths() {
+        assert_eq!(status(Some("bob-token"), "/subnet/%63reate").await, 403);
+        assert_eq!(status(Some("bob-token"), "/subnet/%6Dembership/replace").await, 403);
+        assert_eq!(status(Some("alice-token"), "/subnet/%6Dembership/replace").await, 200);
+    }
+
+    #[test]
+    fn client_certificates_need_a_client_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auth.yaml");
+        std::fs::write(&path, "clients:\n  - { name: dashboard, role: read-only, sha256: abcd }\n").unwrap();
+        assert!(AuthConfig::load(&path).is_err());
+    }
+}
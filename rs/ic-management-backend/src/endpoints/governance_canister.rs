@@ -1,6 +1,7 @@
 use super::*;
 use ic_canisters::governance::governance_canister_version;
 
+#[utoipa::path(get, path = "/canisters/governance/version", responses((status = 200, description = "Version of the governance canister")))]
 #[get("/canisters/governance/version")]
 async fn governance_canister_version_endpoint(registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
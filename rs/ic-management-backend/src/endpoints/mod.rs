@@ -1,13 +1,18 @@
 pub mod governance_canister;
 pub mod nodes_ops;
+pub mod openapi;
+pub mod proposals;
 pub mod query_decentralization;
 pub mod release;
 pub mod subnet;
 
+use crate::auth::{self, AuthConfig, Identity};
 use crate::health::HealthStatusQuerier;
+use crate::node_labels::NodeLabelsSource;
+use crate::proposal_history::{self, proposal_history_path, LazyProposalHistory};
 use crate::{health, prometheus, proposal, registry, registry::RegistryState, release::list_subnets_release_statuses, release::RolloutBuilder};
 use actix_web::dev::Service;
-use actix_web::{get, post, web, App, Error, HttpResponse, HttpServer, Responder, Result};
+use actix_web::{get, post, web, App, Error, HttpMessage, HttpResponse, HttpServer, Responder, Result};
 use decentralization::network::AvailableNodesQuerier;
 use ic_management_types::Network;
 use ic_registry_nns_data_provider::registry::RegistryCanister;
@@ -20,9 +25,12 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
+use utoipa::IntoParams;
 
 pub async fn run_backend(
     target_network: &Network,
+    node_labels: NodeLabelsSource,
+    auth: Option<AuthConfig>,
     listen_ip: &str,
     listen_port: u16,
     run_from_cli: bool,
@@ -30,25 +38,43 @@ pub async fn run_backend(
 ) -> std::io::Result<()> {
     debug!("Starting backend");
     let registry_state = Arc::new(RwLock::new(registry::RegistryState::new(target_network, run_from_cli).await));
+    // The backend only lives as long as a `dre` command, so there's no background
+    // sync then. The history is synced when it's first queried instead, after
+    // the registry update, so that the providers of the nodes are known
+    let proposal_history = Arc::new(LazyProposalHistory::new(
+        proposal_history_path(target_network),
+        run_from_cli.then(|| registry_state.clone()),
+    ));
 
     if run_from_cli {
         registry::update_node_details(&registry_state).await;
     } else {
         let closure_target_network = target_network.clone();
         let registry_state_poll = registry_state.clone();
-        tokio::spawn(async { registry::poll(registry_state_poll, closure_target_network).await });
+        tokio::spawn(async { registry::poll(registry_state_poll, closure_target_network, node_labels).await });
+        tokio::spawn(proposal_history::poll(proposal_history.clone(), registry_state.clone()));
     }
 
     let num_workers = if run_from_cli { 1 } else { 8 };
 
+    let tls = auth
+        .as_ref()
+        .and_then(|auth| auth.tls.as_ref())
+        .map(|tls| tls.server_config())
+        .transpose()
+        .map_err(std::io::Error::other)?;
+    let auth = auth.map(Arc::new);
+
     let closure_target_network = target_network.clone();
-    let mut srv = HttpServer::new(move || {
+    let srv = HttpServer::new(move || {
         let network = closure_target_network.clone();
         // For `dre` cli invocations we don't need more than one worker
 
         let middleware_registry_state = registry_state.clone();
+        let auth = auth.clone();
         App::new()
             .app_data(web::Data::new(registry_state.clone()))
+            .app_data(web::Data::new(proposal_history.clone()))
             .wrap_fn(move |req, srv| {
                 let fut = srv.call(req);
                 let registry_state = middleware_registry_state.clone();
@@ -70,34 +96,27 @@ pub async fn run_backend(
                     }
                 }
             })
-            .service(rollout)
-            .service(subnets_release)
-            .service(version)
-            .service(list_subnets)
-            .service(nodes)
-            .service(available_nodes)
-            .service(missing_guests)
-            .service(guests)
-            .service(operators)
-            .service(nodes_healths)
-            .service(get_subnet)
-            .service(self::subnet::pending_action)
-            .service(self::subnet::replace)
-            .service(self::subnet::create_subnet)
-            .service(self::subnet::resize)
-            .service(self::subnet::change_preview)
-            .service(self::nodes_ops::remove)
-            .service(self::query_decentralization::decentralization_subnet_query)
-            .service(self::query_decentralization::decentralization_whatif_query)
-            .service(self::release::releases_list_all)
-            .service(self::release::retireable)
-            .service(self::release::blessed)
-            .service(self::release::get_nns_replica_version)
-            .service(self::governance_canister::governance_canister_version_endpoint)
+            // Registered last so that it runs first
+            .wrap_fn(move |req, srv| {
+                let res = match &auth {
+                    Some(auth) => auth.authenticate(&req),
+                    None => Ok(Identity::anonymous()),
+                }
+                .map(|identity| {
+                    req.extensions_mut().insert(identity);
+                    srv.call(req)
+                });
+                async move { res?.await }
+            })
+            .configure(configure)
     })
+    .on_connect(auth::on_connect)
     .shutdown_timeout(10)
-    .workers(num_workers)
-    .bind((listen_ip, listen_port))
+    .workers(num_workers);
+    let mut srv = match tls {
+        Some(tls) => srv.bind_rustls_0_22((listen_ip, listen_port), tls),
+        None => srv.bind((listen_ip, listen_port)),
+    }
     .unwrap();
 
     if run_from_cli {
@@ -123,8 +142,43 @@ pub async fn run_backend(
     srv.await
 }
 
-#[derive(Deserialize, Serialize)]
+/// Registers all the backend routes. Kept separate from [`run_backend`] so
+/// the routes can be checked against the OpenAPI spec without a registry.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(rollout)
+        .service(subnets_release)
+        .service(version)
+        .service(list_subnets)
+        .service(nodes)
+        .service(available_nodes)
+        .service(missing_guests)
+        .service(guests)
+        .service(operators)
+        .service(node_labels_report)
+        .service(nodes_healths)
+        .service(get_subnet)
+        .service(self::subnet::pending_action)
+        .service(self::subnet::replace)
+        .service(self::subnet::create_subnet)
+        .service(self::subnet::resize)
+        .service(self::subnet::change_preview)
+        .service(self::nodes_ops::remove)
+        .service(self::query_decentralization::decentralization_subnet_query)
+        .service(self::query_decentralization::decentralization_whatif_query)
+        .service(self::release::releases_list_all)
+        .service(self::release::retireable)
+        .service(self::release::blessed)
+        .service(self::release::get_nns_replica_version)
+        .service(self::governance_canister::governance_canister_version_endpoint)
+        .service(self::proposals::history)
+        .service(self::openapi::openapi_json);
+}
+
+#[derive(Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SubnetRequest {
+    /// Principal of the subnet
+    #[param(example = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe")]
     id: String,
 }
 
@@ -134,6 +188,15 @@ pub struct NewSubnet {
     exclusions: Option<Vec<PrincipalId>>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/subnet",
+    params(SubnetRequest),
+    responses(
+        (status = 200, description = "Registry record of the subnet"),
+        (status = 400, description = "The subnet ID is not a valid principal, or there is no such subnet")
+    )
+)]
 #[get("/subnet")]
 async fn get_subnet(
     registry: web::Data<Arc<RwLock<registry::RegistryState>>>,
@@ -156,6 +219,7 @@ async fn get_subnet(
     HttpResponse::Ok().json(record)
 }
 
+#[utoipa::path(get, path = "/rollout", responses((status = 200, description = "Current GuestOS rollout")))]
 #[get("/rollout")]
 async fn rollout(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
@@ -172,6 +236,7 @@ async fn rollout(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> R
     response_from_result(service.build().await)
 }
 
+#[utoipa::path(get, path = "/subnets/versions", responses((status = 200, description = "Release status of each subnet")))]
 #[get("/subnets/versions")]
 async fn subnets_release(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
@@ -190,29 +255,38 @@ async fn subnets_release(registry: web::Data<Arc<RwLock<registry::RegistryState>
     )
 }
 
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses((status = 200, description = "Registry version the backend is synced to", content_type = "application/json", body = u64))
+)]
 #[get("/version")]
 async fn version(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
     query_registry(registry, |r| r.version()).await
 }
 
+#[utoipa::path(get, path = "/subnets", responses((status = 200, description = "All subnets, with their open topology proposals")))]
 #[get("/subnets")]
 async fn list_subnets(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
     let registry = registry.read().await;
     response_from_result(registry.subnets_with_proposals().await)
 }
 
+#[utoipa::path(get, path = "/nodes", responses((status = 200, description = "All nodes, with their open topology proposals")))]
 #[get("/nodes")]
 async fn nodes(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
     response_from_result(registry.nodes_with_proposals().await)
 }
 
+#[utoipa::path(get, path = "/nodes/available", responses((status = 200, description = "Nodes that are not assigned to a subnet")))]
 #[get("/nodes/available")]
 async fn available_nodes(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
     response_from_result(registry.available_nodes().await)
 }
 
+#[utoipa::path(get, path = "/nodes/healths", responses((status = 200, description = "Health status of each node")))]
 #[get("/nodes/healths")]
 async fn nodes_healths(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
@@ -226,21 +300,34 @@ async fn nodes_healths(registry: web::Data<Arc<RwLock<registry::RegistryState>>>
     }))
 }
 
+#[utoipa::path(get, path = "/missing_guests", responses((status = 200, description = "Labeled guests that are not in the registry")))]
 #[get("/missing_guests")]
 async fn missing_guests(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
     query_registry(registry, |r| r.missing_guests()).await
 }
 
+#[utoipa::path(get, path = "/guests", responses((status = 200, description = "Guests from the node labels")))]
 #[get("/guests")]
 async fn guests(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
     query_registry(registry, |r| r.guests()).await
 }
 
+#[utoipa::path(get, path = "/operators", responses((status = 200, description = "All node operators")))]
 #[get("/operators")]
 async fn operators(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
     query_registry(registry, |r| r.operators()).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/node_labels/report",
+    responses((status = 200, description = "Registry nodes without a label, duplicate labels and labels of nodes no longer in the registry"))
+)]
+#[get("/node_labels/report")]
+async fn node_labels_report(registry: web::Data<Arc<RwLock<registry::RegistryState>>>) -> impl Responder {
+    query_registry(registry, |r| r.node_labels_report()).await
+}
+
 fn response_from_result<T: Serialize, E: std::fmt::Debug + std::fmt::Display + 'static>(result: Result<T, E>) -> Result<HttpResponse, Error> {
     match result {
         Ok(data) => Ok(HttpResponse::Ok().json(data)),
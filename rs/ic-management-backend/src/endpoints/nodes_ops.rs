@@ -8,8 +8,20 @@ use decentralization::network::Node as DecentralizationNode;
 
 /// Finds all nodes that need to be removed from the network either because
 /// they're offline or duplicated
+#[utoipa::path(
+    post,
+    path = "/nodes/remove",
+    operation_id = "remove_nodes",
+    request_body = NodesRemoveRequest,
+    responses((status = 200, description = "Nodes planned for removal", body = NodesRemoveResponse))
+)]
 #[post("/nodes/remove")]
-async fn remove(request: web::Json<NodesRemoveRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
+async fn remove(
+    request: web::Json<NodesRemoveRequest>,
+    registry: web::Data<Arc<RwLock<RegistryState>>>,
+    identity: Identity,
+) -> Result<HttpResponse, Error> {
+    auth::audit(&identity, "remove_nodes", &*request);
     let registry = registry.read().await;
     let health_client = health::HealthClient::new(registry.network());
     let nodes_with_proposals = registry.nodes_with_proposals();
@@ -0,0 +1,306 @@
+use super::*;
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "IC Management Backend",
+        description = "Plans topology changes and tracks releases of the Internet Computer"
+    ),
+    paths(
+        super::get_subnet,
+        super::rollout,
+        super::subnets_release,
+        super::version,
+        super::list_subnets,
+        super::nodes,
+        super::available_nodes,
+        super::nodes_healths,
+        super::missing_guests,
+        super::guests,
+        super::operators,
+        super::node_labels_report,
+        super::subnet::pending_action,
+        super::subnet::change_preview,
+        super::subnet::replace,
+        super::subnet::create_subnet,
+        super::subnet::resize,
+        super::nodes_ops::remove,
+        super::query_decentralization::decentralization_subnet_query,
+        super::query_decentralization::decentralization_whatif_query,
+        super::release::releases_list_all,
+        super::release::retireable,
+        super::release::blessed,
+        super::release::get_nns_replica_version,
+        super::governance_canister::governance_canister_version_endpoint,
+        super::proposals::history,
+    ),
+    components(schemas(
+        ic_management_types::Artifact,
+        ic_management_types::MinNakamotoCoefficients,
+        ic_management_types::ProposalHistoryEntry,
+        ic_management_types::ProposalHistoryNode,
+        ic_management_types::ProposalNodeChange,
+        ic_management_types::ProposalStatusTransition,
+        ic_management_types::Release,
+        ic_management_types::Status,
+        ic_management_types::TopologyChangeProposal,
+        ic_management_types::requests::MembershipReplaceRequest,
+        ic_management_types::requests::NodeRemoval,
+        ic_management_types::requests::NodeRemovalReason,
+        ic_management_types::requests::NodesRemoveRequest,
+        ic_management_types::requests::NodesRemoveResponse,
+        ic_management_types::requests::ProposalHistoryQuery,
+        ic_management_types::requests::ReplaceTarget,
+        ic_management_types::requests::SubnetCreateRequest,
+        ic_management_types::requests::SubnetResizeRequest,
+        ic_management_types::requests::SubnetWhatIfRequest,
+        decentralization::SubnetChangeResponse,
+    ))
+)]
+pub struct ApiDoc;
+
+/// OpenAPI spec of the backend. The `dre` CLI generates its backend client
+/// from this spec at build time.
+pub fn spec() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+#[get("/openapi.json")]
+async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(spec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proposal_history::ProposalHistory;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test;
+    use ic_management_types::{ProposalHistoryEntry, ProposalStatusTransition};
+    use ic_registry_local_registry::LocalRegistry;
+    use ic_registry_local_store::{compact_delta_to_changelog, LocalStoreImpl, LocalStoreWriter};
+    use serde_json::Value;
+
+    /// Every operation in the spec must be served by the backend, otherwise
+    /// the generated client calls routes that don't exist. Handlers fail on
+    /// the missing registry state, which is fine: only unrouted requests end
+    /// up as 404 or 405.
+    #[actix_web::test]
+    async fn spec_operations_are_served() {
+        let app = test::init_service(App::new().configure(configure)).await;
+        let spec = serde_json::to_value(spec()).unwrap();
+
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for (method, operation) in item.as_object().unwrap() {
+                let mut uri = path.clone();
+                let mut query = vec![];
+                for param in operation["parameters"].as_array().into_iter().flatten() {
+                    let name = param["name"].as_str().unwrap();
+                    let value = param["example"]
+                        .as_str()
+                        .unwrap_or_else(|| panic!("parameter {name} of {method} {path} needs an example"));
+                    match param["in"].as_str() {
+                        Some("path") => uri = uri.replace(&format!("{{{name}}}"), value),
+                        Some("query") => query.push(format!("{name}={value}")),
+                        _ => {}
+                    }
+                }
+                if !query.is_empty() {
+                    uri = format!("{uri}?{}", query.join("&"));
+                }
+
+                let request = test::TestRequest::default()
+                    .method(Method::from_str(&method.to_uppercase()).unwrap())
+                    .uri(&uri)
+                    .to_request();
+                let response = test::call_service(&app, request).await;
+                assert!(
+                    !matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED),
+                    "{} {} is in the spec but not served by the backend",
+                    method.to_uppercase(),
+                    path
+                );
+            }
+        }
+    }
+
+    /// Mismatches between a value and the parts of JSON schema that utoipa
+    /// generates: references, types, enums, nullability, items and properties.
+    fn schema_mismatches(spec: &Value, schema: &Value, value: &Value, at: &str) -> Vec<String> {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            return schema_mismatches(spec, &spec["components"]["schemas"][name], value, at);
+        }
+        if value.is_null() && schema["nullable"].as_bool() == Some(true) {
+            return vec![];
+        }
+        if let Some(all) = schema["allOf"].as_array() {
+            return all.iter().flat_map(|s| schema_mismatches(spec, s, value, at)).collect();
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(any) = schema[key].as_array() {
+                if any.iter().any(|s| schema_mismatches(spec, s, value, at).is_empty()) {
+                    return vec![];
+                }
+                return vec![format!("{at}: {value} matches none of the {key} schemas")];
+            }
+        }
+
+        let type_matches = match schema["type"].as_str() {
+            Some("object") => value.is_object(),
+            Some("array") => value.is_array(),
+            Some("string") => value.is_string(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        if !type_matches {
+            return vec![format!("{at}: expected {}, got {value}", schema["type"])];
+        }
+        if let Some(variants) = schema["enum"].as_array() {
+            if !variants.contains(value) {
+                return vec![format!("{at}: {value} is not one of {}", schema["enum"])];
+            }
+        }
+
+        let mut mismatches = vec![];
+        if let Some(items) = value.as_array() {
+            for (i, item) in items.iter().enumerate() {
+                mismatches.extend(schema_mismatches(spec, &schema["items"], item, &format!("{at}/{i}")));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for required in schema["required"].as_array().into_iter().flatten().filter_map(|r| r.as_str()) {
+                if !object.contains_key(required) {
+                    mismatches.push(format!("{at}: missing required property {required}"));
+                }
+            }
+            for (key, property) in object {
+                let property_schema = match &schema["properties"][key] {
+                    Value::Null => &schema["additionalProperties"],
+                    property_schema => property_schema,
+                };
+                mismatches.extend(schema_mismatches(spec, property_schema, property, &format!("{at}/{key}")));
+            }
+        }
+        mismatches
+    }
+
+    /// Operations that only need the local registry and the proposal history, so
+    /// that they succeed without network access: method, path in the spec, uri,
+    /// request body and the status they respond with.
+    const OFFLINE_OPERATIONS: &[(&str, &str, &str, &str, StatusCode)] = &[
+        ("get", "/version", "/version", "", StatusCode::OK),
+        ("get", "/releases/all", "/releases/all", "", StatusCode::OK),
+        (
+            "get",
+            "/release/versions/blessed/{release_artifact}",
+            "/release/versions/blessed/guestos",
+            "",
+            StatusCode::OK,
+        ),
+        ("get", "/release/versions/nns", "/release/versions/nns", "", StatusCode::OK),
+        (
+            "get",
+            "/subnet",
+            "/subnet?id=tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe",
+            "",
+            StatusCode::BAD_REQUEST,
+        ),
+        ("post", "/proposals/history", "/proposals/history", "{}", StatusCode::OK),
+    ];
+
+    /// Operations respond with the documented status, and with a body matching
+    /// the documented schema, so that the generated client can decode it.
+    #[actix_web::test]
+    async fn spec_responses_are_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStoreImpl::new(dir.path());
+        let (_, changelog) = compact_delta_to_changelog(ic_registry_local_store_artifacts::MAINNET_DELTA_00_6D_C1).unwrap();
+        for (version, entry) in changelog.into_iter().enumerate() {
+            store.store((version as u64 + 1).into(), entry).unwrap();
+        }
+        let network = Network {
+            name: "mainnet".to_string(),
+            nns_urls: vec![],
+        };
+        let local_registry = Arc::new(LocalRegistry::new(dir.path(), Duration::from_secs(1)).unwrap());
+        let registry = Arc::new(RwLock::new(RegistryState::with_local_registry(&network, local_registry)));
+        let history = ProposalHistory::open_in_memory().unwrap();
+        history
+            .record(&[ProposalHistoryEntry {
+                id: 1,
+                kind: "ChangeSubnetMembership".to_string(),
+                title: None,
+                summary: "Replacing 1 node".to_string(),
+                proposer: Some(40),
+                timestamp_seconds: 100,
+                subnet: Some(PrincipalId::new_subnet_test_id(1)),
+                nodes: vec![],
+                status_transitions: vec![ProposalStatusTransition {
+                    status: "open".to_string(),
+                    timestamp_seconds: 100,
+                }],
+                payload: serde_json::json!({}),
+            }])
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(registry))
+                .app_data(web::Data::new(Arc::new(LazyProposalHistory::opened(history))))
+                .configure(configure),
+        )
+        .await;
+        let spec = serde_json::to_value(spec()).unwrap();
+
+        for (method, path, uri, body, expected) in OFFLINE_OPERATIONS {
+            let operation = &spec["paths"][*path][*method];
+            assert!(operation.is_object(), "{} {} is not in the spec", method.to_uppercase(), path);
+
+            let mut request = test::TestRequest::default()
+                .method(Method::from_str(&method.to_uppercase()).unwrap())
+                .uri(uri);
+            if !body.is_empty() {
+                request = request.insert_header(("content-type", "application/json")).set_payload(*body);
+            }
+            let response = test::call_service(&app, request.to_request()).await;
+            let status = response.status();
+            let body: Value = serde_json::from_slice(&test::read_body(response).await).unwrap_or(Value::Null);
+
+            assert_eq!(status, *expected, "{} {}: {}", method.to_uppercase(), uri, body);
+            let documented = &operation["responses"][status.as_str()];
+            assert!(
+                documented.is_object(),
+                "{} {} responds with {} which is not in the spec",
+                method.to_uppercase(),
+                path,
+                status
+            );
+            let schema = &documented["content"]["application/json"]["schema"];
+            if !schema.is_null() {
+                let mismatches = schema_mismatches(&spec, schema, &body, "");
+                assert!(mismatches.is_empty(), "{} {}: {:?}", method.to_uppercase(), path, mismatches);
+            }
+        }
+    }
+
+    #[test]
+    fn spec_operation_ids_are_unique() {
+        let spec = serde_json::to_value(spec()).unwrap();
+        let operation_ids = spec["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .flat_map(|item| item.as_object().unwrap().values())
+            .map(|operation| operation["operationId"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        let unique = operation_ids.iter().collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(
+            operation_ids.len(),
+            unique.len(),
+            "operation ids are client method names and must be unique"
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use super::*;
+use crate::proposal_history::LazyProposalHistory;
+use ic_management_types::requests::ProposalHistoryQuery;
+
+/// Proposals recorded in the proposal history, newest first. The history is
+/// synced with the governance canister in the background, or on the first
+/// request when the backend runs for a `dre` command.
+#[utoipa::path(
+    post,
+    path = "/proposals/history",
+    operation_id = "proposal_history",
+    request_body = ProposalHistoryQuery,
+    responses(
+        (status = 200, description = "Recorded proposals matching the query", body = Vec<ProposalHistoryEntry>),
+        (status = 500, description = "The proposal history couldn't be read")
+    )
+)]
+#[post("/proposals/history")]
+async fn history(request: web::Json<ProposalHistoryQuery>, history: web::Data<Arc<LazyProposalHistory>>) -> Result<HttpResponse, Error> {
+    match history.get().await {
+        Some(history) => response_from_result(history.query(&request)),
+        None => Err(actix_web::error::ErrorInternalServerError("The proposal history isn't available")),
+    }
+}
@@ -2,6 +2,7 @@ use super::*;
 use decentralization::network::{DecentralizedSubnet, SubnetChange};
 use decentralization::SubnetChangeResponse;
 use ic_base_types::PrincipalId;
+use ic_management_types::requests::SubnetWhatIfRequest;
 use ic_management_types::MinNakamotoCoefficients;
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +20,12 @@ struct DecentralizedSubnetResponse {
 }
 
 /// Get the decentralization coefficients for a subnet
+#[utoipa::path(
+    get,
+    path = "/decentralization/subnet/{subnet}",
+    params(("subnet" = String, Path, description = "Principal of the subnet", example = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe")),
+    responses((status = 200, description = "Decentralization analysis of the subnet"))
+)]
 #[get("/decentralization/subnet/{subnet}")]
 async fn decentralization_subnet_query(
     request: web::Path<SubnetRequest>,
@@ -27,15 +34,13 @@ async fn decentralization_subnet_query(
     get_decentralization_analysis(registry, Some(request.subnet), None, None, None).await
 }
 
-#[derive(Deserialize)]
-struct SubnetWhatIfRequest {
-    subnet: Option<PrincipalId>,
-    nodes_to_add: Option<Vec<PrincipalId>>,
-    nodes_to_remove: Option<Vec<PrincipalId>>,
-    min_nakamoto_coefficients: Option<MinNakamotoCoefficients>,
-}
-
 /// Get the decentralization coefficients for a subnet
+#[utoipa::path(
+    get,
+    path = "/decentralization/whatif",
+    request_body = SubnetWhatIfRequest,
+    responses((status = 200, description = "Decentralization analysis of the changed subnet"))
+)]
 #[get("/decentralization/whatif")]
 async fn decentralization_whatif_query(
     request: web::Json<SubnetWhatIfRequest>,
@@ -7,24 +7,44 @@ struct ReleaseRequest {
     release_artifact: Artifact,
 }
 
+#[utoipa::path(get, path = "/releases/all", responses((status = 200, description = "All known GuestOS releases", body = Vec<Release>)))]
 #[get("/releases/all")]
 async fn releases_list_all(registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
     Ok(HttpResponse::Ok().json(registry.replica_releases()))
 }
 
+#[utoipa::path(
+    get,
+    path = "/release/retireable/{release_artifact}",
+    operation_id = "get_retireable_versions",
+    params(("release_artifact" = Artifact, Path, description = "Release artifact", example = "guestos")),
+    responses((status = 200, description = "Elected versions that are safe to retire", body = Vec<Release>))
+)]
 #[get("/release/retireable/{release_artifact}")]
 async fn retireable(request: web::Path<ReleaseRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
     response_from_result(registry.retireable_versions(&request.release_artifact).await)
 }
 
-#[get("release/versions/blessed/{release_artifact}")]
+#[utoipa::path(
+    get,
+    path = "/release/versions/blessed/{release_artifact}",
+    operation_id = "get_blessed_versions",
+    params(("release_artifact" = Artifact, Path, description = "Release artifact", example = "guestos")),
+    responses((status = 200, description = "Elected versions", body = Vec<String>))
+)]
+#[get("/release/versions/blessed/{release_artifact}")]
 async fn blessed(request: web::Path<ReleaseRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
     response_from_result(registry.blessed_versions(&request.release_artifact).await)
 }
 
+#[utoipa::path(
+    get,
+    path = "/release/versions/nns",
+    responses((status = 200, description = "GuestOS version of the NNS subnet", body = Option<String>))
+)]
 #[get("/release/versions/nns")]
 async fn get_nns_replica_version(registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     let registry = registry.read().await;
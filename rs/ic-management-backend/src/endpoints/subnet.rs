@@ -14,6 +14,13 @@ struct SubnetRequest {
     subnet: PrincipalId,
 }
 
+#[utoipa::path(
+    get,
+    path = "/subnet/{subnet}/pending_action",
+    operation_id = "subnet_pending_action",
+    params(("subnet" = String, Path, description = "Principal of the subnet", example = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe")),
+    responses((status = 200, description = "Open topology proposal for the subnet, if any", body = Option<TopologyChangeProposal>))
+)]
 #[get("/subnet/{subnet}/pending_action")]
 async fn pending_action(request: web::Path<SubnetRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     match registry.read().await.subnets_with_proposals().await {
@@ -31,6 +38,12 @@ async fn pending_action(request: web::Path<SubnetRequest>, registry: web::Data<A
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/subnet/{subnet}/change_preview",
+    params(("subnet" = String, Path, description = "Principal of the subnet", example = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe")),
+    responses((status = 200, description = "Changes the open topology proposal would make to the subnet"))
+)]
 #[get("/subnet/{subnet}/change_preview")]
 async fn change_preview(request: web::Path<SubnetRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
     match registry.read().await.subnets_with_proposals().await {
@@ -56,8 +69,20 @@ async fn change_preview(request: web::Path<SubnetRequest>, registry: web::Data<A
 /// Principals.
 ///
 /// All nodes in the request must belong to exactly one subnet.
+#[utoipa::path(
+    post,
+    path = "/subnet/membership/replace",
+    operation_id = "membership_replace",
+    request_body = MembershipReplaceRequest,
+    responses((status = 200, description = "Planned subnet membership change", body = SubnetChangeResponse))
+)]
 #[post("/subnet/membership/replace")]
-async fn replace(request: web::Json<MembershipReplaceRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
+async fn replace(
+    request: web::Json<MembershipReplaceRequest>,
+    registry: web::Data<Arc<RwLock<RegistryState>>>,
+    identity: Identity,
+) -> Result<HttpResponse, Error> {
+    auth::audit(&identity, "membership_replace", &*request);
     let registry = registry.read().await;
     let all_nodes = registry.nodes();
 
@@ -156,8 +181,20 @@ async fn replace(request: web::Json<MembershipReplaceRequest>, registry: web::Da
 }
 
 /// Simulates creation of a new subnet
+#[utoipa::path(
+    post,
+    path = "/subnet/create",
+    operation_id = "subnet_create",
+    request_body = SubnetCreateRequest,
+    responses((status = 200, description = "Nodes planned for the new subnet", body = SubnetChangeResponse))
+)]
 #[post("/subnet/create")]
-async fn create_subnet(registry: web::Data<Arc<RwLock<RegistryState>>>, request: web::Json<SubnetCreateRequest>) -> Result<HttpResponse, Error> {
+async fn create_subnet(
+    registry: web::Data<Arc<RwLock<RegistryState>>>,
+    request: web::Json<SubnetCreateRequest>,
+    identity: Identity,
+) -> Result<HttpResponse, Error> {
+    auth::audit(&identity, "subnet_create", &*request);
     let registry = registry.read().await;
     println!(
         "Received a request to create a subnet of size {:?} and MinNakamotoCoefficients {}",
@@ -179,8 +216,20 @@ async fn create_subnet(registry: web::Data<Arc<RwLock<RegistryState>>>, request:
 }
 
 /// Simulates resizing the subnet, i.e. adding or removing nodes to a subnet.
+#[utoipa::path(
+    post,
+    path = "/subnet/membership/resize",
+    operation_id = "subnet_resize",
+    request_body = SubnetResizeRequest,
+    responses((status = 200, description = "Planned subnet membership change", body = SubnetChangeResponse))
+)]
 #[post("/subnet/membership/resize")]
-async fn resize(request: web::Json<SubnetResizeRequest>, registry: web::Data<Arc<RwLock<RegistryState>>>) -> Result<HttpResponse, Error> {
+async fn resize(
+    request: web::Json<SubnetResizeRequest>,
+    registry: web::Data<Arc<RwLock<RegistryState>>>,
+    identity: Identity,
+) -> Result<HttpResponse, Error> {
+    auth::audit(&identity, "subnet_resize", &*request);
     let registry = registry.read().await;
 
     let change = registry
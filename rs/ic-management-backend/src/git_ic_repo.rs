@@ -1,8 +1,14 @@
 use custom_error::custom_error;
 use fs2::FileExt;
-use log::{info, warn};
+use itertools::Itertools;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
 use std::path::PathBuf;
 use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use std::{collections::HashMap, fs::File};
 
 custom_error! {IoError
@@ -12,16 +18,95 @@ custom_error! {IoError
     } = @{format!("{path}: {source}", source=source, path=path.display())},
 }
 
+/// URL of the IC repo that is cloned when no other release source is configured.
+pub const DEFAULT_IC_REPO_URL: &str = "https://github.com/dfinity/ic";
+
+/// How often the IC repo is fetched on refresh, to find the new release branches of
+/// commits that are already known. Commits that are missing are fetched right away.
+const FETCH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Maps a git commit to the release branches that contain it.
+pub trait CommitBranches: Send + Sync {
+    fn get_branches_with_commit(&mut self, commit_sha: &str) -> anyhow::Result<Vec<String>>;
+
+    /// Picks up the branches created since the last refresh, before looking up the
+    /// branches of the current releases.
+    fn refresh(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the releases (the branches containing a blessed commit) are discovered from.
+///
+/// Read from the `IC_RELEASE_SOURCE` environment variable:
+///  * `http://...`, `https://...` or `git@...`: a remote IC repo, cloned to the cache dir (default)
+///  * a path to a `.yaml` file: a release index such as the `release-index.yaml` of this repo
+///  * any other path: a pre-existing local clone or bare mirror of the IC repo, used in place.
+///    It is only fetched from if it has an `origin` remote
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReleaseSource {
+    Remote(String),
+    Local(PathBuf),
+    Index(PathBuf),
+}
+
+impl Default for ReleaseSource {
+    fn default() -> Self {
+        Self::Remote(DEFAULT_IC_REPO_URL.to_string())
+    }
+}
+
+impl FromStr for ReleaseSource {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(if s.starts_with("http://") || s.starts_with("https://") || s.starts_with("git@") {
+            Self::Remote(s.to_string())
+        } else if s.ends_with(".yaml") || s.ends_with(".yml") {
+            Self::Index(PathBuf::from(s))
+        } else {
+            Self::Local(PathBuf::from(s))
+        })
+    }
+}
+
+impl ReleaseSource {
+    pub fn from_env() -> Self {
+        match std::env::var("IC_RELEASE_SOURCE") {
+            Ok(source) => Self::from_str(&source).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn open(&self) -> anyhow::Result<Box<dyn CommitBranches>> {
+        info!("Discovering releases from {:?}", self);
+        Ok(match self {
+            Self::Remote(url) => Box::new(IcRepo::with_remote(url)?),
+            Self::Local(path) => Box::new(IcRepo::with_local(path.clone())?),
+            Self::Index(path) => Box::new(ReleaseIndex::new(path.clone())),
+        })
+    }
+}
+
+/// The branches of the commits looked up so far, valid as long as the refs don't change.
+#[derive(Default, Deserialize, Serialize)]
+struct CommitBranchCache {
+    /// SHA-256 of the refs the branches were looked up in
+    refs: String,
+    branches: HashMap<String, Vec<String>>,
+}
+
 // Define the IcRepo struct
 pub struct IcRepo {
     repo_path: PathBuf,
+    git_dir: PathBuf,
     cache_file_path: PathBuf,
-    cache: HashMap<String, Vec<String>>,
+    cache: CommitBranchCache,
 }
 
 impl IcRepo {
-    // Initialize the IcRepo struct, to work with a local clone of the IC repo
-    pub fn new() -> anyhow::Result<Self> {
+    // Initialize the IcRepo struct, to work with a local clone of the given remote in the cache dir
+    pub fn with_remote(url: &str) -> anyhow::Result<Self> {
         let repo_path: PathBuf = match std::env::var("REPO_CACHE_PATH") {
             Ok(path) => PathBuf::from(path),
             Err(_) => match dirs::cache_dir() {
@@ -47,12 +132,6 @@ impl IcRepo {
         })?;
         lock_file.lock_exclusive()?;
 
-        let mut repo = Self {
-            repo_path: repo_path.clone(),
-            cache_file_path: repo_path.join(".git/commit_branch_cache.json"),
-            cache: HashMap::new(),
-        };
-
         if repo_path.exists() {
             // If the directory exists, but git status does not return success, remove the
             // directory
@@ -69,25 +148,80 @@ impl IcRepo {
 
         if !repo_path.exists() {
             info!("Repo {} does not exist, cloning", &repo_path.to_str().unwrap());
-            Command::new("git")
-                .args(["clone", "https://github.com/dfinity/ic", repo_path.to_str().unwrap()])
-                .status()?;
+            Command::new("git").args(["clone", url, repo_path.to_str().unwrap()]).status()?;
         }
 
         lock_file.unlock()?;
 
+        Self::with_local(repo_path)
+    }
+
+    // Work with a pre-existing local clone or bare mirror of the IC repo, without cloning
+    pub fn with_local(repo_path: PathBuf) -> anyhow::Result<Self> {
+        let output = Command::new("git")
+            .args(["-C", repo_path.to_str().unwrap(), "rev-parse", "--absolute-git-dir"])
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("{} is not a git repository", repo_path.display()));
+        }
+        let git_dir = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+
+        let mut repo = Self {
+            repo_path,
+            cache_file_path: git_dir.join("commit_branch_cache.json"),
+            git_dir,
+            cache: CommitBranchCache::default(),
+        };
         repo.load_commit_branch_cache()?;
+        repo.expire_commit_branch_cache()?;
 
         Ok(repo)
     }
 
+    fn git(&self, args: &[&str]) -> anyhow::Result<std::process::Output> {
+        Ok(Command::new("git").arg("-C").arg(&self.repo_path).args(args).output()?)
+    }
+
+    fn has_commit(&self, commit_sha: &str) -> bool {
+        self.git(&["cat-file", "-e", &format!("{}^{{commit}}", commit_sha)])
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    // Only fetches the refs that changed since the last fetch. Failing to fetch is not an
+    // error, so that the repo can be used in environments without network access.
     fn refetch(&self) -> anyhow::Result<()> {
-        Command::new("git")
-            .args(["-C", self.repo_path.to_str().unwrap(), "pull", "--force", "origin"])
-            .output()?;
+        if !self.git(&["remote", "get-url", "origin"])?.status.success() {
+            debug!("Repo {} has no origin remote, not fetching", self.repo_path.display());
+            return Ok(());
+        }
+        let output = self.git(&["fetch", "--prune", "origin"])?;
+        if !output.status.success() {
+            warn!(
+                "Failed to fetch {}, using the local refs: {}",
+                self.repo_path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
         Ok(())
     }
 
+    // Git writes FETCH_HEAD on every fetch, so unlike an in-memory timestamp this also
+    // spares the fetch to the short-lived backends of `dre` commands
+    fn fetched_recently(&self) -> bool {
+        std::fs::metadata(self.git_dir.join("FETCH_HEAD"))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed < FETCH_INTERVAL)
+    }
+
+    // Remote branches of a regular clone, local branches of a bare mirror
+    fn refs(&self) -> anyhow::Result<String> {
+        let output = self.git(&["for-each-ref", "--format=%(objectname) %(refname)", "refs/remotes/origin", "refs/heads"])?;
+        Ok(hex::encode(Sha256::digest(output.stdout)))
+    }
+
     fn load_commit_branch_cache(&mut self) -> anyhow::Result<()> {
         // Check if there is a cache file with the git rev --> branch mapping
         if self.cache_file_path.exists() {
@@ -95,8 +229,26 @@ impl IcRepo {
                 source: e,
                 path: self.cache_file_path.to_path_buf(),
             })?;
-            let cache: HashMap<String, Vec<String>> = serde_json::from_reader(cache_file).map_err(|e| anyhow::format_err!(e))?;
-            self.cache = cache;
+            // A cache in an older format is rebuilt
+            match serde_json::from_reader(cache_file) {
+                Ok(cache) => self.cache = cache,
+                Err(e) => debug!("Ignoring the commit branch cache {}: {}", self.cache_file_path.display(), e),
+            }
+        }
+        Ok(())
+    }
+
+    // Branches may have been created or deleted since the branches were looked up, by
+    // our fetches or by whoever maintains a local mirror
+    fn expire_commit_branch_cache(&mut self) -> anyhow::Result<()> {
+        let refs = self.refs()?;
+        if self.cache.refs != refs {
+            debug!("Refs of {} changed, expiring the commit branch cache", self.repo_path.display());
+            self.cache = CommitBranchCache {
+                refs,
+                branches: HashMap::new(),
+            };
+            self.save_commit_branch_cache()?;
         }
         Ok(())
     }
@@ -111,32 +263,38 @@ impl IcRepo {
     }
 
     pub fn get_branches_with_commit(&mut self, commit_sha: &str) -> anyhow::Result<Vec<String>> {
-        let branches = match self.cache.get(commit_sha) {
+        let branches = match self.cache.branches.get(commit_sha) {
             Some(branches) => branches.clone(),
             None => {
-                self.refetch()?;
-                let output = Command::new("git")
-                    .args([
-                        "-C",
-                        self.repo_path.to_str().unwrap(),
-                        "branch",
-                        "--remote",
-                        "--color=never",
-                        "--format=%(refname)",
-                        "--contains",
-                        commit_sha,
-                    ])
-                    .output()?;
+                if !self.has_commit(commit_sha) {
+                    self.refetch()?;
+                    self.expire_commit_branch_cache()?;
+                }
+                // Remote branches of a regular clone, local branches of a bare mirror
+                let output = self.git(&[
+                    "for-each-ref",
+                    "--format=%(refname)",
+                    "--contains",
+                    commit_sha,
+                    "refs/remotes/origin",
+                    "refs/heads",
+                ])?;
 
                 let branches: Vec<String> = String::from_utf8(output.stdout)?
                     .lines()
-                    .map(|s| s.trim().trim_start_matches("refs/remotes/origin/").to_string())
+                    .map(|s| {
+                        s.trim()
+                            .trim_start_matches("refs/remotes/origin/")
+                            .trim_start_matches("refs/heads/")
+                            .to_string()
+                    })
+                    .unique()
                     .collect();
 
                 if branches.is_empty() {
                     warn!("No branches found for commit {} -- do you have a full repo clone?", commit_sha)
                 } else {
-                    self.cache.insert(commit_sha.to_string(), branches.clone());
+                    self.cache.branches.insert(commit_sha.to_string(), branches.clone());
                     self.save_commit_branch_cache()?;
                 }
                 branches
@@ -146,6 +304,99 @@ impl IcRepo {
     }
 }
 
+impl CommitBranches for IcRepo {
+    fn get_branches_with_commit(&mut self, commit_sha: &str) -> anyhow::Result<Vec<String>> {
+        IcRepo::get_branches_with_commit(self, commit_sha)
+    }
+
+    fn refresh(&mut self) -> anyhow::Result<()> {
+        if !self.fetched_recently() {
+            self.refetch()?;
+        }
+        self.expire_commit_branch_cache()
+    }
+}
+
+#[derive(Deserialize)]
+struct ReleaseIndexFile {
+    releases: Vec<ReleaseIndexEntry>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseIndexEntry {
+    rc_name: String,
+    versions: Vec<ReleaseIndexVersion>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseIndexVersion {
+    name: String,
+    version: String,
+}
+
+/// Releases listed in a `release-index.yaml` file. Needs neither the IC repo nor
+/// network access. The file is re-read only when its modification time changes.
+pub struct ReleaseIndex {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    branches: HashMap<String, Vec<String>>,
+}
+
+impl ReleaseIndex {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            branches: HashMap::new(),
+        }
+    }
+
+    // The `base` version is built from the `rc_name` branch, the other versions from
+    // the `<rc_name>-<name>` branches
+    fn parse(content: &str) -> anyhow::Result<HashMap<String, Vec<String>>> {
+        let index: ReleaseIndexFile = serde_yaml::from_str(content)?;
+        let mut branches: HashMap<String, Vec<String>> = HashMap::new();
+        for release in index.releases {
+            for version in release.versions {
+                let branch = if version.name == "base" {
+                    release.rc_name.clone()
+                } else {
+                    format!("{}-{}", release.rc_name, version.name)
+                };
+                branches.entry(version.version).or_default().push(branch);
+            }
+        }
+        Ok(branches)
+    }
+
+    fn reload(&mut self) -> anyhow::Result<()> {
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).map_err(|e| IoError::Io {
+            source: e,
+            path: self.path.clone(),
+        })?;
+        if self.modified != Some(modified) {
+            let content = std::fs::read_to_string(&self.path).map_err(|e| IoError::Io {
+                source: e,
+                path: self.path.clone(),
+            })?;
+            self.branches = Self::parse(&content).map_err(|e| anyhow::anyhow!("Invalid release index {}: {}", self.path.display(), e))?;
+            self.modified = Some(modified);
+        }
+        Ok(())
+    }
+}
+
+impl CommitBranches for ReleaseIndex {
+    fn get_branches_with_commit(&mut self, commit_sha: &str) -> anyhow::Result<Vec<String>> {
+        self.reload()?;
+        let branches = self.branches.get(commit_sha).cloned().unwrap_or_default();
+        if branches.is_empty() {
+            warn!("Commit {} is not in the release index {}", commit_sha, self.path.display())
+        }
+        Ok(branches)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,9 +405,173 @@ mod tests {
     #[test]
     fn test_get_branches_with_nonexistent_commit() {
         // TODO: mock the git repo so we don't have to clone the whole thing
-        let mut ic_repo = IcRepo::new().unwrap();
+        let mut ic_repo = IcRepo::with_remote(DEFAULT_IC_REPO_URL).unwrap();
         let branches = ic_repo.get_branches_with_commit("80a6745673a28ee53d257b3fe19dcd6b7efa93d1");
         assert!(branches.is_ok());
         assert!(!branches.unwrap().is_empty());
     }
+
+    #[test]
+    fn release_source_from_str() {
+        assert_eq!(ReleaseSource::from_str(DEFAULT_IC_REPO_URL).unwrap(), ReleaseSource::default());
+        assert_eq!(
+            ReleaseSource::from_str("git@github.com:dfinity/ic.git").unwrap(),
+            ReleaseSource::Remote("git@github.com:dfinity/ic.git".to_string())
+        );
+        assert_eq!(
+            ReleaseSource::from_str("/srv/dre/release-index.yaml").unwrap(),
+            ReleaseSource::Index(PathBuf::from("/srv/dre/release-index.yaml"))
+        );
+        assert_eq!(
+            ReleaseSource::from_str("/srv/ic.git").unwrap(),
+            ReleaseSource::Local(PathBuf::from("/srv/ic.git"))
+        );
+    }
+
+    #[test]
+    fn release_index_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("release-index.yaml");
+        std::fs::write(
+            &path,
+            r#"
+rollout:
+  stages: []
+releases:
+  - rc_name: rc--2024-05-15_23-02
+    versions:
+      - name: base
+        version: 5ba1412f9175d987661ae3c0d8dbd1ac3e092b7d
+      - name: storage-layer
+        version: b6b2ef469bb00d38b48b789cae91251f27011b82
+"#,
+        )
+        .unwrap();
+
+        let mut index = ReleaseIndex::new(path.clone());
+        assert_eq!(
+            index.get_branches_with_commit("5ba1412f9175d987661ae3c0d8dbd1ac3e092b7d").unwrap(),
+            vec!["rc--2024-05-15_23-02"]
+        );
+        assert_eq!(
+            index.get_branches_with_commit("b6b2ef469bb00d38b48b789cae91251f27011b82").unwrap(),
+            vec!["rc--2024-05-15_23-02-storage-layer"]
+        );
+        assert!(index
+            .get_branches_with_commit("80a6745673a28ee53d257b3fe19dcd6b7efa93d1")
+            .unwrap()
+            .is_empty());
+
+        std::fs::write(
+            &path,
+            r#"
+releases:
+  - rc_name: rc--2024-05-22_23-01
+    versions:
+      - name: base
+        version: 80a6745673a28ee53d257b3fe19dcd6b7efa93d1
+"#,
+        )
+        .unwrap();
+        // Make sure the modification time changes even on filesystems with a coarse resolution
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(
+            index.get_branches_with_commit("80a6745673a28ee53d257b3fe19dcd6b7efa93d1").unwrap(),
+            vec!["rc--2024-05-22_23-01"]
+        );
+    }
+
+    #[test]
+    fn test_get_branches_from_local_bare_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        let bare = dir.path().join("ic.git");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=dre", "-c", "user.email=dre@localhost"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {} failed", args.join(" "));
+        };
+        let work_str = work.to_str().unwrap();
+        git(&["init", "--quiet", work_str]);
+        git(&["-C", work_str, "commit", "--quiet", "--allow-empty", "-m", "release"]);
+        git(&["-C", work_str, "branch", "rc--2024-05-15_23-02"]);
+        git(&["clone", "--quiet", "--bare", work_str, bare.to_str().unwrap()]);
+        // Without a remote the repo must be usable as is
+        git(&["-C", bare.to_str().unwrap(), "remote", "remove", "origin"]);
+
+        let commit = String::from_utf8(Command::new("git").args(["-C", work_str, "rev-parse", "HEAD"]).output().unwrap().stdout).unwrap();
+        let mut ic_repo = IcRepo::with_local(bare.clone()).unwrap();
+        let branches = ic_repo.get_branches_with_commit(commit.trim()).unwrap();
+        assert!(branches.contains(&"rc--2024-05-15_23-02".to_string()), "{:?}", branches);
+        assert!(bare.join("commit_branch_cache.json").exists());
+
+        // Branches created after the lookup are found once the repo is refreshed
+        git(&["-C", bare.to_str().unwrap(), "branch", "rc--2024-05-22_23-01", commit.trim()]);
+        ic_repo.refresh().unwrap();
+        let branches = ic_repo.get_branches_with_commit(commit.trim()).unwrap();
+        assert!(branches.contains(&"rc--2024-05-22_23-01".to_string()), "{:?}", branches);
+        let mut reopened = IcRepo::with_local(bare).unwrap();
+        assert_eq!(reopened.get_branches_with_commit(commit.trim()).unwrap(), branches);
+    }
+
+    #[test]
+    fn fetches_missing_commits_and_on_the_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        let clone = dir.path().join("clone");
+        let (origin_str, clone_str) = (origin.to_str().unwrap(), clone.to_str().unwrap());
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(["-c", "user.name=dre", "-c", "user.email=dre@localhost"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {} failed", args.join(" "));
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        git(&["init", "--quiet", origin_str]);
+        git(&["-C", origin_str, "commit", "--quiet", "--allow-empty", "-m", "release"]);
+        git(&["-C", origin_str, "branch", "rc--2024-05-15_23-02"]);
+        git(&["clone", "--quiet", origin_str, clone_str]);
+        let commit = git(&["-C", origin_str, "rev-parse", "HEAD"]);
+
+        let mut ic_repo = IcRepo::with_local(clone.clone()).unwrap();
+        ic_repo.refresh().unwrap();
+        assert!(clone.join(".git").join("FETCH_HEAD").exists());
+
+        // Not fetched again within the interval
+        git(&["-C", origin_str, "branch", "rc--2024-05-22_23-01"]);
+        ic_repo.refresh().unwrap();
+        let branches = ic_repo.get_branches_with_commit(&commit).unwrap();
+        assert!(branches.contains(&"rc--2024-05-15_23-02".to_string()), "{:?}", branches);
+        assert!(!branches.contains(&"rc--2024-05-22_23-01".to_string()), "{:?}", branches);
+
+        // Unless a commit is missing
+        git(&["-C", origin_str, "checkout", "--quiet", "-b", "rc--2024-05-29_23-02"]);
+        git(&["-C", origin_str, "commit", "--quiet", "--allow-empty", "-m", "next release"]);
+        let next_commit = git(&["-C", origin_str, "rev-parse", "HEAD"]);
+        assert_eq!(ic_repo.get_branches_with_commit(&next_commit).unwrap(), vec!["rc--2024-05-29_23-02"]);
+
+        // Or the interval passed
+        File::options()
+            .write(true)
+            .open(clone.join(".git").join("FETCH_HEAD"))
+            .unwrap()
+            .set_modified(SystemTime::now() - FETCH_INTERVAL)
+            .unwrap();
+        git(&["-C", origin_str, "branch", "rc--2024-06-05_23-01", &commit]);
+        ic_repo.refresh().unwrap();
+        assert!(ic_repo
+            .get_branches_with_commit(&commit)
+            .unwrap()
+            .contains(&"rc--2024-06-05_23-01".to_string()));
+    }
 }
@@ -1,9 +1,11 @@
+pub mod auth;
 pub mod endpoints;
 pub mod git_ic_repo;
 pub mod health;
 pub mod node_labels;
 pub mod prometheus;
 pub mod proposal;
+pub mod proposal_history;
 pub mod public_dashboard;
 pub mod registry;
 pub mod release;
@@ -1,9 +1,11 @@
+mod auth;
 mod endpoints;
 mod git_ic_repo;
 mod health;
 mod node_labels;
 mod prometheus;
 mod proposal;
+mod proposal_history;
 mod public_dashboard;
 mod registry;
 mod release;
@@ -11,6 +13,8 @@ mod subnets;
 
 use clap::Parser;
 use dotenv::dotenv;
+use node_labels::{NodeLabelsSource, DEFAULT_NODE_LABELS_URL};
+use std::path::PathBuf;
 use url::Url;
 
 #[actix_web::main]
@@ -26,7 +30,10 @@ async fn main() -> std::io::Result<()> {
     let listen_port = std::env::var("BACKEND_PORT")
         .map(|p| p.parse().expect("Unable to parse BACKEND_PORT environment variable as a valid port"))
         .unwrap_or(8080);
-    endpoints::run_backend(&target_network, "0.0.0.0", listen_port, false, None).await
+    let auth = args
+        .auth_config
+        .map(|path| auth::AuthConfig::load(&path).expect("Failed to load the auth config"));
+    endpoints::run_backend(&target_network, args.node_labels, auth, "0.0.0.0", listen_port, false, None).await
 }
 
 #[derive(Parser, Debug)]
@@ -40,4 +47,15 @@ struct Cli {
     // The argument is mandatory for testnets, and is optional for mainnet and staging
     #[clap(long, env = "NNS_URLS", aliases = &["registry-url", "nns-url"], value_delimiter = ',')]
     pub nns_urls: Vec<Url>,
+
+    // Source of the node labels: a local file or directory with "<network>.yaml" files,
+    // a git repo as "git+<path or url>[#<ref>]", or an http(s) URL where "{network}" is
+    // replaced with the network name
+    #[clap(long, env = "NODE_LABELS", default_value = DEFAULT_NODE_LABELS_URL)]
+    node_labels: NodeLabelsSource,
+
+    // YAML file with the bearer tokens and client certificates that may access the backend,
+    // and their roles. Without it, everyone can access all the endpoints
+    #[clap(long, env = "AUTH_CONFIG")]
+    auth_config: Option<PathBuf>,
 }
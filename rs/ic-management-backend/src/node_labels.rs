@@ -1,51 +1,405 @@
 use ic_management_types::Guest;
+use ic_types::PrincipalId;
+use itertools::Itertools;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::net::Ipv6Addr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::process::Command;
 
 use crate::registry::DFINITY_DCS;
 
-pub async fn query_guests(network: &String) -> anyhow::Result<Vec<Guest>> {
-    let file_path = format!("node-labels/{}.yaml", network);
-    let mut content = octocrab::instance()
-        .repos("dfinity", "dre")
-        .get_content()
-        .path(&file_path)
-        .r#ref("main")
-        .send()
-        .await?;
-
-    let items = content.take_items();
-
-    let data = match items.first() {
-        Some(f) => match f.decoded_content() {
-            Some(c) => match serde_yaml::from_str::<serde_yaml::Value>(&c)? {
-                serde_yaml::Value::Mapping(c) => c,
-                _ => return Err(anyhow::anyhow!("Failed to parse file: {}", file_path)),
-            },
-            None => return Err(anyhow::anyhow!("Couldn't decode file: {}", file_path)),
-        },
-        None => return Err(anyhow::anyhow!("File not found on path: {}", file_path)),
-    };
-    let data = match data.get("data") {
-        Some(serde_yaml::Value::Mapping(c)) => match c.get("v1") {
-            Some(serde_yaml::Value::Mapping(c)) => c,
-            _ => return Err(anyhow::anyhow!("Couldn't find data.v1 in file: {}", file_path)),
-        },
-        _ => return Err(anyhow::anyhow!("Couldn't find data in file: {}", file_path)),
-    };
+/// Labels of the `dfinity/dre` repository, used when no other source is configured.
+pub const DEFAULT_NODE_LABELS_URL: &str = "https://raw.githubusercontent.com/dfinity/dre/main/node-labels/{network}.yaml";
+
+/// Where the `node-labels/<network>.yaml` files are read from.
+///
+/// Parsed from a string:
+///  * `http://...` or `https://...`: URL of the file, `{network}` is replaced with the network name
+///  * `git+<repo>[#<ref>]`: a local git checkout, or a remote repo that gets mirrored to the cache
+///    dir. The file is read at `<ref>` (`HEAD` by default) from `node-labels/<network>.yaml`
+///  * anything else: a local file, or a directory containing `<network>.yaml`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeLabelsSource {
+    File(PathBuf),
+    Git { repo: String, reference: String },
+    Http(String),
+}
+
+impl Default for NodeLabelsSource {
+    fn default() -> Self {
+        Self::Http(DEFAULT_NODE_LABELS_URL.to_string())
+    }
+}
+
+impl FromStr for NodeLabelsSource {
+    type Err = Infallible;
 
-    Ok(data
-        .iter()
-        .map(|(key, value)| {
-            let ip = key.as_str().unwrap();
-            let dc = value.get("dc").unwrap().as_str().unwrap();
-            let label = value.get("label").unwrap().as_str().unwrap();
-
-            Guest {
-                datacenter: dc.to_string(),
-                ipv6: ip.parse().unwrap(),
-                name: format!("{}-{}", dc, label),
-                dfinity_owned: DFINITY_DCS.contains(dc),
-                decentralized: true,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(if s.starts_with("http://") || s.starts_with("https://") {
+            Self::Http(s.to_string())
+        } else if let Some(git) = s.strip_prefix("git+") {
+            match git.rsplit_once('#') {
+                Some((repo, reference)) => Self::Git {
+                    repo: repo.to_string(),
+                    reference: reference.to_string(),
+                },
+                None => Self::Git {
+                    repo: git.to_string(),
+                    reference: "HEAD".to_string(),
+                },
             }
+        } else {
+            Self::File(PathBuf::from(s.strip_prefix("file://").unwrap_or(s)))
         })
-        .collect())
+    }
+}
+
+impl Display for NodeLabelsSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Git { repo, reference } => write!(f, "git+{}#{}", repo, reference),
+            Self::Http(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl NodeLabelsSource {
+    pub async fn query_guests(&self, network: &str) -> anyhow::Result<Vec<Guest>> {
+        let content = match self {
+            Self::File(path) => {
+                let path = if path.is_dir() {
+                    path.join(format!("{}.yaml", network))
+                } else {
+                    path.clone()
+                };
+                tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Couldn't read file {}: {}", path.display(), e))?
+            }
+            Self::Git { repo, reference } => read_from_git(repo, reference, network).await?,
+            Self::Http(url) => {
+                let url = url.replace("{network}", network);
+                reqwest::get(&url).await?.error_for_status()?.text().await?
+            }
+        };
+        parse_guests(&content).map_err(|e| anyhow::anyhow!("Invalid node labels from {}: {}", self, e))
+    }
+}
+
+async fn read_from_git(repo: &str, reference: &str, network: &str) -> anyhow::Result<String> {
+    let checkout = if Path::new(repo).exists() {
+        PathBuf::from(repo)
+    } else {
+        let mirror = dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("/tmp"))
+            .join("git")
+            .join("node-labels")
+            .join(repo.replace(|c: char| !c.is_ascii_alphanumeric(), "_"));
+        if !mirror.exists() {
+            info!("Mirroring node labels repo {} to {}", repo, mirror.display());
+            git(Path::new("."), &["clone", "--mirror", repo, &mirror.display().to_string()]).await?;
+        } else if let Err(e) = git(&mirror, &["fetch", "--prune"]).await {
+            // Offline, or the remote is gone. The mirror still has the last fetched labels.
+            warn!("Failed to fetch node labels repo {}, using the cached mirror: {}", repo, e);
+        }
+        mirror
+    };
+    git(&checkout, &["show", &format!("{}:node-labels/{}.yaml", reference, network)]).await
+}
+
+async fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().await?;
+    if output.status.success() {
+        Ok(String::from_utf8(output.stdout)?)
+    } else {
+        Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeLabelsFile {
+    data: NodeLabelsData,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeLabelsData {
+    v1: BTreeMap<String, NodeLabelEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeLabelEntry {
+    dc: String,
+    label: String,
+}
+
+/// Parses and validates a node labels file. All invalid entries are reported
+/// at once, rather than only the first one.
+pub fn parse_guests(content: &str) -> anyhow::Result<Vec<Guest>> {
+    // Going through `Value` rejects duplicate keys, which would otherwise
+    // silently overwrite each other.
+    let file: NodeLabelsFile = serde_yaml::from_value(serde_yaml::from_str(content)?)?;
+
+    let mut problems = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut guests = Vec::new();
+    for (key, entry) in file.data.v1 {
+        let ipv6 = match Ipv6Addr::from_str(&key) {
+            Ok(ipv6) => ipv6,
+            Err(_) => {
+                problems.push(format!("{}: not a valid IPv6 address", key));
+                continue;
+            }
+        };
+        if !seen.insert(ipv6) {
+            problems.push(format!("{}: IPv6 address is listed more than once", key));
+        }
+        if entry.dc.is_empty() {
+            problems.push(format!("{}: empty dc", key));
+        }
+        if entry.label.is_empty() {
+            problems.push(format!("{}: empty label", key));
+        }
+        guests.push(Guest {
+            datacenter: entry.dc.clone(),
+            ipv6,
+            name: format!("{}-{}", entry.dc, entry.label),
+            dfinity_owned: DFINITY_DCS.contains(&entry.dc),
+            decentralized: true,
+        });
+    }
+
+    if problems.is_empty() {
+        Ok(guests)
+    } else {
+        Err(anyhow::anyhow!("\n{}", problems.iter().map(|p| format!(" * {}", p)).join("\n")))
+    }
+}
+
+/// Consistency of the node labels with the registry.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct NodeLabelsReport {
+    /// Registry nodes that have no label
+    pub unlabeled_nodes: Vec<PrincipalId>,
+    /// Labels that are assigned to more than one IPv6 address
+    pub duplicate_labels: BTreeMap<String, Vec<Ipv6Addr>>,
+    /// Labeled IPv6 addresses that are no longer in the registry
+    pub stale_entries: BTreeMap<Ipv6Addr, String>,
+}
+
+impl NodeLabelsReport {
+    pub fn new(guests: &[Guest], registry_nodes: impl IntoIterator<Item = (PrincipalId, Ipv6Addr)>) -> Self {
+        let registry_nodes = registry_nodes.into_iter().collect::<Vec<_>>();
+        let registry_ips = registry_nodes.iter().map(|(_, ip)| *ip).collect::<BTreeSet<_>>();
+        let labeled_ips = guests.iter().map(|g| g.ipv6).collect::<BTreeSet<_>>();
+
+        Self {
+            unlabeled_nodes: registry_nodes
+                .iter()
+                .filter(|(_, ip)| !labeled_ips.contains(ip))
+                .map(|(principal, _)| *principal)
+                .collect(),
+            duplicate_labels: guests
+                .iter()
+                .map(|g| (g.name.clone(), g.ipv6))
+                .into_group_map()
+                .into_iter()
+                .filter(|(_, ips)| ips.len() > 1)
+                .collect(),
+            stale_entries: guests
+                .iter()
+                .filter(|g| !registry_ips.contains(&g.ipv6))
+                .map(|g| (g.ipv6, g.name.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.unlabeled_nodes.is_empty() && self.duplicate_labels.is_empty() && self.stale_entries.is_empty()
+    }
+}
+
+impl Display for NodeLabelsReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} unlabeled nodes, {} duplicate labels, {} stale entries",
+            self.unlabeled_nodes.len(),
+            self.duplicate_labels.len(),
+            self.stale_entries.len()
+        )?;
+        for (label, ips) in &self.duplicate_labels {
+            write!(f, "\n * label {} is used by {}", label, ips.iter().join(", "))?;
+        }
+        for (ip, label) in &self.stale_entries {
+            write!(f, "\n * {} ({}) is not in the registry", ip, label)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: &str = r#"
+data:
+  v1:
+    2001:db8::1:
+      dc: zh2
+      label: dll01
+    2001:db8::2:
+      dc: mm1
+      label: dll02
+    2001:db8::3:
+      dc: mm1
+      label: dll02
+"#;
+
+    #[test]
+    fn source_from_str() {
+        assert_eq!(
+            NodeLabelsSource::from_str("https://example.com/{network}.yaml").unwrap(),
+            NodeLabelsSource::Http("https://example.com/{network}.yaml".to_string())
+        );
+        assert_eq!(
+            NodeLabelsSource::from_str("git+https://github.com/dfinity/dre#main").unwrap(),
+            NodeLabelsSource::Git {
+                repo: "https://github.com/dfinity/dre".to_string(),
+                reference: "main".to_string()
+            }
+        );
+        assert_eq!(
+            NodeLabelsSource::from_str("git+/srv/dre").unwrap(),
+            NodeLabelsSource::Git {
+                repo: "/srv/dre".to_string(),
+                reference: "HEAD".to_string()
+            }
+        );
+        assert_eq!(
+            NodeLabelsSource::from_str("file:///srv/dre/node-labels").unwrap(),
+            NodeLabelsSource::File(PathBuf::from("/srv/dre/node-labels"))
+        );
+        assert_eq!(
+            NodeLabelsSource::from_str("node-labels/mainnet.yaml").unwrap(),
+            NodeLabelsSource::File(PathBuf::from("node-labels/mainnet.yaml"))
+        );
+    }
+
+    #[test]
+    fn parse_valid_labels() {
+        let guests = parse_guests(LABELS).unwrap();
+        assert_eq!(guests.len(), 3);
+        assert_eq!(guests[0].name, "zh2-dll01");
+        assert_eq!(guests[0].ipv6, Ipv6Addr::from_str("2001:db8::1").unwrap());
+        assert!(guests[0].dfinity_owned);
+        assert!(!guests[1].dfinity_owned);
+    }
+
+    #[test]
+    fn parse_reports_all_invalid_entries() {
+        let err = parse_guests(
+            r#"
+data:
+  v1:
+    not-an-ip:
+      dc: zh2
+      label: dll01
+    2001:db8::2:
+      dc: ""
+      label: dll02
+"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("not-an-ip: not a valid IPv6 address"), "{}", err);
+        assert!(err.contains("2001:db8::2: empty dc"), "{}", err);
+    }
+
+    #[test]
+    fn parse_rejects_the_same_address_twice() {
+        let err = parse_guests(
+            r#"
+data:
+  v1:
+    2001:db8::1:
+      dc: zh2
+      label: dll01
+    2001:db8:0::1:
+      dc: zh2
+      label: dll02
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("listed more than once"), "{}", err);
+
+        let err = parse_guests(
+            r#"
+data:
+  v1:
+    2001:db8::1:
+      dc: zh2
+      label: dll01
+    2001:db8::1:
+      dc: zh2
+      label: dll02
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("duplicate entry"), "{}", err);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields() {
+        assert!(parse_guests("data:\n  v1:\n    2001:db8::1:\n      dc: zh2\n      label: dll01\n      owner: x\n").is_err());
+        assert!(parse_guests("data:\n  v2: {}\n").is_err());
+    }
+
+    #[test]
+    fn report() {
+        let guests = parse_guests(LABELS).unwrap();
+        let report = NodeLabelsReport::new(
+            &guests,
+            vec![
+                (PrincipalId::new_node_test_id(1), Ipv6Addr::from_str("2001:db8::1").unwrap()),
+                (PrincipalId::new_node_test_id(2), Ipv6Addr::from_str("2001:db8::2").unwrap()),
+                (PrincipalId::new_node_test_id(4), Ipv6Addr::from_str("2001:db8::4").unwrap()),
+            ],
+        );
+
+        assert_eq!(
+            report,
+            NodeLabelsReport {
+                unlabeled_nodes: vec![PrincipalId::new_node_test_id(4)],
+                duplicate_labels: BTreeMap::from([(
+                    "mm1-dll02".to_string(),
+                    vec![Ipv6Addr::from_str("2001:db8::2").unwrap(), Ipv6Addr::from_str("2001:db8::3").unwrap()]
+                )]),
+                stale_entries: BTreeMap::from([(Ipv6Addr::from_str("2001:db8::3").unwrap(), "mm1-dll02".to_string())]),
+            }
+        );
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn query_guests_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("testnet.yaml"), LABELS).unwrap();
+
+        let guests = NodeLabelsSource::File(dir.path().to_path_buf()).query_guests("testnet").await.unwrap();
+        assert_eq!(guests.len(), 3);
+        assert!(NodeLabelsSource::File(dir.path().to_path_buf()).query_guests("mainnet").await.is_err());
+    }
 }
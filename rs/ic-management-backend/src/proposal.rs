@@ -84,6 +84,12 @@ pub struct UpdateUnassignedNodesProposal {
     pub payload: UpdateUnassignedNodesConfigPayload,
 }
 
+#[derive(Clone, Serialize)]
+pub struct NodesHostosUpdateProposal {
+    pub info: ProposalInfoInternal,
+    pub payload: UpdateNodesHostosVersionPayload,
+}
+
 #[allow(dead_code)]
 impl ProposalAgent {
     pub fn new(nns_urls: &[Url]) -> Self {
@@ -203,7 +209,24 @@ impl ProposalAgent {
             .collect::<Vec<_>>())
     }
 
+    pub async fn list_update_nodes_hostos_version_proposals(&self) -> Result<Vec<NodesHostosUpdateProposal>> {
+        Ok(filter_map_nns_function_proposals(&self.list_proposals(vec![]).await?)
+            .into_iter()
+            .map(|(info, payload)| NodesHostosUpdateProposal { info: info.into(), payload })
+            .collect::<Vec<_>>())
+    }
+
+    /// All proposals with an id greater than or equal to `proposal_id`, or all
+    /// proposals if it's not given.
+    pub async fn list_proposals_since(&self, proposal_id: Option<u64>) -> Result<Vec<ProposalInfo>> {
+        self.list_proposals_newer_than(vec![], proposal_id).await
+    }
+
     async fn list_proposals(&self, include_status: Vec<ProposalStatus>) -> Result<Vec<ProposalInfo>> {
+        self.list_proposals_newer_than(include_status, None).await
+    }
+
+    async fn list_proposals_newer_than(&self, include_status: Vec<ProposalStatus>, since: Option<u64>) -> Result<Vec<ProposalInfo>> {
         let mut proposals = vec![];
         loop {
             let fetch_partial_results = || async {
@@ -254,6 +277,13 @@ impl ProposalAgent {
             } else {
                 proposals.extend(partial_result);
             }
+            // Proposals are listed from the newest to the oldest
+            if let Some(since) = since {
+                if proposals.last().and_then(|p| p.id).map_or(false, |id| id.id <= since) {
+                    proposals.retain(|p| p.id.map_or(false, |id| id.id >= since));
+                    break;
+                }
+            }
         }
         let (empty_payload_proposals, full_payload_proposals): (_, Vec<_>) = proposals.into_iter().partition(|p| {
             if let Some(Action::ExecuteNnsFunction(action)) = p.proposal.clone().expect("proposal is not empty").action {
@@ -0,0 +1,572 @@
+use crate::proposal::ProposalAgent;
+use crate::registry::RegistryState;
+use candid::Decode;
+use ic_management_types::requests::ProposalHistoryQuery;
+use ic_management_types::{
+    Network, NnsFunctionProposal, Node, ProposalHistoryEntry, ProposalHistoryNode, ProposalNodeChange, ProposalStatusTransition,
+    TopologyChangePayload,
+};
+use ic_nns_governance::pb::v1::{proposal::Action, NnsFunction, ProposalInfo, ProposalStatus};
+use ic_types::PrincipalId;
+use log::{info, warn};
+use registry_canister::mutations::do_add_nodes_to_subnet::AddNodesToSubnetPayload;
+use registry_canister::mutations::do_change_subnet_membership::ChangeSubnetMembershipPayload;
+use registry_canister::mutations::do_create_subnet::CreateSubnetPayload;
+use registry_canister::mutations::do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload;
+use registry_canister::mutations::do_remove_nodes_from_subnet::RemoveNodesFromSubnetPayload;
+use registry_canister::mutations::do_revise_elected_replica_versions::ReviseElectedGuestosVersionsPayload;
+use registry_canister::mutations::do_update_elected_hostos_versions::UpdateElectedHostosVersionsPayload;
+use registry_canister::mutations::do_update_nodes_hostos_version::UpdateNodesHostosVersionPayload;
+use registry_canister::mutations::do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload;
+use registry_canister::mutations::node_management::do_remove_nodes::RemoveNodesPayload;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OnceCell, RwLock};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS proposals (
+    id INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    title TEXT,
+    summary TEXT NOT NULL,
+    proposer INTEGER,
+    timestamp_seconds INTEGER NOT NULL,
+    subnet TEXT,
+    payload TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS proposal_nodes (
+    proposal_id INTEGER NOT NULL REFERENCES proposals(id),
+    node TEXT NOT NULL,
+    provider TEXT,
+    change TEXT NOT NULL,
+    PRIMARY KEY (proposal_id, node, change)
+);
+CREATE INDEX IF NOT EXISTS proposal_nodes_node ON proposal_nodes(node);
+CREATE INDEX IF NOT EXISTS proposal_nodes_provider ON proposal_nodes(provider);
+CREATE TABLE IF NOT EXISTS proposal_status (
+    proposal_id INTEGER NOT NULL REFERENCES proposals(id),
+    status TEXT NOT NULL,
+    timestamp_seconds INTEGER NOT NULL,
+    PRIMARY KEY (proposal_id, status)
+);
+"#;
+
+/// Statuses after which a proposal doesn't change anymore.
+const FINAL_STATUSES: &str = "'rejected', 'executed', 'failed'";
+
+/// Local store of the topology, version and HostOS proposals, so that they can
+/// be queried long after they were decided.
+pub struct ProposalHistory {
+    conn: Mutex<Connection>,
+}
+
+impl ProposalHistory {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// Id of the oldest proposal that may still change, or of the newest
+    /// recorded one if all have been decided.
+    fn sync_point(&self) -> anyhow::Result<Option<u64>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let undecided: Option<u64> = conn.query_row(
+            &format!(
+                "SELECT MIN(id) FROM proposals p WHERE NOT EXISTS
+                    (SELECT 1 FROM proposal_status s WHERE s.proposal_id = p.id AND s.status IN ({}))",
+                FINAL_STATUSES
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        match undecided {
+            Some(id) => Ok(Some(id)),
+            None => Ok(conn.query_row("SELECT MAX(id) FROM proposals", [], |row| row.get(0))?),
+        }
+    }
+
+    /// Fetches the proposals that are newer than, or may have changed since,
+    /// the last sync and records them.
+    pub async fn sync(&self, registry_state: &RwLock<RegistryState>) -> anyhow::Result<()> {
+        // Don't hold the registry lock while fetching the proposals
+        let (agent, registry_nodes) = {
+            let registry = registry_state.read().await;
+            (ProposalAgent::new(registry.get_nns_urls()), registry.nodes())
+        };
+        let since = self.sync_point()?;
+        let entries = agent
+            .list_proposals_since(since)
+            .await?
+            .iter()
+            .filter_map(|info| history_entry(info, &registry_nodes))
+            .collect::<Vec<_>>();
+        info!("Recording {} proposals in the proposal history", entries.len());
+        self.record(&entries)
+    }
+
+    pub fn record(&self, entries: &[ProposalHistoryEntry]) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().expect("poisoned lock");
+        let tx = conn.transaction()?;
+        for entry in entries {
+            tx.execute(
+                "INSERT OR IGNORE INTO proposals (id, kind, title, summary, proposer, timestamp_seconds, subnet, payload)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    entry.id,
+                    entry.kind,
+                    entry.title,
+                    entry.summary,
+                    entry.proposer,
+                    entry.timestamp_seconds,
+                    entry.subnet.map(|s| s.to_string()),
+                    entry.payload.to_string(),
+                ],
+            )?;
+            for node in &entry.nodes {
+                // The provider is only known while the node is in the registry, so keep the first one seen
+                tx.execute(
+                    "INSERT INTO proposal_nodes (proposal_id, node, provider, change) VALUES (?1, ?2, ?3, ?4)
+                        ON CONFLICT DO UPDATE SET provider = COALESCE(provider, excluded.provider)",
+                    params![
+                        entry.id,
+                        node.node.to_string(),
+                        node.provider.map(|p| p.to_string()),
+                        node.change.to_string()
+                    ],
+                )?;
+            }
+            for transition in &entry.status_transitions {
+                tx.execute(
+                    "INSERT OR IGNORE INTO proposal_status (proposal_id, status, timestamp_seconds) VALUES (?1, ?2, ?3)",
+                    params![entry.id, transition.status, transition.timestamp_seconds],
+                )?;
+            }
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Recorded proposals matching the query, newest first.
+    pub fn query(&self, query: &ProposalHistoryQuery) -> anyhow::Result<Vec<ProposalHistoryEntry>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut statement = conn.prepare(
+            "SELECT DISTINCT p.id, p.kind, p.title, p.summary, p.proposer, p.timestamp_seconds, p.subnet, p.payload
+                FROM proposals p LEFT JOIN proposal_nodes n ON n.proposal_id = p.id
+                WHERE (?1 IS NULL OR p.subnet = ?1)
+                    AND (?2 IS NULL OR n.node = ?2)
+                    AND (?3 IS NULL OR n.provider = ?3)
+                    AND (?4 IS NULL OR p.timestamp_seconds >= ?4)
+                    AND (?5 IS NULL OR p.timestamp_seconds <= ?5)
+                ORDER BY p.id DESC",
+        )?;
+        let mut entries = statement
+            .query_map(
+                params![
+                    query.subnet.map(|s| s.to_string()),
+                    query.node.map(|n| n.to_string()),
+                    query.provider.map(|p| p.to_string()),
+                    query.since,
+                    query.until,
+                ],
+                |row| {
+                    Ok(ProposalHistoryEntry {
+                        id: row.get(0)?,
+                        kind: row.get(1)?,
+                        title: row.get(2)?,
+                        summary: row.get(3)?,
+                        proposer: row.get(4)?,
+                        timestamp_seconds: row.get(5)?,
+                        subnet: row.get::<_, Option<String>>(6)?.map(|s| parse_column(6, &s)).transpose()?,
+                        payload: serde_json::from_str(&row.get::<_, String>(7)?).map_err(|e| conversion_failure(7, e))?,
+                        nodes: vec![],
+                        status_transitions: vec![],
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut nodes = conn.prepare("SELECT node, provider, change FROM proposal_nodes WHERE proposal_id = ?1 ORDER BY change, node")?;
+        let mut statuses = conn.prepare("SELECT status, timestamp_seconds FROM proposal_status WHERE proposal_id = ?1 ORDER BY timestamp_seconds")?;
+        for entry in &mut entries {
+            entry.nodes = nodes
+                .query_map([entry.id], |row| {
+                    Ok(ProposalHistoryNode {
+                        node: parse_column(0, &row.get::<_, String>(0)?)?,
+                        provider: row.get::<_, Option<String>>(1)?.map(|p| parse_column(1, &p)).transpose()?,
+                        change: parse_column(2, &row.get::<_, String>(2)?)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+            entry.status_transitions = statuses
+                .query_map([entry.id], |row| {
+                    Ok(ProposalStatusTransition {
+                        status: row.get(0)?,
+                        timestamp_seconds: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(entries)
+    }
+}
+
+/// Fails the query, rather than the backend, on values that weren't recorded by us.
+fn conversion_failure(column: usize, e: impl std::fmt::Display) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, e.to_string().into())
+}
+
+fn parse_column<T: FromStr>(column: usize, value: &str) -> rusqlite::Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    T::from_str(value).map_err(|e| conversion_failure(column, e))
+}
+
+/// The proposal history, opened on first use so that `dre` commands that don't
+/// query it neither open nor sync it.
+pub struct LazyProposalHistory {
+    path: PathBuf,
+    /// Registry to sync the history with once it's opened, if it isn't synced
+    /// in the background
+    sync_with: Option<Arc<RwLock<RegistryState>>>,
+    history: OnceCell<Option<Arc<ProposalHistory>>>,
+}
+
+impl LazyProposalHistory {
+    pub fn new(path: PathBuf, sync_with: Option<Arc<RwLock<RegistryState>>>) -> Self {
+        Self {
+            path,
+            sync_with,
+            history: OnceCell::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn opened(history: ProposalHistory) -> Self {
+        Self {
+            path: PathBuf::new(),
+            sync_with: None,
+            history: OnceCell::new_with(Some(Arc::new(history))),
+        }
+    }
+
+    /// The history, or `None` if it can't be opened.
+    pub async fn get(&self) -> Option<Arc<ProposalHistory>> {
+        self.history
+            .get_or_init(|| async {
+                let history = match ProposalHistory::open(&self.path) {
+                    Ok(history) => Arc::new(history),
+                    Err(e) => {
+                        warn!("Failed to open the proposal history at {}: {}", self.path.display(), e);
+                        return None;
+                    }
+                };
+                if let Some(registry_state) = &self.sync_with {
+                    if let Err(e) = history.sync(registry_state).await {
+                        warn!("Failed to sync the proposal history, using the recorded proposals: {}", e);
+                    }
+                }
+                Some(history)
+            })
+            .await
+            .clone()
+    }
+}
+
+/// Keeps the proposal history up to date, when the backend runs as a service.
+pub async fn poll(history: Arc<LazyProposalHistory>, registry_state: Arc<RwLock<RegistryState>>) {
+    let Some(history) = history.get().await else {
+        return;
+    };
+    loop {
+        if let Err(e) = history.sync(&registry_state).await {
+            warn!("Failed to sync the proposal history: {}", e);
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+    }
+}
+
+pub fn proposal_history_path(network: &Network) -> PathBuf {
+    match std::env::var("PROPOSAL_HISTORY_PATH") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => match dirs::cache_dir() {
+            Some(cache_dir) => cache_dir,
+            None => PathBuf::from("/tmp"),
+        },
+    }
+    .join("ic-proposal-history")
+    .join(format!("{}.sqlite", network.name))
+}
+
+/// Payloads of the proposals that are recorded in the proposal history.
+trait HistoryPayload: NnsFunctionProposal + Serialize {
+    fn subnet(&self) -> Option<PrincipalId> {
+        None
+    }
+
+    fn nodes(&self) -> Vec<(PrincipalId, ProposalNodeChange)> {
+        vec![]
+    }
+}
+
+fn topology_nodes<T: TopologyChangePayload>(payload: &T) -> Vec<(PrincipalId, ProposalNodeChange)> {
+    payload
+        .get_added_node_ids()
+        .into_iter()
+        .map(|n| (n, ProposalNodeChange::Added))
+        .chain(payload.get_removed_node_ids().into_iter().map(|n| (n, ProposalNodeChange::Removed)))
+        .collect()
+}
+
+macro_rules! topology_history_payload {
+    ($($payload:ty),*) => {
+        $(impl HistoryPayload for $payload {
+            fn subnet(&self) -> Option<PrincipalId> {
+                self.get_subnet()
+            }
+
+            fn nodes(&self) -> Vec<(PrincipalId, ProposalNodeChange)> {
+                topology_nodes(self)
+            }
+        })*
+    };
+}
+
+topology_history_payload!(
+    CreateSubnetPayload,
+    AddNodesToSubnetPayload,
+    RemoveNodesFromSubnetPayload,
+    ChangeSubnetMembershipPayload,
+    RemoveNodesPayload
+);
+
+impl HistoryPayload for DeployGuestosToAllSubnetNodesPayload {
+    fn subnet(&self) -> Option<PrincipalId> {
+        Some(self.subnet_id.get())
+    }
+}
+
+impl HistoryPayload for UpdateNodesHostosVersionPayload {
+    fn nodes(&self) -> Vec<(PrincipalId, ProposalNodeChange)> {
+        self.node_ids.iter().map(|n| (n.get(), ProposalNodeChange::Updated)).collect()
+    }
+}
+
+impl HistoryPayload for ReviseElectedGuestosVersionsPayload {}
+impl HistoryPayload for UpdateElectedHostosVersionsPayload {}
+impl HistoryPayload for UpdateUnassignedNodesConfigPayload {}
+
+/// Converts a proposal to a proposal history entry, if it's of a kind the history records.
+pub fn history_entry(info: &ProposalInfo, registry_nodes: &BTreeMap<PrincipalId, Node>) -> Option<ProposalHistoryEntry> {
+    let Some(Action::ExecuteNnsFunction(function)) = info.proposal.as_ref().and_then(|p| p.action.as_ref()) else {
+        return None;
+    };
+    let payload = function.payload.as_slice();
+    let entry = match NnsFunction::try_from(function.nns_function).ok()? {
+        NnsFunction::CreateSubnet => decode::<CreateSubnetPayload>(info, payload, registry_nodes),
+        NnsFunction::AddNodeToSubnet => decode::<AddNodesToSubnetPayload>(info, payload, registry_nodes),
+        NnsFunction::RemoveNodesFromSubnet => decode::<RemoveNodesFromSubnetPayload>(info, payload, registry_nodes),
+        NnsFunction::ChangeSubnetMembership => decode::<ChangeSubnetMembershipPayload>(info, payload, registry_nodes),
+        NnsFunction::RemoveNodes => decode::<RemoveNodesPayload>(info, payload, registry_nodes),
+        NnsFunction::DeployGuestosToAllSubnetNodes => decode::<DeployGuestosToAllSubnetNodesPayload>(info, payload, registry_nodes),
+        NnsFunction::UpdateUnassignedNodesConfig => decode::<UpdateUnassignedNodesConfigPayload>(info, payload, registry_nodes),
+        NnsFunction::ReviseElectedGuestosVersions => decode::<ReviseElectedGuestosVersionsPayload>(info, payload, registry_nodes),
+        NnsFunction::UpdateElectedHostosVersions => decode::<UpdateElectedHostosVersionsPayload>(info, payload, registry_nodes),
+        NnsFunction::UpdateNodesHostosVersion => decode::<UpdateNodesHostosVersionPayload>(info, payload, registry_nodes),
+        _ => return None,
+    };
+    entry
+        .map_err(|e| warn!("Failed to decode proposal {:?} for the proposal history: {}", info.id, e))
+        .ok()
+}
+
+fn decode<T: HistoryPayload>(
+    info: &ProposalInfo,
+    payload: &[u8],
+    registry_nodes: &BTreeMap<PrincipalId, Node>,
+) -> anyhow::Result<ProposalHistoryEntry> {
+    let decoded = Decode!(payload, T)?;
+    let proposal = info.proposal.as_ref().expect("proposal is not empty");
+    Ok(ProposalHistoryEntry {
+        id: info.id.ok_or_else(|| anyhow::anyhow!("proposal without id"))?.id,
+        kind: format!("{:?}", T::TYPE),
+        title: proposal.title.clone(),
+        summary: proposal.summary.clone(),
+        proposer: info.proposer.map(|n| n.id),
+        timestamp_seconds: info.proposal_timestamp_seconds,
+        subnet: decoded.subnet(),
+        nodes: decoded
+            .nodes()
+            .into_iter()
+            .map(|(node, change)| ProposalHistoryNode {
+                node,
+                provider: registry_nodes.get(&node).map(|n| n.operator.provider.principal),
+                change,
+            })
+            .collect(),
+        status_transitions: status_transitions(info),
+        payload: serde_json::to_value(&decoded)?,
+    })
+}
+
+/// Reconstructs the status transitions from the timestamps of the proposal,
+/// so that none are missed between two syncs.
+fn status_transitions(info: &ProposalInfo) -> Vec<ProposalStatusTransition> {
+    let rejected = ProposalStatus::try_from(info.status).map_or(false, |s| s == ProposalStatus::Rejected);
+    [
+        ("open", info.proposal_timestamp_seconds),
+        (if rejected { "rejected" } else { "adopted" }, info.decided_timestamp_seconds),
+        ("executed", info.executed_timestamp_seconds),
+        ("failed", info.failed_timestamp_seconds),
+    ]
+    .into_iter()
+    .filter(|(_, timestamp_seconds)| *timestamp_seconds > 0)
+    .map(|(status, timestamp_seconds)| ProposalStatusTransition {
+        status: status.to_string(),
+        timestamp_seconds,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, timestamp_seconds: u64, subnet: Option<u64>, nodes: Vec<(u64, Option<u64>)>, statuses: &[&str]) -> ProposalHistoryEntry {
+        ProposalHistoryEntry {
+            id,
+            kind: "ChangeSubnetMembership".to_string(),
+            title: Some(format!("Proposal {}", id)),
+            summary: "Replacing 1 node to improve subnet decentralization".to_string(),
+            proposer: Some(40),
+            timestamp_seconds,
+            subnet: subnet.map(PrincipalId::new_subnet_test_id),
+            nodes: nodes
+                .into_iter()
+                .map(|(node, provider)| ProposalHistoryNode {
+                    node: PrincipalId::new_node_test_id(node),
+                    provider: provider.map(PrincipalId::new_user_test_id),
+                    change: ProposalNodeChange::Removed,
+                })
+                .collect(),
+            status_transitions: statuses
+                .iter()
+                .enumerate()
+                .map(|(i, status)| ProposalStatusTransition {
+                    status: status.to_string(),
+                    timestamp_seconds: timestamp_seconds + i as u64,
+                })
+                .collect(),
+            payload: serde_json::json!({ "id": id }),
+        }
+    }
+
+    fn ids(history: &ProposalHistory, query: ProposalHistoryQuery) -> Vec<u64> {
+        history.query(&query).unwrap().into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn query_filters() {
+        let history = ProposalHistory::open_in_memory().unwrap();
+        history
+            .record(&[
+                entry(1, 100, Some(1), vec![(1, Some(10)), (2, Some(20))], &["open", "adopted", "executed"]),
+                entry(2, 200, Some(2), vec![(3, Some(10))], &["open", "rejected"]),
+                entry(3, 300, None, vec![(1, None)], &["open"]),
+            ])
+            .unwrap();
+
+        assert_eq!(ids(&history, ProposalHistoryQuery::default()), vec![3, 2, 1]);
+        assert_eq!(
+            ids(
+                &history,
+                ProposalHistoryQuery {
+                    subnet: Some(PrincipalId::new_subnet_test_id(1)),
+                    ..Default::default()
+                }
+            ),
+            vec![1]
+        );
+        assert_eq!(
+            ids(
+                &history,
+                ProposalHistoryQuery {
+                    node: Some(PrincipalId::new_node_test_id(1)),
+                    ..Default::default()
+                }
+            ),
+            vec![3, 1]
+        );
+        assert_eq!(
+            ids(
+                &history,
+                ProposalHistoryQuery {
+                    provider: Some(PrincipalId::new_user_test_id(10)),
+                    ..Default::default()
+                }
+            ),
+            vec![2, 1]
+        );
+        assert_eq!(
+            ids(
+                &history,
+                ProposalHistoryQuery {
+                    since: Some(150),
+                    until: Some(250),
+                    ..Default::default()
+                }
+            ),
+            vec![2]
+        );
+        assert_eq!(
+            history.query(&ProposalHistoryQuery::default()).unwrap().pop().unwrap(),
+            entry(1, 100, Some(1), vec![(1, Some(10)), (2, Some(20))], &["open", "adopted", "executed"])
+        );
+    }
+
+    #[test]
+    fn status_transitions_are_added_and_providers_kept() {
+        let history = ProposalHistory::open_in_memory().unwrap();
+        history.record(&[entry(1, 100, None, vec![(1, Some(10))], &["open"])]).unwrap();
+        history.record(&[entry(2, 200, None, vec![], &["open", "adopted", "executed"])]).unwrap();
+        // Proposal 1 is still open, so the next sync has to start from it
+        assert_eq!(history.sync_point().unwrap(), Some(1));
+
+        // The node has left the registry by the time the proposal is decided
+        history.record(&[entry(1, 100, None, vec![(1, None)], &["open", "rejected"])]).unwrap();
+        assert_eq!(history.sync_point().unwrap(), Some(2));
+        assert_eq!(
+            history.query(&ProposalHistoryQuery::default()).unwrap().pop().unwrap(),
+            entry(1, 100, None, vec![(1, Some(10))], &["open", "rejected"])
+        );
+    }
+
+    #[test]
+    fn invalid_rows_fail_the_query() {
+        let history = ProposalHistory::open_in_memory().unwrap();
+        history.record(&[entry(1, 100, Some(1), vec![], &["open"])]).unwrap();
+        history
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE proposals SET subnet = 'not-a-principal' WHERE id = 1", [])
+            .unwrap();
+
+        assert!(history.query(&ProposalHistoryQuery::default()).is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use crate::git_ic_repo::IcRepo;
+use crate::git_ic_repo::{CommitBranches, ReleaseSource};
 use crate::health::HealthStatusQuerier;
-use crate::node_labels;
-use crate::proposal::{self, SubnetUpdateProposal, UpdateUnassignedNodesProposal};
+use crate::node_labels::{NodeLabelsReport, NodeLabelsSource};
+use crate::proposal::{self, NodesHostosUpdateProposal, SubnetUpdateProposal, UpdateUnassignedNodesProposal};
 use crate::public_dashboard::query_ic_dashboard_list;
 use async_trait::async_trait;
 use decentralization::network::{AvailableNodesQuerier, SubnetQuerier, SubnetQueryBy};
@@ -75,7 +75,7 @@ pub struct RegistryState {
 
     guestos_releases: ArtifactReleases,
     hostos_releases: ArtifactReleases,
-    ic_repo: Option<IcRepo>,
+    ic_repo: Option<Box<dyn CommitBranches>>,
 }
 pub trait RegistryEntry: RegistryValue {
     const KEY_PREFIX: &'static str;
@@ -213,6 +213,14 @@ impl RegistryState {
         let local_registry: Arc<LocalRegistry> =
             Arc::new(LocalRegistry::new(local_registry_path, Duration::from_millis(1000)).expect("Failed to create local registry"));
 
+        Self {
+            ic_repo: Some(ReleaseSource::from_env().open().expect("failed to init release source")),
+            ..Self::with_local_registry(network, local_registry)
+        }
+    }
+
+    /// State of an existing local registry, without releases and before the first update.
+    pub(crate) fn with_local_registry(network: &Network, local_registry: Arc<LocalRegistry>) -> Self {
         Self {
             network: network.clone(),
             local_registry,
@@ -223,7 +231,7 @@ impl RegistryState {
             node_labels_guests: Vec::new(),
             guestos_releases: ArtifactReleases::new(Artifact::GuestOs),
             hostos_releases: ArtifactReleases::new(Artifact::HostOs),
-            ic_repo: Some(IcRepo::new().expect("failed to init ic repo")),
+            ic_repo: None,
             known_subnets: [
                 (
                     "uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe",
@@ -312,6 +320,10 @@ impl RegistryState {
 
             let blessed_versions: HashSet<&String> = blessed_replica_versions.iter().chain(elected_hostos_versions.iter()).collect();
 
+            if let Err(e) = self.ic_repo.as_mut().unwrap().refresh() {
+                warn!("failed to refresh the release branches: {}", e);
+            }
+
             // A HashMap from the git revision to the latest commit branch in which the
             // commit is present
             let mut commit_to_release: HashMap<String, Release> = HashMap::new();
@@ -364,7 +376,13 @@ impl RegistryState {
                 releases.extend(
                     blessed_versions
                         .iter()
-                        .map(|version| commit_to_release.get(version).unwrap().clone())
+                        .filter_map(|version| match commit_to_release.get(version) {
+                            Some(release) => Some(release.clone()),
+                            None => {
+                                warn!("No release found for {} version {}", artifact, version);
+                                None
+                            }
+                        })
                         .sorted_by_key(|rr| rr.time)
                         .collect::<Vec<Release>>(),
                 );
@@ -662,6 +680,12 @@ impl RegistryState {
         proposal_agent.list_update_unassigned_nodes_version_proposals().await
     }
 
+    pub async fn nodes_hostos_upgrade_proposals(&self) -> Result<Vec<NodesHostosUpdateProposal>> {
+        let proposal_agent = proposal::ProposalAgent::new(self.get_nns_urls());
+
+        proposal_agent.list_update_nodes_hostos_version_proposals().await
+    }
+
     async fn retireable_hostos_versions(&self) -> Result<Vec<Release>> {
         let active_releases = self.hostos_releases.get_active_branches();
         let hostos_versions: BTreeSet<String> = self.nodes.values().map(|s| s.hostos_version.clone()).collect();
@@ -725,6 +749,10 @@ impl RegistryState {
         self.operators.clone()
     }
 
+    pub fn node_labels_report(&self) -> NodeLabelsReport {
+        NodeLabelsReport::new(&self.node_labels_guests, self.nodes.values().map(|n| (n.principal, n.ip_addr)))
+    }
+
     pub fn guests(&self) -> Vec<Guest> {
         self.node_labels_guests.clone()
     }
@@ -1020,7 +1048,7 @@ pub async fn sync_local_store(target_network: &Network) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Network) {
+pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Network, node_labels: NodeLabelsSource) {
     let nns_urls = target_network.get_nns_urls().clone();
     let registry_canister = RegistryCanister::new(nns_urls);
     loop {
@@ -1031,8 +1059,12 @@ pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Ne
             continue;
         };
         if latest_version != registry_state.read().await.version() {
-            fetch_and_add_node_labels_guests_to_registry(&target_network, &registry_state).await;
+            fetch_and_add_node_labels_guests_to_registry(&target_network, &node_labels, &registry_state).await;
             update_node_details(&registry_state).await;
+            let report = registry_state.read().await.node_labels_report();
+            if !report.is_clean() {
+                warn!("Node labels are not consistent with the registry: {}", report);
+            }
         } else {
             debug!(
                 "Skipping update. Registry already on latest version: {}",
@@ -1043,8 +1075,12 @@ pub async fn poll(registry_state: Arc<RwLock<RegistryState>>, target_network: Ne
 }
 
 // TODO: try to get rid of node_labels data source
-async fn fetch_and_add_node_labels_guests_to_registry(target_network: &Network, registry_state: &Arc<RwLock<RegistryState>>) {
-    let guests_result = node_labels::query_guests(&target_network.name).await;
+async fn fetch_and_add_node_labels_guests_to_registry(
+    target_network: &Network,
+    node_labels: &NodeLabelsSource,
+    registry_state: &Arc<RwLock<RegistryState>>,
+) {
+    let guests_result = node_labels.query_guests(&target_network.name).await;
 
     match guests_result {
         Ok(node_labels_guests) => {
@@ -1052,7 +1088,8 @@ async fn fetch_and_add_node_labels_guests_to_registry(target_network: &Network,
             registry_state.update_node_labels_guests(node_labels_guests);
         }
         Err(e) => {
-            warn!("Failed querying guests file: {}", e);
+            // Keep the last successfully loaded labels
+            warn!("Failed querying guests file from {}: {}", node_labels, e);
         }
     }
 }
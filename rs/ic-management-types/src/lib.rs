@@ -31,6 +31,7 @@ use std::str::FromStr;
 use strum::VariantNames;
 use strum_macros::EnumString;
 use url::Url;
+use utoipa::ToSchema;
 
 pub trait NnsFunctionProposal: CandidType + serde::de::DeserializeOwned {
     const TYPE: NnsFunction;
@@ -159,14 +160,69 @@ impl TopologyChangePayload for RemoveNodesPayload {
     }
 }
 
-#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TopologyChangeProposal {
+    #[schema(value_type = Vec<String>)]
     pub node_ids_added: Vec<PrincipalId>,
+    #[schema(value_type = Vec<String>)]
     pub node_ids_removed: Vec<PrincipalId>,
+    #[schema(value_type = Option<String>)]
     pub subnet_id: Option<PrincipalId>,
     pub id: u64,
 }
 
+/// A proposal recorded in the proposal history of the backend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ProposalHistoryEntry {
+    pub id: u64,
+    /// NNS function of the proposal, e.g. `ChangeSubnetMembership`
+    pub kind: String,
+    pub title: Option<String>,
+    /// Summary of the proposal, with the motivation for the change
+    pub summary: String,
+    pub proposer: Option<u64>,
+    /// Seconds since the UNIX epoch
+    pub timestamp_seconds: u64,
+    #[schema(value_type = Option<String>)]
+    pub subnet: Option<PrincipalId>,
+    pub nodes: Vec<ProposalHistoryNode>,
+    /// Ordered by time, starting with `open`
+    pub status_transitions: Vec<ProposalStatusTransition>,
+    /// The decoded payload of the NNS function
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ProposalHistoryNode {
+    #[schema(value_type = String)]
+    pub node: PrincipalId,
+    /// Provider of the node when the proposal was recorded, if the node was in the registry
+    #[schema(value_type = Option<String>)]
+    pub provider: Option<PrincipalId>,
+    pub change: ProposalNodeChange,
+}
+
+#[derive(strum_macros::Display, EnumString, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalNodeChange {
+    /// Added to a subnet
+    Added,
+    /// Removed from a subnet or from the network
+    Removed,
+    /// Version updated
+    Updated,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ProposalStatusTransition {
+    /// One of `open`, `adopted`, `rejected`, `executed` or `failed`
+    pub status: String,
+    /// Seconds since the UNIX epoch
+    pub timestamp_seconds: u64,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct UpdateElectedReplicaVersionsProposal {
     pub proposal_id: u64,
@@ -261,8 +317,9 @@ impl NodeFeature {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, ToSchema)]
 pub struct MinNakamotoCoefficients {
+    #[schema(value_type = BTreeMap<String, f64>)]
     pub coefficients: BTreeMap<NodeFeature, f64>,
     pub average: f64,
 }
@@ -415,7 +472,7 @@ pub enum Health {
     Unknown,
 }
 
-#[derive(PartialOrd, Ord, Eq, PartialEq, EnumString, Serialize, strum_macros::Display, Deserialize, Debug, Clone, Hash)]
+#[derive(PartialOrd, Ord, Eq, PartialEq, EnumString, Serialize, strum_macros::Display, Deserialize, Debug, Clone, Hash, ToSchema)]
 pub enum Status {
     Healthy,
     Degraded,
@@ -464,11 +521,12 @@ pub struct NodeVersion {
     pub replica_version: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, ToSchema)]
 pub struct Release {
     pub commit_hash: String,
     pub branch: String,
     pub name: String,
+    #[schema(value_type = String, format = DateTime)]
     pub time: chrono::NaiveDateTime,
     pub previous_patch_release: Option<Box<Release>>,
 }
@@ -535,7 +593,7 @@ impl ArtifactReleases {
     }
 }
 
-#[derive(strum_macros::Display, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[derive(strum_macros::Display, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, ToSchema)]
 #[strum(serialize_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
 pub enum Artifact {
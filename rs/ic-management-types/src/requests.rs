@@ -1,14 +1,16 @@
 use crate::{MinNakamotoCoefficients, Node, Status};
 use ic_base_types::PrincipalId;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct MembershipReplaceRequest {
     pub target: ReplaceTarget,
     pub heal: bool,
     pub optimize: Option<usize>,
     pub exclude: Option<Vec<String>>,
     pub only: Vec<String>,
+    #[schema(value_type = Option<Vec<String>>)]
     pub include: Option<Vec<PrincipalId>>,
     pub min_nakamoto_coefficients: Option<MinNakamotoCoefficients>,
 }
@@ -49,35 +51,54 @@ impl std::fmt::Display for MembershipReplaceRequest {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ReplaceTarget {
     /// Subnet targeted for replacements
+    #[schema(value_type = String)]
     Subnet(PrincipalId),
     /// Nodes on the same subnet that need to be replaced for other reasons
-    Nodes { nodes: Vec<PrincipalId>, motivation: String },
+    Nodes {
+        #[schema(value_type = Vec<String>)]
+        nodes: Vec<PrincipalId>,
+        motivation: String,
+    },
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct SubnetCreateRequest {
     pub size: usize,
     pub min_nakamoto_coefficients: Option<MinNakamotoCoefficients>,
     pub exclude: Option<Vec<String>>,
     pub only: Option<Vec<String>>,
+    #[schema(value_type = Option<Vec<String>>)]
     pub include: Option<Vec<PrincipalId>>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct SubnetResizeRequest {
+    #[schema(value_type = String)]
     pub subnet: PrincipalId,
     pub add: usize,
     pub remove: usize,
     pub exclude: Option<Vec<String>>,
     pub only: Option<Vec<String>>,
+    #[schema(value_type = Option<Vec<String>>)]
     pub include: Option<Vec<PrincipalId>>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SubnetWhatIfRequest {
+    #[schema(value_type = Option<String>)]
+    pub subnet: Option<PrincipalId>,
+    #[schema(value_type = Option<Vec<String>>)]
+    pub nodes_to_add: Option<Vec<PrincipalId>>,
+    #[schema(value_type = Option<Vec<String>>)]
+    pub nodes_to_remove: Option<Vec<PrincipalId>>,
+    pub min_nakamoto_coefficients: Option<MinNakamotoCoefficients>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct NodesRemoveRequest {
     pub no_auto: bool,
     pub remove_degraded: bool,
@@ -86,20 +107,22 @@ pub struct NodesRemoveRequest {
     pub motivation: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct NodesRemoveResponse {
     pub removals: Vec<NodeRemoval>,
     pub motivation: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct NodeRemoval {
+    #[schema(value_type = Object)]
     pub node: Node,
     pub reason: NodeRemovalReason,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub enum NodeRemovalReason {
+    #[schema(value_type = String)]
     Duplicates(PrincipalId),
     Unhealthy(Status),
     MatchedFilter(String),
@@ -114,3 +137,19 @@ impl NodeRemovalReason {
         }
     }
 }
+
+/// Filters of the proposal history. All filters are optional, and proposals
+/// have to match all of the given ones.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default)]
+pub struct ProposalHistoryQuery {
+    #[schema(value_type = Option<String>)]
+    pub subnet: Option<PrincipalId>,
+    #[schema(value_type = Option<String>)]
+    pub node: Option<PrincipalId>,
+    #[schema(value_type = Option<String>)]
+    pub provider: Option<PrincipalId>,
+    /// Only proposals created at or after this time, in seconds since the UNIX epoch
+    pub since: Option<u64>,
+    /// Only proposals created at or before this time, in seconds since the UNIX epoch
+    pub until: Option<u64>,
+}
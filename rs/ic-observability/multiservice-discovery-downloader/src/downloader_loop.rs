@@ -1,12 +1,15 @@
 use crossbeam_channel::Receiver;
 use multiservice_discovery_shared::builders::exec_log_config_structure::ExecLogConfigBuilderImpl;
+use multiservice_discovery_shared::builders::otel_collector_config_structure::{OtelCollectorConfigBuilderImpl, OtelLogReceiver};
 use multiservice_discovery_shared::builders::script_log_config_structure::ScriptLogConfigBuilderImpl;
 use multiservice_discovery_shared::filters::ic_name_regex_filter::IcNameRegexFilter;
 use multiservice_discovery_shared::filters::node_regex_id_filter::NodeIDRegexFilter;
+use multiservice_discovery_shared::filters::shard_filter::ShardFilter;
 use multiservice_discovery_shared::filters::{TargetGroupFilter, TargetGroupFilterList};
 use multiservice_discovery_shared::{
     builders::{log_vector_config_structure::VectorConfigBuilderImpl, prometheus_config_structure::PrometheusConfigBuilder, ConfigBuilder},
     contracts::target::TargetDto,
+    contracts::target_change::{diff_targets, TargetChange, TargetChangeKind},
 };
 use service_discovery::job_types::JobType;
 use slog::{debug, info, warn, Logger};
@@ -16,6 +19,7 @@ use std::{
 };
 
 use crate::log_subtype::Subtype;
+use crate::otel_subtype::LogReceiver;
 use crate::CliArgs;
 
 pub async fn run_downloader_loop(logger: Logger, cli: CliArgs, stop_signal: Receiver<()>) {
@@ -36,7 +40,17 @@ pub async fn run_downloader_loop(logger: Logger, cli: CliArgs, stop_signal: Rece
         filters.add(Box::new(IcNameRegexFilter::new(regex.clone())));
     }
 
+    if let Some(selector) = &cli.filter {
+        filters.add(Box::new(selector.clone()));
+    }
+
+    if cli.shard_count > 1 {
+        // The shard was validated when parsing the arguments
+        filters.add(Box::new(ShardFilter::new(cli.shard_index, cli.shard_count).unwrap()));
+    }
+
     let mut current_hash: u64 = 0;
+    let mut current_targets: Option<Vec<TargetDto>> = None;
 
     loop {
         let tick = crossbeam::select! {
@@ -88,16 +102,48 @@ pub async fn run_downloader_loop(logger: Logger, cli: CliArgs, stop_signal: Rece
             info!(logger, "Received new targets from {} @ interval {:?}", cli.sd_url, tick);
             current_hash = hash;
 
+            if let Some(previous) = &current_targets {
+                log_changes(&logger, &diff_targets(previous, &targets));
+            }
+            current_targets = Some(targets.clone());
+
             generate_config(&cli, targets, logger.clone());
         }
     }
 }
 
+/// Logs what changed, which helps to explain a sudden drop in scraped targets
+fn log_changes(logger: &Logger, changes: &[TargetChange]) {
+    for change in changes {
+        info!(
+            logger,
+            "Target {}", change.kind;
+            "reason" => %change.reason,
+            "ic" => &change.target.ic_name,
+            "node_id" => %change.target.node_id,
+            "name" => &change.target.name
+        );
+    }
+    let added = changes.iter().filter(|c| c.kind == TargetChangeKind::Added).count();
+    info!(logger, "{} targets added and {} removed", added, changes.len() - added);
+}
+
 fn generate_config(cli: &CliArgs, targets: Vec<TargetDto>, logger: Logger) {
-    let jobs = match cli.generator {
+    let mut jobs = match cli.generator {
         crate::Generator::Log(_) => JobType::all_for_logs(),
-        crate::Generator::Metric => JobType::all_for_ic_nodes(),
-    };
+        crate::Generator::Metric | crate::Generator::Otel(_) => JobType::all_for_ic_nodes(),
+    }
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<_>>();
+    // The jobs that aren't built in are only known from the targets
+    if !matches!(cli.generator, crate::Generator::Log(_)) {
+        for job in targets.iter().flat_map(|target| &target.custom_jobs) {
+            if !jobs.contains(&job.name) {
+                jobs.push(job.name.clone());
+            }
+        }
+    }
 
     if std::fs::metadata(&cli.output_dir).is_err() {
         std::fs::create_dir_all(cli.output_dir.parent().unwrap()).unwrap();
@@ -105,15 +151,7 @@ fn generate_config(cli: &CliArgs, targets: Vec<TargetDto>, logger: Logger) {
     }
 
     for job in &jobs {
-        let targets_with_job = targets
-            .clone()
-            .iter_mut()
-            .filter(|f| f.jobs.contains(job))
-            .map(|f| TargetDto {
-                jobs: vec![*job],
-                ..f.clone()
-            })
-            .collect();
+        let targets_with_job = targets.iter().filter_map(|f| f.with_only_job(job)).collect();
 
         let config = match &cli.generator {
             crate::Generator::Log(subtype) => match &subtype.subcommands {
@@ -152,6 +190,18 @@ fn generate_config(cli: &CliArgs, targets: Vec<TargetDto>, logger: Logger) {
                 .build(targets_with_job),
             },
             crate::Generator::Metric => PrometheusConfigBuilder {}.build(targets_with_job),
+            crate::Generator::Otel(subtype) => OtelCollectorConfigBuilderImpl {
+                exporters: subtype.exporters.clone(),
+                log_receiver: subtype.logs.as_ref().map(|logs| match logs {
+                    LogReceiver::Journald { journals_folder } => OtelLogReceiver::Journald {
+                        journals_folder: journals_folder.clone(),
+                    },
+                    LogReceiver::Filelog { logs_folder } => OtelLogReceiver::Filelog {
+                        logs_folder: logs_folder.clone(),
+                    },
+                }),
+            }
+            .build(targets_with_job),
         };
 
         let path = cli.output_dir.join(format!("{}.json", job));
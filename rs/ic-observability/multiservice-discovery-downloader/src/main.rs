@@ -1,10 +1,12 @@
 use std::{path::PathBuf, time::Duration};
 
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{error::ErrorKind, ArgAction, CommandFactory, Parser, Subcommand};
 use downloader_loop::run_downloader_loop;
 use futures_util::FutureExt;
 use humantime::parse_duration;
 use ic_async_utils::shutdown_signal;
+use multiservice_discovery_shared::filters::label_selector_filter::LabelSelectorFilter;
+use multiservice_discovery_shared::filters::shard_filter::ShardFilter;
 use regex::Regex;
 use slog::{info, o, Drain, Logger};
 use tokio::runtime::Runtime;
@@ -17,6 +19,9 @@ fn main() {
     let rt = Runtime::new().unwrap();
     let shutdown_signal = shutdown_signal(logger.clone()).shared();
     let cli_args = CliArgs::parse();
+    if let Err(e) = ShardFilter::new(cli_args.shard_index, cli_args.shard_count) {
+        CliArgs::command().error(ErrorKind::ValueValidation, e).exit();
+    }
     let (stop_signal_sender, stop_signal_rcv) = crossbeam::channel::bounded::<()>(0);
 
     info!(logger, "Starting downloader loop"; "cli_args" => ?cli_args);
@@ -101,6 +106,44 @@ Regex used to filter the ic name
     )]
     filter_ic_name_regex: Option<Regex>,
 
+    #[clap(
+        long = "filter",
+        help = r#"
+Label selector expression used to filter the targets, for example
+'ic_name=mercury, job=~"replica|orchestrator" and not dc_id=zh2'.
+Selectors compare a field of the targets (node_id, ic_name, name,
+subnet_id, dc_id, operator_id, node_provider_id, is_api_bn, job) or a
+custom label with =, !=, =~ or !~, and are combined with and (or ','),
+or and not. Applied together with the regex filters.
+
+"#
+    )]
+    filter: Option<LabelSelectorFilter>,
+
+    #[clap(
+        long = "shard-count",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = r#"
+Number of downloaders the targets are split across. The nodes are assigned
+to the shards with a consistent hash of their node ID, so all jobs of a
+node are downloaded by the same downloader.
+
+"#
+    )]
+    shard_count: u32,
+
+    #[clap(
+        long = "shard-index",
+        default_value = "0",
+        help = r#"
+The shard of the targets this downloader keeps, from 0 to
+'--shard-count' - 1.
+
+"#
+    )]
+    shard_index: u32,
+
     #[clap(
         long = "bn-source-port",
         help = r#"
@@ -117,6 +160,37 @@ pub enum Generator {
     Log(log_subtype::LogSubtype),
     #[clap(about = "Generate a vector config for a metric source")]
     Metric,
+    #[clap(about = "Generate an OpenTelemetry Collector config for metrics and logs")]
+    Otel(otel_subtype::OtelSubtype),
+}
+
+pub mod otel_subtype {
+    use super::*;
+    #[derive(Parser, Clone, Debug)]
+    pub struct OtelSubtype {
+        #[clap(
+            long = "exporter",
+            default_value = "otlp",
+            help = "Exporter of the pipelines, has to be configured in another config of the collector. Can be given multiple times"
+        )]
+        pub exporters: Vec<String>,
+        #[clap(subcommand)]
+        pub logs: Option<LogReceiver>,
+    }
+
+    #[derive(Subcommand, Clone, Debug)]
+    pub enum LogReceiver {
+        #[clap(about = "Also collect the logs with a journald receiver")]
+        Journald {
+            #[clap(long = "journals-folder", help = "Path to the root journals folder")]
+            journals_folder: String,
+        },
+        #[clap(about = "Also collect the logs with a filelog receiver")]
+        Filelog {
+            #[clap(long = "logs-folder", help = "Path to the root logs folder")]
+            logs_folder: String,
+        },
+    }
 }
 
 pub mod log_subtype {
@@ -207,6 +207,7 @@ mod tests {
             dc_id: "dc1".to_string(),
             targets: targets.clone(),
             jobs: jobs.clone(),
+            custom_jobs: vec![],
             operator_id: PrincipalId::new_anonymous(),
             custom_labels: custom_labels.clone(),
             is_api_bn: false,
@@ -220,6 +221,7 @@ mod tests {
             dc_id: "dc1".to_string(),
             targets: targets.clone(),
             jobs: jobs.clone(),
+            custom_jobs: vec![],
             operator_id: PrincipalId::new_anonymous(),
             custom_labels: custom_labels.clone(),
             is_api_bn: false,
@@ -4,6 +4,7 @@ use crate::contracts::target::TargetDto;
 
 pub mod exec_log_config_structure;
 pub mod log_vector_config_structure;
+pub mod otel_collector_config_structure;
 pub mod prometheus_config_structure;
 pub mod script_log_config_structure;
 pub mod sns_canister_config_structure;
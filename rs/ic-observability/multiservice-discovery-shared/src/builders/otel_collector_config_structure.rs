@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ic_types::PrincipalId;
+use serde::Serialize;
+use service_discovery::job_types::{JobDefinition, JobType};
+
+use crate::contracts::target::TargetDto;
+
+use super::{prometheus_config_structure::target_labels, ConfigBuilder};
+
+/// Renders the targets into an OpenTelemetry Collector config.
+///
+/// Every node and job gets a `prometheus` receiver scraping it and, for the log
+/// jobs, a `journald` or `filelog` receiver. Both are put into pipelines with a
+/// `resource` processor that sets the IC labels as resource attributes. The
+/// exporters are referenced by name and have to be configured in another config
+/// file passed to the collector.
+#[derive(Debug, Clone)]
+pub struct OtelCollectorConfigBuilderImpl {
+    pub exporters: Vec<String>,
+    pub log_receiver: Option<OtelLogReceiver>,
+}
+
+#[derive(Debug, Clone)]
+pub enum OtelLogReceiver {
+    /// Reads the journals in `<journals_folder>/<node>-<job>`, e.g. the ones
+    /// written by the exec script.
+    Journald { journals_folder: String },
+    /// Reads the files matching `<logs_folder>/<node>-<job>/*.log`
+    Filelog { logs_folder: String },
+}
+
+#[derive(Debug, Default, Serialize)]
+struct OtelCollectorConfig {
+    receivers: BTreeMap<String, OtelReceiver>,
+    processors: BTreeMap<String, OtelResourceProcessor>,
+    service: OtelService,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum OtelReceiver {
+    Prometheus { config: OtelPrometheusConfig },
+    Journald { directory: String },
+    Filelog { include: Vec<String>, start_at: String },
+}
+
+#[derive(Debug, Serialize)]
+struct OtelPrometheusConfig {
+    scrape_configs: Vec<OtelScrapeConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct OtelScrapeConfig {
+    job_name: String,
+    scheme: String,
+    metrics_path: String,
+    static_configs: Vec<OtelStaticConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct OtelStaticConfig {
+    targets: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OtelResourceProcessor {
+    attributes: Vec<OtelResourceAttribute>,
+}
+
+#[derive(Debug, Serialize)]
+struct OtelResourceAttribute {
+    key: String,
+    value: String,
+    action: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct OtelService {
+    pipelines: BTreeMap<String, OtelPipeline>,
+}
+
+#[derive(Debug, Serialize)]
+struct OtelPipeline {
+    receivers: Vec<String>,
+    processors: Vec<String>,
+    exporters: Vec<String>,
+}
+
+impl ConfigBuilder for OtelCollectorConfigBuilderImpl {
+    fn build(&self, target_groups: BTreeSet<TargetDto>) -> String {
+        let mut config = OtelCollectorConfig::default();
+        let anonymous = PrincipalId::new_anonymous().to_string();
+
+        for record in &target_groups {
+            let is_bn = record.node_id.to_string() == anonymous;
+            for job in &record.job_definitions() {
+                let key = match is_bn {
+                    true => format!("{}-{}", record.name, job.name),
+                    false => format!("{}-{}", record.node_id, job.name),
+                };
+                let processor = format!("resource/{}", key);
+                config.processors.insert(
+                    processor.clone(),
+                    OtelResourceProcessor {
+                        attributes: target_labels(record, &job.name)
+                            .into_iter()
+                            .map(|(key, value)| OtelResourceAttribute {
+                                key,
+                                value,
+                                action: "upsert".to_string(),
+                            })
+                            .collect(),
+                    },
+                );
+
+                let metrics_receiver = format!("prometheus/{}", key);
+                config.receivers.insert(
+                    metrics_receiver.clone(),
+                    OtelReceiver::Prometheus {
+                        config: OtelPrometheusConfig {
+                            scrape_configs: vec![OtelScrapeConfig {
+                                job_name: job.name.clone(),
+                                scheme: job.scheme.clone(),
+                                metrics_path: job.path.clone(),
+                                static_configs: vec![OtelStaticConfig {
+                                    targets: record.targets.iter().map(|sa| job.sockaddr(*sa, false).to_string()).collect(),
+                                }],
+                            }],
+                        },
+                    },
+                );
+                config
+                    .service
+                    .pipelines
+                    .insert(format!("metrics/{}", key), self.pipeline(metrics_receiver, &processor));
+
+                if let Some((name, receiver)) = self.log_receiver(job, &key) {
+                    config.receivers.insert(name.clone(), receiver);
+                    config.service.pipelines.insert(format!("logs/{}", key), self.pipeline(name, &processor));
+                }
+            }
+        }
+
+        serde_json::to_string_pretty(&config).unwrap()
+    }
+}
+
+impl OtelCollectorConfigBuilderImpl {
+    fn pipeline(&self, receiver: String, processor: &str) -> OtelPipeline {
+        OtelPipeline {
+            receivers: vec![receiver],
+            processors: vec![processor.to_string()],
+            exporters: self.exporters.clone(),
+        }
+    }
+
+    /// The log receiver of the node and job, if logs are collected for the job
+    fn log_receiver(&self, job: &JobDefinition, key: &str) -> Option<(String, OtelReceiver)> {
+        if !job.job_type().is_some_and(|job_type| JobType::all_for_logs().contains(&job_type)) {
+            return None;
+        }
+        match self.log_receiver.as_ref()? {
+            OtelLogReceiver::Journald { journals_folder } => Some((
+                format!("journald/{}", key),
+                OtelReceiver::Journald {
+                    directory: format!("{}/{}", journals_folder, key),
+                },
+            )),
+            OtelLogReceiver::Filelog { logs_folder } => Some((
+                format!("filelog/{}", key),
+                OtelReceiver::Filelog {
+                    include: vec![format!("{}/{}/*.log", logs_folder, key)],
+                    start_at: "beginning".to_string(),
+                },
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use serde_json::{json, Value};
+    use service_discovery::job_types::NodeOS;
+
+    use super::*;
+
+    #[test]
+    fn test_otel_collector_config_builder() {
+        let builder = OtelCollectorConfigBuilderImpl {
+            exporters: vec!["otlp".to_string()],
+            log_receiver: Some(OtelLogReceiver::Journald {
+                journals_folder: "/journals".to_string(),
+            }),
+        };
+        let node_id = PrincipalId::new_node_test_id(1);
+        let target = TargetDto {
+            node_id: node_id.into(),
+            name: "node".to_string(),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "dc1".to_string(),
+            targets: BTreeSet::from(["[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:8080".parse::<SocketAddr>().unwrap()]),
+            jobs: vec![JobType::Replica, JobType::NodeExporter(NodeOS::Host)],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::new(),
+            is_api_bn: false,
+        };
+
+        let config: Value = serde_json::from_str(&builder.build(BTreeSet::from([target]))).unwrap();
+
+        let replica = format!("{}-replica", node_id);
+        let host = format!("{}-host_node_exporter", node_id);
+        assert_eq!(
+            config["receivers"][format!("prometheus/{}", host)],
+            json!({
+                "config": {
+                    "scrape_configs": [{
+                        "job_name": "host_node_exporter",
+                        "scheme": "https",
+                        "metrics_path": "/metrics",
+                        "static_configs": [{ "targets": ["[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:9100"] }]
+                    }]
+                }
+            })
+        );
+        assert_eq!(
+            config["receivers"][format!("journald/{}", host)],
+            json!({ "directory": format!("/journals/{}", host) })
+        );
+        // Logs are only collected for the log jobs
+        assert!(config["receivers"].get(format!("journald/{}", replica)).is_none());
+        assert_eq!(
+            config["processors"][format!("resource/{}", replica)]["attributes"],
+            json!([
+                { "key": "ic", "value": "mercury", "action": "upsert" },
+                { "key": "ic_node", "value": node_id.to_string(), "action": "upsert" },
+                { "key": "job", "value": "replica", "action": "upsert" },
+            ])
+        );
+        assert_eq!(
+            config["service"]["pipelines"][format!("logs/{}", host)],
+            json!({
+                "receivers": [format!("journald/{}", host)],
+                "processors": [format!("resource/{}", host)],
+                "exporters": ["otlp"]
+            })
+        );
+        assert_eq!(config["service"]["pipelines"].as_object().unwrap().len(), 3);
+    }
+}
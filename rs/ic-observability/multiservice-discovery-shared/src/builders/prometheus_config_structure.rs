@@ -1,4 +1,5 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
 
 use ic_types::PrincipalId;
 use serde::{Deserialize, Serialize, Serializer};
@@ -39,43 +40,68 @@ pub const JOB: &str = "job";
 // const NODE_PROVIDER_ID: &str = "node_provider_id";
 // const NODE_OPERATOR_ID: &str = "node_operator_id";
 
+pub const SCHEME: &str = "__scheme__";
+pub const METRICS_PATH: &str = "__metrics_path__";
+
+pub(crate) fn target_labels(tg: &TargetDto, job: &impl Display) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (IC_NAME.into(), tg.ic_name.clone()),
+        (
+            IC_NODE.into(),
+            if tg.node_id.to_string() == PrincipalId::new_anonymous().to_string() {
+                tg.name.clone()
+            } else {
+                tg.node_id.to_string()
+            },
+        ),
+        (JOB.into(), job.to_string()),
+    ])
+    .into_iter()
+    .chain(match tg.subnet_id {
+        Some(subnet_id) => vec![(IC_SUBNET.into(), subnet_id.to_string())],
+        None => vec![],
+    })
+    .chain(tg.custom_labels.clone())
+    .collect()
+    // TODO: Re-add the labels below once we resolve the issues with the public dashboard queries
+    // https://dfinity.atlassian.net/browse/OB-442
+    // labels.insert(DC.into(), tg.dc_id.clone());
+    // labels.insert(NODE_PROVIDER_ID.into(), tg.node_provider_id.to_string());
+    // labels.insert(NODE_OPERATOR_ID.into(), tg.operator_id.to_string());
+}
+
 pub fn map_target_group(target_groups: Vec<TargetDto>) -> Vec<PrometheusStaticConfig> {
     target_groups
         .into_iter()
         .flat_map(|tg| {
-            let mut ret = vec![];
-            for job in &tg.jobs {
-                ret.push(PrometheusStaticConfig {
+            tg.job_definitions()
+                .iter()
+                .map(|job| PrometheusStaticConfig {
                     targets: tg.targets.iter().map(|sa| job.url(*sa, false)).collect(),
-                    labels: {
-                        BTreeMap::from([
-                            (IC_NAME.into(), tg.ic_name.clone()),
-                            (
-                                IC_NODE.into(),
-                                if tg.node_id.to_string() == PrincipalId::new_anonymous().to_string() {
-                                    tg.name.clone()
-                                } else {
-                                    tg.node_id.to_string()
-                                },
-                            ),
-                            (JOB.into(), job.to_string()),
-                        ])
+                    labels: target_labels(&tg, &job.name),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Maps the target groups to the format of Prometheus' `http_sd_configs`, where
+/// the targets are plain `host:port` and the scheme and metrics path of the job
+/// are given as labels.
+pub fn map_target_group_http_sd(target_groups: Vec<TargetDto>) -> Vec<PrometheusStaticConfig> {
+    target_groups
+        .into_iter()
+        .flat_map(|tg| {
+            tg.job_definitions()
+                .iter()
+                .map(|job| PrometheusStaticConfig {
+                    targets: tg.targets.iter().map(|sa| job.sockaddr(*sa, false).to_string()).collect(),
+                    labels: target_labels(&tg, &job.name)
                         .into_iter()
-                        .chain(match tg.subnet_id {
-                            Some(subnet_id) => vec![(IC_SUBNET.into(), subnet_id.to_string())],
-                            None => vec![],
-                        })
-                        .chain(tg.custom_labels.clone().into_iter())
-                        .collect()
-                        // TODO: Re-add the labels below once we resolve the issues with the public dashboard queries
-                        // https://dfinity.atlassian.net/browse/OB-442
-                        // labels.insert(DC.into(), tg.dc_id.clone());
-                        // labels.insert(NODE_PROVIDER_ID.into(), tg.node_provider_id.to_string());
-                        // labels.insert(NODE_OPERATOR_ID.into(), tg.operator_id.to_string());
-                    },
+                        .chain([(SCHEME.to_string(), job.scheme.clone()), (METRICS_PATH.to_string(), job.path.clone())])
+                        .collect(),
                 })
-            }
-            ret
+                .collect::<Vec<_>>()
         })
         .collect()
 }
@@ -86,3 +112,75 @@ impl ConfigBuilder for PrometheusConfigBuilder {
         serde_json::to_string_pretty(&new_configs).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use service_discovery::job_types::{JobDefinition, JobType, NodeKind, NodeOS};
+
+    use super::*;
+
+    #[test]
+    fn http_sd_labels() {
+        let target = TargetDto {
+            node_id: PrincipalId::new_node_test_id(1).into(),
+            name: "node".to_string(),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "dc1".to_string(),
+            targets: BTreeSet::from(["[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:8080".parse::<SocketAddr>().unwrap()]),
+            jobs: vec![JobType::NodeExporter(NodeOS::Host)],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::new(),
+            is_api_bn: false,
+        };
+
+        assert_eq!(
+            map_target_group_http_sd(vec![target.clone()]),
+            vec![PrometheusStaticConfig {
+                targets: BTreeSet::from(["[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:9100".to_string()]),
+                labels: BTreeMap::from([
+                    (IC_NAME.to_string(), "mercury".to_string()),
+                    (IC_NODE.to_string(), PrincipalId::new_node_test_id(1).to_string()),
+                    (JOB.to_string(), "host_node_exporter".to_string()),
+                    (SCHEME.to_string(), "https".to_string()),
+                    (METRICS_PATH.to_string(), "/metrics".to_string()),
+                ]),
+            }]
+        );
+
+        let certificate_syncer = JobDefinition {
+            name: "certificate_syncer".to_string(),
+            port: 9322,
+            path: "/metrics".to_string(),
+            scheme: "http".to_string(),
+            address: Default::default(),
+            node_kinds: vec![NodeKind::ApiBoundaryNode],
+            assigned_nodes_only: false,
+        };
+        let host_node_exporter = JobDefinition {
+            port: 9200,
+            ..JobType::NodeExporter(NodeOS::Host).definition()
+        };
+        let target = TargetDto {
+            custom_jobs: vec![host_node_exporter, certificate_syncer],
+            ..target
+        };
+        let configs = map_target_group_http_sd(vec![target]);
+        assert_eq!(configs.len(), 2);
+        assert_eq!(
+            configs[0].targets,
+            BTreeSet::from(["[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:9200".to_string()])
+        );
+        assert_eq!(configs[0].labels[JOB], "host_node_exporter");
+        assert_eq!(
+            configs[1].targets,
+            BTreeSet::from(["[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9322".to_string()])
+        );
+        assert_eq!(configs[1].labels[JOB], "certificate_syncer");
+        assert_eq!(configs[1].labels[SCHEME], "http");
+    }
+}
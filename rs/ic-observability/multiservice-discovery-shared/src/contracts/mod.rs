@@ -1,8 +1,20 @@
 pub mod deployed_sns;
 pub mod target;
+pub mod target_change;
 
 pub trait DataContract {
     fn get_name(&self) -> String;
     fn get_id(&self) -> String;
     fn get_target_name(&self) -> String;
+
+    /// The values of a field or label, used by the label selectors. Labels
+    /// that aren't set have no values.
+    fn get_label_values(&self, label: &str) -> Vec<String> {
+        match label {
+            "ic_name" => vec![self.get_name()],
+            "node_id" => vec![self.get_id()],
+            "name" => vec![self.get_target_name()],
+            _ => vec![],
+        }
+    }
 }
@@ -6,7 +6,10 @@ use std::{
 
 use ic_types::{NodeId, PrincipalId, SubnetId};
 use serde::{Deserialize, Serialize};
-use service_discovery::{job_types::JobType, TargetGroup};
+use service_discovery::{
+    job_types::{JobDefinition, JobType},
+    TargetGroup,
+};
 
 use super::DataContract;
 
@@ -24,6 +27,10 @@ pub struct TargetDto {
     pub operator_id: PrincipalId,
     pub node_provider_id: PrincipalId,
     pub jobs: Vec<JobType>,
+    /// The definitions of the jobs that aren't built in, and of the built-in
+    /// jobs whose definition was changed in configuration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_jobs: Vec<JobDefinition>,
     pub custom_labels: BTreeMap<String, String>,
     pub name: String,
     pub is_api_bn: bool,
@@ -41,27 +48,67 @@ impl DataContract for TargetDto {
     fn get_target_name(&self) -> String {
         self.name.to_string()
     }
+
+    /// The fields of the target take precedence over custom labels of the
+    /// same name. A target has a `job` value for each of its jobs.
+    fn get_label_values(&self, label: &str) -> Vec<String> {
+        match label {
+            "node_id" => vec![self.node_id.to_string()],
+            "ic_name" => vec![self.ic_name.clone()],
+            "name" => vec![self.name.clone()],
+            "subnet_id" => self.subnet_id.iter().map(|s| s.to_string()).collect(),
+            "dc_id" => vec![self.dc_id.clone()],
+            "operator_id" => vec![self.operator_id.to_string()],
+            "node_provider_id" => vec![self.node_provider_id.to_string()],
+            "is_api_bn" => vec![self.is_api_bn.to_string()],
+            "job" => self.job_definitions().into_iter().map(|j| j.name).collect(),
+            _ => self.custom_labels.get(label).cloned().into_iter().collect(),
+        }
+    }
 }
 
-pub fn map_to_target_dto(
-    value: &TargetGroup,
-    job_type: JobType,
-    custom_labels: BTreeMap<String, String>,
-    name: String,
-    def_name: String,
-) -> TargetDto {
-    TargetDto {
-        name,
-        node_id: value.node_id,
-        ic_name: def_name,
-        targets: value.targets.clone(),
-        subnet_id: value.subnet_id,
-        dc_id: value.dc_id.clone(),
-        operator_id: value.operator_id,
-        node_provider_id: value.node_provider_id,
-        jobs: vec![job_type],
-        custom_labels,
-        is_api_bn: value.is_api_bn,
+impl TargetDto {
+    /// The definitions of the jobs of the target, the custom definitions
+    /// replace the built-in ones of the same name
+    pub fn job_definitions(&self) -> Vec<JobDefinition> {
+        let built_in = self.jobs.iter().map(|job| {
+            let name = job.to_string();
+            match self.custom_jobs.iter().find(|custom| custom.name == name) {
+                Some(custom) => custom.clone(),
+                None => job.definition(),
+            }
+        });
+        let custom = self
+            .custom_jobs
+            .iter()
+            .filter(|custom| custom.job_type().map_or(true, |job| !self.jobs.contains(&job)))
+            .cloned();
+        built_in.chain(custom).collect()
+    }
+
+    /// Adds the job to the target. A built-in job whose definition was
+    /// changed is added to both `jobs` and `custom_jobs`.
+    pub fn add_job(&mut self, job: &JobDefinition) {
+        if let Some(job_type) = job.job_type() {
+            self.jobs.push(job_type);
+        }
+        if !job.is_built_in() {
+            self.custom_jobs.push(job.clone());
+        }
+    }
+
+    /// The target with only the job of the given name, if it has it
+    pub fn with_only_job(&self, name: &str) -> Option<Self> {
+        let jobs: Vec<_> = self.jobs.iter().filter(|job| job.to_string() == name).copied().collect();
+        let custom_jobs: Vec<_> = self.custom_jobs.iter().filter(|job| job.name == name).cloned().collect();
+        if jobs.is_empty() && custom_jobs.is_empty() {
+            return None;
+        }
+        Some(Self {
+            jobs,
+            custom_jobs,
+            ..self.clone()
+        })
     }
 }
 
@@ -86,6 +133,7 @@ impl From<&TargetGroup> for TargetDto {
             name: "".to_string(),
             custom_labels: BTreeMap::new(),
             jobs: vec![],
+            custom_jobs: vec![],
             node_id: value.node_id,
             ic_name: value.ic_name.clone(),
             targets: value.targets.clone(),
@@ -108,6 +156,7 @@ impl Hash for TargetDto {
         self.operator_id.hash(state);
         self.node_provider_id.hash(state);
         self.jobs.hash(state);
+        self.custom_jobs.hash(state);
         self.name.hash(state);
         self.custom_labels.hash(state);
     }
@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+use ic_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use service_discovery::job_types::JobType;
+
+use super::target::TargetDto;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetChangeKind {
+    Added,
+    Removed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetChangeReason {
+    /// The definition of the target was added
+    DefinitionAdded,
+    /// The definition of the target was removed
+    DefinitionRemoved,
+    /// The node was added to the registry of its definition
+    NodeAdded,
+    /// The node was removed from the registry of its definition
+    NodeRemoved,
+    /// The node moved to another subnet, or was added to or removed from one
+    SubnetReassigned,
+    /// Anything else changed, e.g. the addresses, jobs or labels of the node
+    TargetUpdated,
+}
+
+/// A target that was added or removed. A target that changed is removed and
+/// added again with the same reason.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TargetChange {
+    pub kind: TargetChangeKind,
+    pub reason: TargetChangeReason,
+    pub target: TargetDto,
+}
+
+impl Display for TargetChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetChangeKind::Added => write!(f, "added"),
+            TargetChangeKind::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+impl Display for TargetChangeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetChangeReason::DefinitionAdded => write!(f, "definition_added"),
+            TargetChangeReason::DefinitionRemoved => write!(f, "definition_removed"),
+            TargetChangeReason::NodeAdded => write!(f, "node_added"),
+            TargetChangeReason::NodeRemoved => write!(f, "node_removed"),
+            TargetChangeReason::SubnetReassigned => write!(f, "subnet_reassigned"),
+            TargetChangeReason::TargetUpdated => write!(f, "target_updated"),
+        }
+    }
+}
+
+/// Identifies a target across two lists of targets. IC nodes are identified by
+/// their node ID, boundary nodes by their name and jobs, as they don't have a
+/// node ID and have a target per job.
+type TargetKey = (String, String, Vec<JobType>);
+
+fn target_key(target: &TargetDto, bn_principal_placeholder: &str) -> TargetKey {
+    let node_id = target.node_id.to_string();
+    match node_id == bn_principal_placeholder {
+        true => (target.ic_name.clone(), target.name.clone(), target.jobs.clone()),
+        false => (target.ic_name.clone(), node_id, vec![]),
+    }
+}
+
+/// The changes that turn the `old` targets into the `new` ones
+pub fn diff_targets(old: &[TargetDto], new: &[TargetDto]) -> Vec<TargetChange> {
+    let bn_principal_placeholder = PrincipalId::new_anonymous().to_string();
+    let index = |targets: &[TargetDto]| -> BTreeMap<TargetKey, TargetDto> {
+        targets.iter().map(|t| (target_key(t, &bn_principal_placeholder), t.clone())).collect()
+    };
+    let (old, new) = (index(old), index(new));
+    let old_ics = old.keys().map(|(ic_name, _, _)| ic_name.clone()).collect::<BTreeSet<_>>();
+    let new_ics = new.keys().map(|(ic_name, _, _)| ic_name.clone()).collect::<BTreeSet<_>>();
+    let change = |kind, reason, target: &TargetDto| TargetChange {
+        kind,
+        reason,
+        target: target.clone(),
+    };
+
+    let mut changes = vec![];
+    for (key, target) in &old {
+        match new.get(key) {
+            None if new_ics.contains(&key.0) => changes.push(change(TargetChangeKind::Removed, TargetChangeReason::NodeRemoved, target)),
+            None => changes.push(change(TargetChangeKind::Removed, TargetChangeReason::DefinitionRemoved, target)),
+            Some(new_target) if new_target != target => {
+                let reason = match new_target.subnet_id != target.subnet_id {
+                    true => TargetChangeReason::SubnetReassigned,
+                    false => TargetChangeReason::TargetUpdated,
+                };
+                changes.push(change(TargetChangeKind::Removed, reason, target));
+                changes.push(change(TargetChangeKind::Added, reason, new_target));
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, target) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+        let reason = match old_ics.contains(&key.0) {
+            true => TargetChangeReason::NodeAdded,
+            false => TargetChangeReason::DefinitionAdded,
+        };
+        changes.push(change(TargetChangeKind::Added, reason, target));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_types::SubnetId;
+
+    use super::*;
+
+    fn target(ic_name: &str, node: u64) -> TargetDto {
+        TargetDto {
+            node_id: PrincipalId::new_node_test_id(node).into(),
+            name: format!("node-{}", node),
+            ic_name: ic_name.to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "dc1".to_string(),
+            targets: BTreeSet::new(),
+            jobs: vec![JobType::Replica],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::new(),
+            is_api_bn: false,
+        }
+    }
+
+    /// The changes in a stable order, the order of the node IDs is arbitrary
+    fn reasons(changes: &[TargetChange]) -> Vec<(TargetChangeKind, TargetChangeReason, String)> {
+        let mut reasons = changes.iter().map(|c| (c.kind, c.reason, c.target.name.clone())).collect::<Vec<_>>();
+        reasons.sort();
+        reasons
+    }
+
+    #[test]
+    fn changes_have_reasons() {
+        let reassigned = TargetDto {
+            subnet_id: Some(SubnetId::from(PrincipalId::new_subnet_test_id(1))),
+            ..target("mercury", 2)
+        };
+        let updated = TargetDto {
+            jobs: vec![JobType::Replica, JobType::Orchestrator],
+            ..target("mercury", 3)
+        };
+        let old = vec![target("mercury", 1), target("mercury", 2), target("mercury", 3), target("staging", 1)];
+        let new = vec![target("mercury", 1), reassigned, updated, target("mercury", 4), target("testnet", 1)];
+
+        assert!(diff_targets(&old, &old).is_empty());
+        assert_eq!(
+            reasons(&diff_targets(&old, &new)),
+            vec![
+                (TargetChangeKind::Added, TargetChangeReason::DefinitionAdded, "node-1".to_string()),
+                (TargetChangeKind::Added, TargetChangeReason::NodeAdded, "node-4".to_string()),
+                (TargetChangeKind::Added, TargetChangeReason::SubnetReassigned, "node-2".to_string()),
+                (TargetChangeKind::Added, TargetChangeReason::TargetUpdated, "node-3".to_string()),
+                (TargetChangeKind::Removed, TargetChangeReason::DefinitionRemoved, "node-1".to_string()),
+                (TargetChangeKind::Removed, TargetChangeReason::SubnetReassigned, "node-2".to_string()),
+                (TargetChangeKind::Removed, TargetChangeReason::TargetUpdated, "node-3".to_string()),
+            ]
+        );
+        assert_eq!(
+            reasons(&diff_targets(&new[..1], &[])),
+            vec![(TargetChangeKind::Removed, TargetChangeReason::DefinitionRemoved, "node-1".to_string())]
+        );
+        assert_eq!(
+            reasons(&diff_targets(&new[..2], &new[1..2])),
+            vec![(TargetChangeKind::Removed, TargetChangeReason::NodeRemoved, "node-1".to_string())]
+        );
+    }
+}
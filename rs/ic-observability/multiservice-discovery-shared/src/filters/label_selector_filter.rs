@@ -0,0 +1,383 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{contracts::DataContract, filters::TargetGroupFilter};
+
+/// Filters targets with an expression of label selectors, for example:
+///
+/// ```text
+/// ic_name=mercury, job=~"replica|orchestrator" and not (dc_id=zh2 or subnet_id!="")
+/// ```
+///
+/// A selector compares a field of the target (`node_id`, `ic_name`, `name`,
+/// `subnet_id`, `dc_id`, `operator_id`, `node_provider_id`, `is_api_bn`,
+/// `job`) or a custom label with `=`, `!=`, `=~` or `!~`. Like in Prometheus,
+/// regexes have to match the whole value and a label that isn't set has the
+/// value `""`. Selectors are combined with `and` (or `,` / `&&`), `or` (or
+/// `||`) and `not` (or `!`), where `not` binds strongest and `or` weakest.
+/// Values containing spaces or any of `=!~(),&|` have to be quoted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct LabelSelectorFilter {
+    expression: Expression,
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Selector { label: String, matcher: Matcher },
+    Not(Box<Expression>),
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Equal(String),
+    NotEqual(String),
+    Regex(Regex),
+    NotRegex(Regex),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelSelectorParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl Display for LabelSelectorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid label selector at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for LabelSelectorParseError {}
+
+impl FromStr for LabelSelectorFilter {
+    type Err = LabelSelectorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            next: 0,
+            end: s.len(),
+        };
+        let expression = parser.or()?;
+        match parser.peek() {
+            None => Ok(Self { expression }),
+            Some((position, token)) => Err(LabelSelectorParseError {
+                position: *position,
+                message: format!("unexpected {}", token),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for LabelSelectorFilter {
+    type Error = LabelSelectorParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TargetGroupFilter for LabelSelectorFilter {
+    fn filter(&self, target_group: &dyn DataContract) -> bool {
+        self.expression.matches(target_group)
+    }
+}
+
+impl Expression {
+    fn matches(&self, target_group: &dyn DataContract) -> bool {
+        match self {
+            Expression::Selector { label, matcher } => {
+                let mut values = target_group.get_label_values(label);
+                if values.is_empty() {
+                    values.push(String::new());
+                }
+                match matcher {
+                    Matcher::Equal(value) => values.iter().any(|v| v == value),
+                    Matcher::NotEqual(value) => values.iter().all(|v| v != value),
+                    Matcher::Regex(regex) => values.iter().any(|v| regex.is_match(v)),
+                    Matcher::NotRegex(regex) => values.iter().all(|v| !regex.is_match(v)),
+                }
+            }
+            Expression::Not(expression) => !expression.matches(target_group),
+            Expression::And(expressions) => expressions.iter().all(|e| e.matches(target_group)),
+            Expression::Or(expressions) => expressions.iter().any(|e| e.matches(target_group)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Equal,
+    NotEqual,
+    Regex,
+    NotRegex,
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(value) => write!(f, "{:?}", value),
+            Token::Equal => write!(f, "'='"),
+            Token::NotEqual => write!(f, "'!='"),
+            Token::Regex => write!(f, "'=~'"),
+            Token::NotRegex => write!(f, "'!~'"),
+            Token::And => write!(f, "'and'"),
+            Token::Or => write!(f, "'or'"),
+            Token::Not => write!(f, "'not'"),
+            Token::LeftParen => write!(f, "'('"),
+            Token::RightParen => write!(f, "')'"),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"=!~(),&|\"".contains(c)
+}
+
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, LabelSelectorParseError> {
+    let error = |position: usize, message: &str| LabelSelectorParseError {
+        position,
+        message: message.to_string(),
+    };
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::And,
+            '&' | '|' => match chars.next_if(|(_, next)| *next == c) {
+                Some(_) if c == '&' => Token::And,
+                Some(_) => Token::Or,
+                None => return Err(error(position, &format!("expected '{}{}'", c, c))),
+            },
+            '=' => match chars.next_if(|(_, next)| *next == '~') {
+                Some(_) => Token::Regex,
+                None => Token::Equal,
+            },
+            '!' => match chars.next_if(|(_, next)| *next == '=' || *next == '~') {
+                Some((_, '=')) => Token::NotEqual,
+                Some(_) => Token::NotRegex,
+                None => Token::Not,
+            },
+            '~' => return Err(error(position, "unexpected '~'")),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => value.push(escaped),
+                            None => return Err(error(position, "unterminated string")),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(error(position, "unterminated string")),
+                    }
+                }
+                Token::Quoted(value)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    word.push(c);
+                }
+                match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word(word),
+                }
+            }
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// The length of the selector, the position of errors at its end
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.next)
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        let matches = self.peek().is_some_and(|(_, t)| t == token);
+        if matches {
+            self.next += 1;
+        }
+        matches
+    }
+
+    fn error(&self, expected: &str) -> LabelSelectorParseError {
+        match self.peek() {
+            Some((position, token)) => LabelSelectorParseError {
+                position: *position,
+                message: format!("expected {}, found {}", expected, token),
+            },
+            None => LabelSelectorParseError {
+                position: self.end,
+                message: format!("expected {}, found the end of the selector", expected),
+            },
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, LabelSelectorParseError> {
+        let mut expressions = vec![self.and()?];
+        while self.next_if(&Token::Or) {
+            expressions.push(self.and()?);
+        }
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::Or(expressions),
+        })
+    }
+
+    fn and(&mut self) -> Result<Expression, LabelSelectorParseError> {
+        let mut expressions = vec![self.not()?];
+        while self.next_if(&Token::And) {
+            expressions.push(self.not()?);
+        }
+        Ok(match expressions.len() {
+            1 => expressions.remove(0),
+            _ => Expression::And(expressions),
+        })
+    }
+
+    fn not(&mut self) -> Result<Expression, LabelSelectorParseError> {
+        if self.next_if(&Token::Not) {
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        }
+        if self.next_if(&Token::LeftParen) {
+            let expression = self.or()?;
+            if !self.next_if(&Token::RightParen) {
+                return Err(self.error("')'"));
+            }
+            return Ok(expression);
+        }
+        self.selector()
+    }
+
+    fn selector(&mut self) -> Result<Expression, LabelSelectorParseError> {
+        let label = match self.peek() {
+            Some((_, Token::Word(label))) => label.clone(),
+            _ => return Err(self.error("a label")),
+        };
+        self.next += 1;
+        let operator = match self.peek() {
+            Some((_, operator @ (Token::Equal | Token::NotEqual | Token::Regex | Token::NotRegex))) => operator.clone(),
+            _ => return Err(self.error("'=', '!=', '=~' or '!~'")),
+        };
+        self.next += 1;
+        let (position, value) = match self.peek() {
+            Some((position, Token::Word(value) | Token::Quoted(value))) => (*position, value.clone()),
+            _ => return Err(self.error("a value")),
+        };
+        self.next += 1;
+
+        let regex = || {
+            Regex::new(&format!("^(?:{})$", value)).map_err(|e| LabelSelectorParseError {
+                position,
+                message: e.to_string(),
+            })
+        };
+        let matcher = match operator {
+            Token::Equal => Matcher::Equal(value.clone()),
+            Token::NotEqual => Matcher::NotEqual(value.clone()),
+            Token::Regex => Matcher::Regex(regex()?),
+            _ => Matcher::NotRegex(regex()?),
+        };
+        Ok(Expression::Selector { label, matcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use ic_types::PrincipalId;
+    use service_discovery::job_types::{JobType, NodeOS};
+
+    use crate::contracts::target::TargetDto;
+
+    use super::*;
+
+    fn target() -> TargetDto {
+        TargetDto {
+            node_id: PrincipalId::new_node_test_id(1).into(),
+            name: "node".to_string(),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "zh2".to_string(),
+            targets: BTreeSet::new(),
+            jobs: vec![JobType::Replica, JobType::NodeExporter(NodeOS::Host)],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::from([("env".to_string(), "prod".to_string())]),
+            is_api_bn: false,
+        }
+    }
+
+    fn matches(selector: &str) -> bool {
+        LabelSelectorFilter::from_str(selector).unwrap().filter(&target())
+    }
+
+    #[test]
+    fn selectors() {
+        assert!(matches("ic_name=mercury"));
+        assert!(matches("dc_id != zh1"));
+        assert!(matches("dc_id=~\"zh.*\""));
+        assert!(!matches("dc_id=~zh"));
+        assert!(matches("env!~test"));
+        assert!(matches("subnet_id=\"\""));
+        assert!(matches("missing=\"\""));
+        assert!(matches("job=host_node_exporter"));
+        assert!(!matches("job!=replica"));
+        assert!(matches("is_api_bn=false"));
+    }
+
+    #[test]
+    fn combinations() {
+        assert!(matches("ic_name=mercury, dc_id=zh2"));
+        assert!(!matches("ic_name=mercury && dc_id=zh1"));
+        assert!(matches("dc_id=zh1 || dc_id=zh2"));
+        assert!(matches("not dc_id=zh1 and NOT (env=test or env=staging)"));
+        assert!(!matches("!(dc_id=zh1 or dc_id=zh2)"));
+        assert!(matches("dc_id=zh1 and env=test or env=prod"));
+        assert!(!matches("dc_id=zh1 and (env=test or env=prod)"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |selector: &str| LabelSelectorFilter::from_str(selector).unwrap_err();
+        assert_eq!(error("dc_id zh2").position, 6);
+        assert_eq!(error("dc_id=").position, 6);
+        assert_eq!(error("(dc_id=zh2").position, 10);
+        assert_eq!(error("dc_id=zh2)").position, 9);
+        assert_eq!(error("dc_id=\"zh2").position, 6);
+        assert_eq!(error("dc_id=~\"(\"").position, 7);
+        assert_eq!(error("dc_id=zh2 & env=prod").position, 10);
+        assert!(LabelSelectorFilter::from_str("").is_err());
+    }
+}
@@ -3,7 +3,9 @@ use std::fmt::Debug;
 use crate::contracts::DataContract;
 
 pub mod ic_name_regex_filter;
+pub mod label_selector_filter;
 pub mod node_regex_id_filter;
+pub mod shard_filter;
 
 pub trait TargetGroupFilter: Send + Sync + Debug {
     fn filter(&self, target_groups: &dyn DataContract) -> bool;
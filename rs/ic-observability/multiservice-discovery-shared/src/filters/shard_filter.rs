@@ -0,0 +1,115 @@
+use std::fmt::{self, Display};
+
+use ic_types::PrincipalId;
+
+use crate::{contracts::DataContract, filters::TargetGroupFilter};
+
+/// Keeps the targets of one of `count` shards.
+///
+/// Nodes are assigned to a shard with a jump consistent hash of their node ID,
+/// or of their name for boundary nodes, which have no node ID. All jobs of a
+/// node land on the same shard, adding nodes doesn't move the other nodes, and
+/// adding a shard only moves the nodes that end up on the new shard.
+#[derive(Debug, Clone)]
+pub struct ShardFilter {
+    index: u32,
+    count: u32,
+    bn_principal_placeholder: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidShard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Display for InvalidShard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid shard {} of {}: the shard count has to be positive and the shard index lower than it",
+            self.index, self.count
+        )
+    }
+}
+
+impl std::error::Error for InvalidShard {}
+
+impl ShardFilter {
+    pub fn new(index: u32, count: u32) -> Result<Self, InvalidShard> {
+        if index >= count {
+            return Err(InvalidShard { index, count });
+        }
+        Ok(Self {
+            index,
+            count,
+            bn_principal_placeholder: PrincipalId::new_anonymous().to_string(),
+        })
+    }
+}
+
+impl TargetGroupFilter for ShardFilter {
+    fn filter(&self, target_group: &dyn DataContract) -> bool {
+        let id = target_group.get_id();
+        let key = match id == self.bn_principal_placeholder {
+            true => target_group.get_target_name(),
+            false => id,
+        };
+        shard_of(&key, self.count) == self.index
+    }
+}
+
+/// The shard of the key. Unlike `DefaultHasher` the hash is stable across
+/// releases, so all downloaders agree on the shards.
+pub fn shard_of(key: &str, count: u32) -> u32 {
+    jump_consistent_hash(fnv1a(key.as_bytes()), count)
+}
+
+/// The 64 bit FNV-1a hash of the bytes, which is the same on all platforms
+/// and releases
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// "A Fast, Minimal Memory, Consistent Hash Algorithm" by Lamping and Veach
+fn jump_consistent_hash(mut key: u64, buckets: u32) -> u32 {
+    let (mut bucket, mut next) = (-1_i64, 0_i64);
+    while next < buckets as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1_u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_are_consistent() {
+        let nodes = (0..1000).map(|i| PrincipalId::new_node_test_id(i).to_string()).collect::<Vec<_>>();
+
+        let shards = nodes.iter().map(|node| shard_of(node, 4)).collect::<Vec<_>>();
+        for shard in 0..4 {
+            let size = shards.iter().filter(|s| **s == shard).count();
+            assert!((200..300).contains(&size), "shard {} has {} nodes", shard, size);
+        }
+
+        // Adding a shard only moves nodes to the new shard
+        for (node, shard) in nodes.iter().zip(&shards) {
+            let new_shard = shard_of(node, 5);
+            assert!(new_shard == *shard || new_shard == 4);
+        }
+        assert_eq!(shard_of(&nodes[0], 1), 0);
+    }
+
+    #[test]
+    fn invalid_shards() {
+        assert!(ShardFilter::new(0, 0).is_err());
+        assert!(ShardFilter::new(2, 2).is_err());
+        assert!(ShardFilter::new(1, 2).is_ok());
+    }
+}
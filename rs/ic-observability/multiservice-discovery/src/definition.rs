@@ -1,15 +1,19 @@
 use crossbeam_channel::Receiver;
 use crossbeam_channel::Sender;
 use futures_util::future::join_all;
-use ic_registry_client::client::ThresholdSigPublicKey;
-use multiservice_discovery_shared::contracts::target::map_to_target_dto;
+use ic_registry_client::client::{RegistryVersion, ThresholdSigPublicKey};
+use ic_types::{NodeId, PrincipalId};
 use multiservice_discovery_shared::contracts::target::TargetDto;
+use multiservice_discovery_shared::filters::label_selector_filter::LabelSelectorFilter;
+use multiservice_discovery_shared::filters::shard_filter::ShardFilter;
+use multiservice_discovery_shared::filters::TargetGroupFilter;
 use serde::Deserialize;
 use serde::Serialize;
-use service_discovery::job_types::{JobType, NodeOS};
+use service_discovery::job_types::{JobDefinition, JobDefinitionError, JobType, NodeKind, NodeOS};
 use service_discovery::registry_sync::SyncError;
 use service_discovery::IcServiceDiscovery;
 use service_discovery::IcServiceDiscoveryError;
+use service_discovery::NodeMetadata;
 use service_discovery::TargetGroup;
 use service_discovery::{registry_sync::sync_local_registry, IcServiceDiscoveryImpl};
 use slog::error;
@@ -20,8 +24,6 @@ use std::collections::HashSet;
 use std::error::Error;
 use std::fmt::Debug;
 use std::fmt::{Display, Error as FmtError, Formatter};
-use std::fs;
-use std::io::Write;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::{
@@ -31,6 +33,8 @@ use std::{
 use tokio::sync::Mutex;
 use url::Url;
 
+use crate::definition_store::{Change, ChangeKind, DefinitionStore};
+use crate::enrichment::Enrichers;
 use crate::make_logger;
 use crate::metrics::RunningDefinitionsMetrics;
 
@@ -72,13 +76,15 @@ pub struct Definition {
     pub boundary_nodes: Vec<BoundaryNode>,
 }
 
-impl From<FSDefinition> for Definition {
-    fn from(fs_definition: FSDefinition) -> Self {
+impl TryFrom<FSDefinition> for Definition {
+    type Error = IcServiceDiscoveryError;
+
+    fn try_from(fs_definition: FSDefinition) -> Result<Self, Self::Error> {
         if std::fs::metadata(&fs_definition.registry_path).is_err() {
-            std::fs::create_dir_all(fs_definition.registry_path.clone()).unwrap();
+            std::fs::create_dir_all(fs_definition.registry_path.clone())?;
         }
         let log = make_logger();
-        Self {
+        Ok(Self {
             nns_urls: fs_definition.nns_urls,
             registry_path: fs_definition.registry_path.clone(),
             name: fs_definition.name,
@@ -86,9 +92,13 @@ impl From<FSDefinition> for Definition {
             public_key: fs_definition.public_key,
             poll_interval: fs_definition.poll_interval,
             registry_query_timeout: fs_definition.registry_query_timeout,
-            ic_discovery: Arc::new(IcServiceDiscoveryImpl::new(log, fs_definition.registry_path, fs_definition.registry_query_timeout).unwrap()),
-            boundary_nodes: vec![],
-        }
+            ic_discovery: Arc::new(IcServiceDiscoveryImpl::new(
+                log,
+                fs_definition.registry_path,
+                fs_definition.registry_query_timeout,
+            )?),
+            boundary_nodes: fs_definition.boundary_nodes,
+        })
     }
 }
 
@@ -124,12 +134,31 @@ impl Display for BoundaryNodeAlreadyExists {
     }
 }
 
+#[derive(Debug)]
+pub(crate) enum BoundaryNodeChangeError {
+    NotFound(String),
+    DefinitionEnded(String),
+}
+
+impl Error for BoundaryNodeChangeError {}
+
+impl Display for BoundaryNodeChangeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Self::NotFound(name) => write!(f, "boundary node {} not found", name),
+            Self::DefinitionEnded(name) => write!(f, "definition {} has ended", name),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RunningDefinition {
     pub(crate) definition: Definition,
     stop_signal: Receiver<()>,
     ender: Arc<Mutex<Option<Ender>>>,
     metrics: RunningDefinitionsMetrics,
+    enrichers: Enrichers,
+    job_definitions: Vec<JobDefinition>,
 }
 
 pub struct TestDefinition {
@@ -137,17 +166,25 @@ pub struct TestDefinition {
 }
 
 impl TestDefinition {
-    pub(crate) fn new(definition: Definition, metrics: RunningDefinitionsMetrics) -> Self {
+    /// A definition with the built-in jobs and the `custom_jobs`
+    pub(crate) fn new(
+        definition: Definition,
+        metrics: RunningDefinitionsMetrics,
+        enrichers: Enrichers,
+        custom_jobs: Vec<JobDefinition>,
+    ) -> Result<Self, JobDefinitionError> {
         let (_, stop_signal) = crossbeam::channel::bounded::<()>(0);
         let ender: Arc<Mutex<Option<Ender>>> = Arc::new(Mutex::new(None));
-        Self {
+        Ok(Self {
             running_def: RunningDefinition {
                 definition,
                 stop_signal,
                 ender,
                 metrics,
+                enrichers,
+                job_definitions: JobDefinition::with_defaults(custom_jobs)?,
             },
-        }
+        })
     }
 
     /// Syncs the registry update the in-memory cache then stops.
@@ -211,7 +248,13 @@ impl Definition {
         }
     }
 
-    pub(crate) async fn run(self, rt: tokio::runtime::Handle, metrics: RunningDefinitionsMetrics) -> RunningDefinition {
+    pub(crate) async fn run(
+        self,
+        rt: tokio::runtime::Handle,
+        metrics: RunningDefinitionsMetrics,
+        enrichers: Enrichers,
+        job_definitions: Vec<JobDefinition>,
+    ) -> RunningDefinition {
         fn wrap(definition: RunningDefinition, rt: tokio::runtime::Handle) -> impl FnMut() {
             move || {
                 rt.block_on(definition.run());
@@ -226,6 +269,8 @@ impl Definition {
             stop_signal,
             ender: ender.clone(),
             metrics,
+            enrichers,
+            job_definitions,
         };
         let join_handle = std::thread::spawn(wrap(d.clone(), rt));
         ender.lock().await.replace(Ender {
@@ -250,8 +295,25 @@ impl RunningDefinition {
         }
     }
 
-    pub(crate) fn get_target_groups(&self, job_type: JobType) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError> {
-        self.definition.ic_discovery.get_target_groups(job_type, self.definition.log.clone())
+    /// The definition of the built-in job, as changed in configuration
+    pub(crate) fn job_definition(&self, job_type: JobType) -> JobDefinition {
+        let name = job_type.to_string();
+        match self.job_definitions.iter().find(|job| job.name == name) {
+            Some(job) => job.clone(),
+            None => job_type.definition(),
+        }
+    }
+
+    pub(crate) fn get_target_groups_for_job(&self, job: &JobDefinition) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError> {
+        self.definition.ic_discovery.get_target_groups_for_job(job, self.definition.log.clone())
+    }
+
+    pub(crate) fn get_node_metadata(&self) -> Result<BTreeMap<NodeId, NodeMetadata>, IcServiceDiscoveryError> {
+        self.definition.ic_discovery.get_node_metadata()
+    }
+
+    pub(crate) fn get_registry_versions(&self) -> BTreeMap<String, RegistryVersion> {
+        self.definition.ic_discovery.get_registry_versions()
     }
 
     async fn initial_registry_sync(&self, use_current_version: bool) -> Result<(), SyncError> {
@@ -393,11 +455,64 @@ impl RunningDefinition {
         }
     }
 
+    /// Replaces the boundary node with the same name
+    pub(crate) async fn update_boundary_node(&mut self, target: BoundaryNode) -> Result<(), BoundaryNodeChangeError> {
+        // Lock modifications to this object while mods are happening.
+        match self.ender.lock().await.as_ref() {
+            Some(_) => match self.definition.boundary_nodes.iter_mut().find(|bn| bn.name == target.name) {
+                Some(bn) => {
+                    *bn = target;
+                    Ok(())
+                }
+                None => Err(BoundaryNodeChangeError::NotFound(target.name)),
+            },
+            None => Err(BoundaryNodeChangeError::DefinitionEnded(self.name())),
+        }
+    }
+
+    pub(crate) async fn remove_boundary_node(&mut self, name: &str) -> Result<(), BoundaryNodeChangeError> {
+        // Lock modifications to this object while mods are happening.
+        match self.ender.lock().await.as_ref() {
+            Some(_) => match self.definition.boundary_nodes.iter().position(|bn| bn.name == name) {
+                Some(index) => {
+                    self.definition.boundary_nodes.remove(index);
+                    Ok(())
+                }
+                None => Err(BoundaryNodeChangeError::NotFound(name.to_string())),
+            },
+            None => Err(BoundaryNodeChangeError::DefinitionEnded(self.name())),
+        }
+    }
+
     pub fn name(&self) -> String {
         self.definition.name.clone()
     }
 }
 
+#[cfg(test)]
+impl RunningDefinition {
+    /// A definition of the `supervisor` that counts as running until it's
+    /// ended, without syncing its registry
+    pub(crate) fn idle(definition: Definition, supervisor: &DefinitionsSupervisor) -> Self {
+        let (stop_signal_sender, stop_signal) = crossbeam::channel::bounded::<()>(0);
+        let receiver = stop_signal.clone();
+        let join_handle = std::thread::spawn(move || {
+            let _ = receiver.recv();
+        });
+        Self {
+            definition,
+            stop_signal,
+            ender: Arc::new(Mutex::new(Some(Ender {
+                stop_signal_sender,
+                join_handle,
+            }))),
+            metrics: RunningDefinitionsMetrics::new(),
+            enrichers: Enrichers::default(),
+            job_definitions: supervisor.job_definitions.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct BoundaryNode {
     pub name: String,
@@ -481,62 +596,79 @@ pub(super) struct DefinitionsSupervisor {
     rt: tokio::runtime::Handle,
     pub(super) definitions: Arc<Mutex<BTreeMap<String, RunningDefinition>>>,
     allow_mercury_deletion: bool,
-    networks_state_file: Option<PathBuf>,
+    store: Option<DefinitionStore>,
+    enrichers: Enrichers,
+    pub(super) job_definitions: Vec<JobDefinition>,
     log: Logger,
 }
 
 impl DefinitionsSupervisor {
-    pub(crate) fn new(rt: tokio::runtime::Handle, allow_mercury_deletion: bool, networks_state_file: Option<PathBuf>, log: Logger) -> Self {
-        DefinitionsSupervisor {
+    /// A supervisor whose definitions have the built-in jobs and the
+    /// `custom_jobs`
+    pub(crate) fn new(
+        rt: tokio::runtime::Handle,
+        allow_mercury_deletion: bool,
+        networks_state_file: Option<PathBuf>,
+        enrichers: Enrichers,
+        custom_jobs: Vec<JobDefinition>,
+        log: Logger,
+    ) -> Result<Self, JobDefinitionError> {
+        Ok(DefinitionsSupervisor {
             rt,
             definitions: Arc::new(Mutex::new(BTreeMap::new())),
             allow_mercury_deletion,
-            networks_state_file,
+            store: networks_state_file.map(|path| DefinitionStore::new(path, log.clone())),
+            enrichers,
+            job_definitions: JobDefinition::with_defaults(custom_jobs)?,
             log,
-        }
+        })
     }
 
     pub(crate) async fn load_or_create_defs(&self, metrics: RunningDefinitionsMetrics) -> Result<(), Box<dyn Error>> {
-        if let Some(networks_state_file) = self.networks_state_file.clone() {
-            if networks_state_file.exists() {
-                let file_content = fs::read_to_string(networks_state_file.clone())?;
-                let initial_definitions: Vec<FSDefinition> = serde_json::from_str(&file_content)?;
-                let names = initial_definitions.iter().map(|def| def.name.clone()).collect::<Vec<_>>();
-                info!(self.log, "Definitions loaded from {:?}:\n{:?}", networks_state_file.as_path(), names);
-                self.start(
-                    initial_definitions.into_iter().map(|def| def.into()).collect(),
-                    StartMode::AddToDefinitions,
-                    metrics,
-                )
-                .await?;
+        if let Some(store) = &self.store {
+            let mut initial_definitions = vec![];
+            for fs_definition in store.load()? {
+                let name = fs_definition.name.clone();
+                match Definition::try_from(fs_definition) {
+                    Ok(definition) => initial_definitions.push(definition),
+                    Err(e) => warn!(self.log, "Skipping stored definition '{}': {:?}", name, e),
+                }
             }
+            let names = initial_definitions.iter().map(|def| def.name.clone()).collect::<Vec<_>>();
+            info!(self.log, "Definitions loaded from the networks state file:\n{:?}", names);
+            self.start(initial_definitions, StartMode::AddToDefinitions, metrics, None).await?;
         }
         Ok(())
     }
 
+    /// Writes the definitions to the networks state file and appends the
+    /// changes that led to them to its history. Failures are only logged, as
+    /// the running definitions are already changed.
     // FIXME: if the file contents on disk are the same as the contents about to
     // be persisted, then the file should not be overwritten because it was
     // already updated by another MSD sharing the same directory.
-    pub(crate) async fn persist_defs(&self, existing: &mut BTreeMap<String, RunningDefinition>) -> Result<(), Box<dyn Error>> {
-        if let Some(networks_state_file) = self.networks_state_file.clone() {
-            retry::retry(retry::delay::Exponential::from_millis(10).take(5), || {
-                std::fs::OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .open(networks_state_file.as_path())
-                    .and_then(|mut file| {
-                        let fs_def: Vec<FSDefinition> = existing
-                            .values()
-                            .cloned()
-                            .map(|running_def| running_def.definition.into())
-                            .collect::<Vec<_>>();
-
-                        file.write_all(serde_json::to_string(&fs_def)?.as_bytes()).map(|_| file)
-                    })
-                    .and_then(|mut file| file.flush())
-            })?;
+    pub(crate) fn persist(&self, existing: &BTreeMap<String, RunningDefinition>, changes: &[Change]) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let fs_defs = existing
+            .values()
+            .map(|running_def| running_def.definition.clone().into())
+            .collect::<Vec<FSDefinition>>();
+        if let Err(e) = store.save(&fs_defs) {
+            warn!(self.log, "Error while persisting definitions to disk '{}'", e);
+        }
+        if let Err(e) = store.record(changes) {
+            warn!(self.log, "Error while recording the history of definitions '{}'", e);
+        }
+    }
+
+    /// The recorded changes of the definitions, oldest first.
+    pub(crate) fn history(&self) -> std::io::Result<Vec<Change>> {
+        match &self.store {
+            Some(store) => store.history(),
+            None => Ok(vec![]),
         }
-        Ok(())
     }
 
     async fn start_inner(
@@ -545,6 +677,7 @@ impl DefinitionsSupervisor {
         definitions: Vec<Definition>,
         start_mode: StartMode,
         metrics: RunningDefinitionsMetrics,
+        changed_by: Option<&str>,
     ) -> Result<(), StartDefinitionsError> {
         let mut error = StartDefinitionsError { errors: vec![] };
         let mut ic_names_to_add: HashSet<String> = HashSet::new();
@@ -595,15 +728,31 @@ impl DefinitionsSupervisor {
         // End them and join them all.
         join_all(defs_to_end.iter_mut().map(|def| async { def.end().await })).await;
         drop(defs_to_end);
+        // Loading definitions from disk is not a change.
+        let changes = match changed_by {
+            Some(changed_by) => ic_names_to_end
+                .iter()
+                .filter(|ic_name| !ic_names_to_add.contains(*ic_name))
+                .map(|ic_name| Change::new(changed_by, ChangeKind::Removed, ic_name.clone()))
+                .chain(definitions.iter().map(|definition| match ic_names_to_end.contains(&definition.name) {
+                    true => Change::new(changed_by, ChangeKind::Replaced, definition.name.clone()),
+                    false => Change::new(changed_by, ChangeKind::Added, definition.name.clone()),
+                }))
+                .collect(),
+            None => vec![],
+        };
         drop(ic_names_to_end);
         // Now we add the incoming definitions.
         for definition in definitions.into_iter() {
-            existing.insert(definition.name.clone(), definition.run(self.rt.clone(), metrics.clone()).await);
+            existing.insert(
+                definition.name.clone(),
+                definition
+                    .run(self.rt.clone(), metrics.clone(), self.enrichers.clone(), self.job_definitions.clone())
+                    .await,
+            );
         }
         // Now we rewrite definitions to disk.
-        if let Err(e) = self.persist_defs(existing).await {
-            warn!(self.log, "Error while peristing definitions to disk '{}'", e);
-        }
+        self.persist(existing, &changes);
         Ok(())
     }
 
@@ -613,14 +762,18 @@ impl DefinitionsSupervisor {
     /// of any of the incoming definitions will be stopped.  If it is false,
     /// any incoming definition named after any running definition will
     /// add an AlreadyExists error to the errors list.
+    ///
+    /// `changed_by` is recorded in the history of the definitions, unless it
+    /// is None because the definitions are loaded from disk.
     pub(crate) async fn start(
         &self,
         definitions: Vec<Definition>,
         start_mode: StartMode,
         metrics: RunningDefinitionsMetrics,
+        changed_by: Option<&str>,
     ) -> Result<(), StartDefinitionsError> {
         let mut existing = self.definitions.lock().await;
-        self.start_inner(&mut existing, definitions, start_mode, metrics).await
+        self.start_inner(&mut existing, definitions, start_mode, metrics, changed_by).await
     }
 
     /// Stop all definitions and end.
@@ -632,7 +785,7 @@ impl DefinitionsSupervisor {
         existing.clear()
     }
 
-    pub(crate) async fn stop(&self, definition_names: Vec<String>) -> Result<(), StopDefinitionsError> {
+    pub(crate) async fn stop(&self, definition_names: Vec<String>, changed_by: &str) -> Result<(), StopDefinitionsError> {
         let mut defs = self.definitions.lock().await;
         let mut errors: Vec<StopDefinitionError> = definition_names
             .clone()
@@ -650,20 +803,65 @@ impl DefinitionsSupervisor {
             return Err(StopDefinitionsError { errors });
         }
 
+        let changes = definition_names
+            .iter()
+            .map(|name| Change::new(changed_by, ChangeKind::Removed, name.clone()))
+            .collect::<Vec<_>>();
         for name in definition_names.into_iter() {
             defs.remove(&name).unwrap().end().await
         }
+        self.persist(&defs, &changes);
         Ok(())
     }
 }
 
 #[derive(Deserialize)]
+#[serde(try_from = "TargetFilterQuery")]
 pub struct TargetFilterSpec {
     pub node_provider_id: Option<String>,
     pub operator_id: Option<String>,
     pub dc_id: Option<String>,
     pub ic_name: Option<String>,
     pub subnet_id: Option<String>,
+    /// A label selector expression, applied after the other filters
+    pub selector: Option<LabelSelectorFilter>,
+    /// Only the targets of this shard are kept
+    pub shard: Option<ShardFilter>,
+}
+
+/// The filters as they are passed in the query string, the shard is given
+/// by `shard_index` and `shard_count`
+#[derive(Deserialize)]
+struct TargetFilterQuery {
+    node_provider_id: Option<String>,
+    operator_id: Option<String>,
+    dc_id: Option<String>,
+    ic_name: Option<String>,
+    subnet_id: Option<String>,
+    selector: Option<LabelSelectorFilter>,
+    shard_index: Option<u32>,
+    shard_count: Option<u32>,
+}
+
+impl TryFrom<TargetFilterQuery> for TargetFilterSpec {
+    type Error = String;
+
+    fn try_from(query: TargetFilterQuery) -> Result<Self, Self::Error> {
+        let shard = match (query.shard_index, query.shard_count) {
+            (None, None) => None,
+            (Some(index), Some(count)) => Some(ShardFilter::new(index, count).map_err(|e| e.to_string())?),
+            _ => return Err("shard_index and shard_count have to be set together".to_string()),
+        };
+        Ok(Self {
+            node_provider_id: query.node_provider_id,
+            operator_id: query.operator_id,
+            dc_id: query.dc_id,
+            ic_name: query.ic_name,
+            subnet_id: query.subnet_id,
+            selector: query.selector,
+            shard,
+        })
+    }
 }
 
 impl TargetFilterSpec {
@@ -708,6 +906,25 @@ impl TargetFilterSpec {
         d
     }
 
+    pub fn matches_selector(&self, t: &TargetDto) -> bool {
+        match &self.selector {
+            None => true,
+            Some(selector) => selector.filter(t),
+        }
+    }
+
+    pub fn matches_shard(&self, t: &TargetDto) -> bool {
+        match &self.shard {
+            None => true,
+            Some(shard) => shard.filter(t),
+        }
+    }
+
+    /// Whether a target that was collected without filters passes them
+    pub fn matches_target(&self, t: &TargetDto) -> bool {
+        self.matches_ic(&t.ic_name) && self.matches_ic_node(t) && self.matches_selector(t) && self.matches_shard(t)
+    }
+
     pub fn matches_ic(&self, ic_name: &String) -> bool {
         match &self.ic_name {
             None => true,
@@ -722,54 +939,96 @@ impl TargetFilterSpec {
             dc_id: None,
             ic_name: None,
             subnet_id: None,
+            selector: None,
+            shard: None,
         }
     }
 }
 
+/// The targets of the IC nodes, boundary nodes and API boundary nodes
+pub fn target_dtos_from_definitions(definitions: &BTreeMap<String, RunningDefinition>, filters: &TargetFilterSpec) -> Vec<TargetDto> {
+    let ic_node_targets = ic_node_target_dtos_from_definitions(definitions, filters);
+
+    let boundary_nodes_targets = boundary_nodes_from_definitions(definitions, filters)
+        .iter()
+        .map(|(definition_name, bn)| boundary_node_target_dto(&definitions[definition_name], bn))
+        .collect();
+
+    let api_boundary_nodes = api_boundary_nodes_target_dtos_from_definitions(definitions, filters);
+
+    [ic_node_targets, boundary_nodes_targets, api_boundary_nodes].concat()
+}
+
 pub fn ic_node_target_dtos_from_definitions(definitions: &BTreeMap<String, RunningDefinition>, filters: &TargetFilterSpec) -> Vec<TargetDto> {
-    from_definitions_into_targets(definitions, filters, JobType::all_for_ic_nodes(), |target| target.is_api_bn)
+    from_definitions_into_targets(definitions, filters, NodeKind::IcNode, |_| true)
 }
 
 pub fn api_boundary_nodes_target_dtos_from_definitions(
     definitions: &BTreeMap<String, RunningDefinition>,
     filters: &TargetFilterSpec,
 ) -> Vec<TargetDto> {
-    from_definitions_into_targets(definitions, filters, JobType::all_for_api_boundary_nodes(), |target| !target.is_api_bn)
+    from_definitions_into_targets(definitions, filters, NodeKind::ApiBoundaryNode, |_| true)
 }
 
+/// Targets of the IC nodes and API boundary nodes for a single job, built in
+/// or custom
+pub fn target_dtos_for_job_from_definitions(
+    definitions: &BTreeMap<String, RunningDefinition>,
+    filters: &TargetFilterSpec,
+    job: &JobDefinition,
+) -> Vec<TargetDto> {
+    [NodeKind::IcNode, NodeKind::ApiBoundaryNode]
+        .into_iter()
+        .filter(|node_kind| job.applies_to(*node_kind))
+        .flat_map(|node_kind| from_definitions_into_targets(definitions, filters, node_kind, |j| j.name == job.name))
+        .collect()
+}
+
+/// The targets of the nodes of the kind, with the jobs of their definition
+/// that apply to it and are `included`
 fn from_definitions_into_targets(
     definitions: &BTreeMap<String, RunningDefinition>,
     filters: &TargetFilterSpec,
-    jobs: Vec<JobType>,
-    skip_filter: impl Fn(&TargetGroup) -> bool,
+    node_kind: NodeKind,
+    included: impl Fn(&JobDefinition) -> bool,
 ) -> Vec<TargetDto> {
     let mut result: Vec<TargetDto> = vec![];
 
     for (_, def) in definitions.iter() {
         if filters.matches_ic(&def.name()) {
-            for job_type in &jobs {
-                let target_groups = match def.get_target_groups(*job_type) {
+            let first_target = result.len();
+            for job in def.job_definitions.iter().filter(|job| job.applies_to(node_kind) && included(job)) {
+                let target_groups = match def.get_target_groups_for_job(job) {
                     Ok(target_groups) => target_groups,
                     Err(_) => continue,
                 };
 
-                target_groups.iter().for_each(|target_group| {
-                    if skip_filter(target_group) {
-                        return;
-                    }
-                    if let Some(target) = result.iter_mut().find(|t| t.node_id == target_group.node_id) {
-                        target.jobs.push(*job_type);
-                    } else {
-                        let target = map_to_target_dto(target_group, *job_type, BTreeMap::new(), target_group.node_id.to_string(), def.name());
-                        if filters.matches_ic_node(&target) {
-                            result.push(target)
-                        };
-                    }
-                });
+                // A job can apply to more kinds of nodes than the targets are for
+                target_groups
+                    .iter()
+                    .filter(|target_group| target_group.node_kind() == node_kind)
+                    .for_each(|target_group| {
+                        if let Some(target) = result[first_target..].iter_mut().find(|t| t.node_id == target_group.node_id) {
+                            target.add_job(job);
+                        } else {
+                            let mut target = TargetDto {
+                                name: target_group.node_id.to_string(),
+                                ic_name: def.name(),
+                                ..TargetDto::from(target_group)
+                            };
+                            target.add_job(job);
+                            if filters.matches_ic_node(&target) {
+                                result.push(target)
+                            };
+                        }
+                    });
             }
+            def.enrichers.enrich(def, &mut result[first_target..]);
         }
     }
 
+    // Selectors can refer to the labels of the enrichers and all jobs of a target
+    result.retain(|target| filters.matches_selector(target) && filters.matches_shard(target));
     result
 }
 
@@ -793,8 +1052,66 @@ pub fn boundary_nodes_from_definitions(definitions: &BTreeMap<String, RunningDef
                 if !filters.matches_boundary_node(bn) {
                     return None;
                 }
+                let target = boundary_node_target_dto(def, bn);
+                if !filters.matches_selector(&target) || !filters.matches_shard(&target) {
+                    return None;
+                }
                 Some((def.name(), bn.clone()))
             })
         })
         .collect()
 }
+
+/// The boundary node in the format of the IC node targets
+pub fn boundary_node_target_dto(definition: &RunningDefinition, bn: &BoundaryNode) -> TargetDto {
+    let job = definition.job_definition(bn.job_type);
+    TargetDto {
+        name: bn.name.clone(),
+        node_id: NodeId::from(PrincipalId::new_anonymous()),
+        jobs: vec![bn.job_type],
+        custom_jobs: match job.is_built_in() {
+            true => vec![],
+            false => vec![job],
+        },
+        custom_labels: bn.custom_labels.clone(),
+        targets: bn.targets.clone(),
+        // The same data center the `dc_id` filter matches boundary nodes by
+        dc_id: bn.custom_labels.get("dc").cloned().unwrap_or_default(),
+        ic_name: definition.name(),
+        node_provider_id: PrincipalId::new_anonymous(),
+        operator_id: PrincipalId::new_anonymous(),
+        subnet_id: None,
+        // These are old boundary nodes which are not the same as API boundary nodes
+        // with time these should become api boundary nodes
+        is_api_bn: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::Query;
+
+    use super::*;
+
+    fn filters(query: &str) -> Result<TargetFilterSpec, String> {
+        let uri = format!("http://localhost/prom/targets?{}", query).parse().unwrap();
+        Query::<TargetFilterSpec>::try_from_uri(&uri).map(|q| q.0).map_err(|e| e.body_text())
+    }
+
+    fn error(query: &str) -> String {
+        filters(query).err().unwrap()
+    }
+
+    #[test]
+    fn shard_from_query() {
+        assert!(filters("").unwrap().shard.is_none());
+        assert!(filters("ic_name=mercury").unwrap().shard.is_none());
+        assert!(filters("shard_index=1&shard_count=2").unwrap().shard.is_some());
+
+        assert!(error("shard_index=2&shard_count=2").contains("invalid shard 2 of 2"));
+        assert!(error("shard_count=0&shard_index=0").contains("invalid shard 0 of 0"));
+        assert!(error("shard_index=1").contains("have to be set together"));
+        assert!(error("shard_count=2").contains("have to be set together"));
+        assert!(!error("shard_index=one&shard_count=2").is_empty());
+    }
+}
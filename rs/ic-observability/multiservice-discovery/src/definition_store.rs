@@ -0,0 +1,251 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use slog::{info, warn, Logger};
+
+use crate::definition::FSDefinition;
+
+/// Persists the definitions to the networks state file whenever they change.
+///
+/// The file is replaced atomically (write to a temporary file, then rename), so
+/// a crash leaves either the old or the new definitions on disk. Every change is
+/// appended to a history file next to it.
+#[derive(Clone)]
+pub(crate) struct DefinitionStore {
+    path: PathBuf,
+    log: Logger,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ChangeKind {
+    Added,
+    Replaced,
+    Removed,
+    BoundaryNodeAdded,
+    BoundaryNodeUpdated,
+    BoundaryNodeRemoved,
+}
+
+/// One line of the history file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Change {
+    /// RFC 3339 timestamp of the change
+    pub(crate) timestamp: String,
+    /// The address of the client that made the change
+    pub(crate) changed_by: String,
+    pub(crate) kind: ChangeKind,
+    pub(crate) definition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) boundary_node: Option<String>,
+}
+
+impl Change {
+    pub(crate) fn new(changed_by: &str, kind: ChangeKind, definition: String) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            changed_by: changed_by.to_string(),
+            kind,
+            definition,
+            boundary_node: None,
+        }
+    }
+
+    pub(crate) fn boundary_node(changed_by: &str, kind: ChangeKind, definition: String, boundary_node: String) -> Self {
+        Self {
+            boundary_node: Some(boundary_node),
+            ..Self::new(changed_by, kind, definition)
+        }
+    }
+}
+
+/// `<path><suffix>`, e.g. `networks.json.history`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+impl DefinitionStore {
+    pub(crate) fn new(path: PathBuf, log: Logger) -> Self {
+        Self { path, log }
+    }
+
+    fn history_path(&self) -> PathBuf {
+        sibling(&self.path, ".history")
+    }
+
+    /// Loads the valid definitions of the state file.
+    ///
+    /// Entries that can't be parsed, have no name or NNS urls, or reuse the
+    /// name of an earlier entry are skipped. In that case the original file is
+    /// kept as `<path>.corrupt`, since it is overwritten with the remaining
+    /// definitions on the next change.
+    pub(crate) fn load(&self) -> io::Result<Vec<FSDefinition>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let entries: Vec<serde_json::Value> = match serde_json::from_str(&content) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    self.log,
+                    "Networks state file {:?} is corrupt, starting without its definitions: {}", self.path, e
+                );
+                self.keep_corrupt()?;
+                return Ok(vec![]);
+            }
+        };
+
+        let mut names = BTreeSet::new();
+        let mut definitions = vec![];
+        let mut skipped = 0;
+        for (i, entry) in entries.into_iter().enumerate() {
+            match serde_json::from_value::<FSDefinition>(entry) {
+                Ok(def) if def.name.is_empty() || def.nns_urls.is_empty() => {
+                    warn!(self.log, "Skipping definition #{} of {:?}: it has no name or NNS urls", i, self.path);
+                    skipped += 1;
+                }
+                Ok(def) if !names.insert(def.name.clone()) => {
+                    warn!(
+                        self.log,
+                        "Skipping definition #{} of {:?}: '{}' is defined more than once", i, self.path, def.name
+                    );
+                    skipped += 1;
+                }
+                Ok(def) => definitions.push(def),
+                Err(e) => {
+                    warn!(self.log, "Skipping definition #{} of {:?}: {}", i, self.path, e);
+                    skipped += 1;
+                }
+            }
+        }
+        if skipped > 0 {
+            self.keep_corrupt()?;
+        }
+
+        Ok(definitions)
+    }
+
+    fn keep_corrupt(&self) -> io::Result<()> {
+        let corrupt = sibling(&self.path, ".corrupt");
+        fs::copy(&self.path, &corrupt)?;
+        info!(self.log, "Kept a copy of the networks state file at {:?}", corrupt);
+        Ok(())
+    }
+
+    /// Replaces the state file with the definitions.
+    pub(crate) fn save(&self, definitions: &[FSDefinition]) -> io::Result<()> {
+        let content = serde_json::to_string(definitions)?;
+        let tmp = sibling(&self.path, ".tmp");
+        retry::retry(retry::delay::Exponential::from_millis(10).take(5), || {
+            let mut file = File::create(&tmp)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)
+        })
+        .map_err(|e| e.error)?;
+
+        // Make the rename itself durable
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Appends the changes to the history file.
+    pub(crate) fn record(&self, changes: &[Change]) -> io::Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(self.history_path())?;
+        for change in changes {
+            writeln!(file, "{}", serde_json::to_string(change)?)?;
+        }
+        file.sync_data()
+    }
+
+    /// The recorded changes, oldest first. Lines that can't be parsed are skipped.
+    pub(crate) fn history(&self) -> io::Result<Vec<Change>> {
+        let content = match fs::read_to_string(self.history_path()) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use slog::o;
+
+    use super::*;
+
+    fn definition(name: &str) -> FSDefinition {
+        FSDefinition {
+            nns_urls: vec!["https://ic0.app".parse().unwrap()],
+            registry_path: PathBuf::from("/tmp").join(name),
+            name: name.to_string(),
+            public_key: None,
+            poll_interval: Duration::from_secs(30),
+            registry_query_timeout: Duration::from_secs(5),
+            boundary_nodes: vec![],
+        }
+    }
+
+    fn store(dir: &Path) -> DefinitionStore {
+        DefinitionStore::new(dir.join("networks.json"), Logger::root(slog::Discard, o!()))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        assert!(store.load().unwrap().is_empty());
+
+        store.save(&[definition("mercury"), definition("staging")]).unwrap();
+        store.save(&[definition("mercury")]).unwrap();
+
+        let names = store.load().unwrap().into_iter().map(|d| d.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["mercury".to_string()]);
+        assert!(!sibling(&store.path, ".tmp").exists());
+    }
+
+    #[test]
+    fn corrupt_entries_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        let valid = serde_json::to_value(definition("mercury")).unwrap();
+        let content = serde_json::json!([valid, {"name": "broken"}, valid]).to_string();
+        fs::write(&store.path, &content).unwrap();
+
+        let names = store.load().unwrap().into_iter().map(|d| d.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["mercury".to_string()]);
+        assert_eq!(fs::read_to_string(sibling(&store.path, ".corrupt")).unwrap(), content);
+
+        fs::write(&store.path, "[{\"name\": ").unwrap();
+        assert!(store.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_is_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        let added = Change::new("127.0.0.1:1234", ChangeKind::Added, "staging".to_string());
+        let boundary_node = Change::boundary_node("127.0.0.1:1234", ChangeKind::BoundaryNodeAdded, "staging".to_string(), "bn-1".to_string());
+        let removed = Change::new("127.0.0.1:1234", ChangeKind::Removed, "staging".to_string());
+
+        store.record(&[added.clone(), boundary_node.clone()]).unwrap();
+        store.record(std::slice::from_ref(&removed)).unwrap();
+
+        assert_eq!(store.history().unwrap(), vec![added, boundary_node, removed]);
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use ic_registry_client::client::RegistryVersion;
+use ic_types::NodeId;
+use multiservice_discovery_shared::contracts::target::TargetDto;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use service_discovery::job_types::{JobType, NodeOS};
+use service_discovery::NodeMetadata;
+use slog::{warn, Logger};
+
+use crate::definition::RunningDefinition;
+
+pub const DC_CONTINENT: &str = "dc_continent";
+pub const DC_COUNTRY: &str = "dc_country";
+pub const DC_CITY: &str = "dc_city";
+pub const DC_OWNER: &str = "dc_owner";
+pub const GUESTOS_VERSION: &str = "guestos_version";
+pub const HOSTOS_VERSION: &str = "hostos_version";
+pub const NODE_LABEL: &str = "node_label";
+
+/// Adds labels to the targets of a definition, e.g. human-readable names for
+/// the IDs the targets carry.
+pub trait Enricher: Send + Sync {
+    fn enrich(&self, definition: &RunningDefinition, targets: &mut [TargetDto]);
+}
+
+/// The enrichers of the discovery. They are applied in order and never
+/// overwrite a label that is already set, so custom labels of a target and
+/// labels of earlier enrichers win.
+#[derive(Clone, Default)]
+pub struct Enrichers(Arc<Vec<Box<dyn Enricher>>>);
+
+impl Enrichers {
+    pub fn new(enrichers: Vec<Box<dyn Enricher>>) -> Self {
+        Self(Arc::new(enrichers))
+    }
+
+    pub fn enrich(&self, definition: &RunningDefinition, targets: &mut [TargetDto]) {
+        for enricher in self.0.iter() {
+            enricher.enrich(definition, targets);
+        }
+    }
+}
+
+fn add_labels(target: &mut TargetDto, labels: impl IntoIterator<Item = (String, String)>) {
+    for (key, value) in labels {
+        target.custom_labels.entry(key).or_insert(value);
+    }
+}
+
+/// Node metadata of a definition, and the registry versions it was read at
+type CachedMetadata = (BTreeMap<String, RegistryVersion>, Arc<BTreeMap<NodeId, NodeMetadata>>);
+
+/// Adds the data center location and owner, and the GuestOS and HostOS
+/// versions of the nodes from the registry of the definition.
+pub struct RegistryEnricher {
+    log: Logger,
+    /// By definition name, read again only when the registries of the definition are updated
+    cache: Mutex<BTreeMap<String, CachedMetadata>>,
+}
+
+impl RegistryEnricher {
+    pub fn new(log: Logger) -> Self {
+        Self {
+            log,
+            cache: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn metadata(&self, definition: &RunningDefinition) -> Option<Arc<BTreeMap<NodeId, NodeMetadata>>> {
+        let versions = definition.get_registry_versions();
+        let mut cache = self.cache.lock().unwrap();
+        if let Some((_, metadata)) = cache.get(&definition.name()).filter(|(cached, _)| *cached == versions) {
+            return Some(metadata.clone());
+        }
+        match definition.get_node_metadata() {
+            Ok(metadata) => {
+                let metadata = Arc::new(metadata);
+                cache.insert(definition.name(), (versions, metadata.clone()));
+                Some(metadata)
+            }
+            Err(e) => {
+                warn!(self.log, "Couldn't read the node metadata of definition {}: {:?}", definition.name(), e);
+                None
+            }
+        }
+    }
+}
+
+impl Enricher for RegistryEnricher {
+    fn enrich(&self, definition: &RunningDefinition, targets: &mut [TargetDto]) {
+        let Some(metadata) = self.metadata(definition) else {
+            return;
+        };
+
+        for target in targets.iter_mut() {
+            let Some(node) = metadata.get(&target.node_id) else {
+                continue;
+            };
+            let mut labels = vec![];
+            if let Some(region) = &node.dc_region {
+                let mut parts = region.splitn(3, ',').map(|s| s.trim().to_string());
+                for key in [DC_CONTINENT, DC_COUNTRY, DC_CITY] {
+                    if let Some(part) = parts.next().filter(|p| !p.is_empty()) {
+                        labels.push((key.to_string(), part));
+                    }
+                }
+            }
+            labels.extend(
+                [
+                    (DC_OWNER, &node.dc_owner),
+                    (GUESTOS_VERSION, &node.guestos_version),
+                    (HOSTOS_VERSION, &node.hostos_version),
+                ]
+                .into_iter()
+                .filter_map(|(key, value)| value.clone().filter(|v| !v.is_empty()).map(|v| (key.to_string(), v))),
+            );
+            add_labels(target, labels);
+        }
+    }
+}
+
+/// A file that is parsed again whenever it is modified
+struct WatchedFile<T> {
+    path: PathBuf,
+    log: Logger,
+    cache: Mutex<Option<(SystemTime, Arc<T>)>>,
+}
+
+impl<T: DeserializeOwned> WatchedFile<T> {
+    fn new(path: PathBuf, log: Logger) -> Self {
+        Self {
+            path,
+            log,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// The parsed content, or the last one that could be parsed
+    fn get(&self) -> Option<Arc<T>> {
+        let mut cache = self.cache.lock().unwrap();
+        let modified = match self.path.metadata().and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(e) => {
+                warn!(self.log, "Couldn't read {:?}: {}", self.path, e);
+                return cache.as_ref().map(|(_, content)| content.clone());
+            }
+        };
+        if cache.as_ref().map(|(m, _)| *m) != Some(modified) {
+            match std::fs::read_to_string(&self.path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_yaml::from_str::<T>(&content).map_err(|e| e.to_string()))
+            {
+                Ok(content) => *cache = Some((modified, Arc::new(content))),
+                Err(e) => warn!(self.log, "Couldn't parse {:?}, keeping the previous content: {}", self.path, e),
+            }
+        }
+        cache.as_ref().map(|(_, content)| content.clone())
+    }
+}
+
+#[derive(Deserialize)]
+struct NodeLabelsFile {
+    data: NodeLabelsData,
+}
+
+#[derive(Deserialize)]
+struct NodeLabelsData {
+    v1: BTreeMap<Ipv6Addr, NodeLabelEntry>,
+}
+
+#[derive(Deserialize)]
+struct NodeLabelEntry {
+    dc: String,
+    label: String,
+}
+
+/// Adds the `<dc>-<label>` name of the nodes from a `node-labels/<network>.yaml`
+/// file. The file maps the IPv6 addresses of the guests, so the targets of the
+/// HostOS jobs are matched by the address of their guest.
+pub struct NodeLabelsEnricher {
+    file: WatchedFile<NodeLabelsFile>,
+}
+
+impl NodeLabelsEnricher {
+    pub fn new(path: PathBuf, log: Logger) -> Self {
+        Self {
+            file: WatchedFile::new(path, log),
+        }
+    }
+}
+
+impl Enricher for NodeLabelsEnricher {
+    fn enrich(&self, _definition: &RunningDefinition, targets: &mut [TargetDto]) {
+        let Some(file) = self.file.get() else {
+            return;
+        };
+        let host_ip = |guest: Ipv6Addr| JobType::NodeExporter(NodeOS::Host).ip(SocketAddr::new(IpAddr::V6(guest), 0), false);
+        let labels = file
+            .data
+            .v1
+            .iter()
+            .flat_map(|(guest, entry)| {
+                let name = format!("{}-{}", entry.dc, entry.label);
+                [(IpAddr::V6(*guest), name.clone()), (host_ip(*guest), name)]
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for target in targets.iter_mut() {
+            if let Some(name) = target.targets.iter().find_map(|addr| labels.get(&addr.ip())) {
+                add_labels(target, [(NODE_LABEL.to_string(), name.clone())]);
+            }
+        }
+    }
+}
+
+/// Labels per value of a field of the targets
+type FieldMapping = BTreeMap<String, BTreeMap<String, String>>;
+
+/// A file mapping values of the target fields to labels, for example:
+///
+/// ```yaml
+/// node_provider_id:
+///   bvcsg-3od6r-jnydw-eysln-aql7w-td5zn-ay5m6-sibd2-jzojt-anwag-mqe:
+///     node_provider_name: DFINITY Stiftung
+/// subnet_id:
+///   tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe:
+///     subnet_name: NNS
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct StaticMapping {
+    #[serde(default)]
+    ic_name: FieldMapping,
+    #[serde(default)]
+    node_id: FieldMapping,
+    #[serde(default)]
+    subnet_id: FieldMapping,
+    #[serde(default)]
+    dc_id: FieldMapping,
+    #[serde(default)]
+    operator_id: FieldMapping,
+    #[serde(default)]
+    node_provider_id: FieldMapping,
+}
+
+/// Adds the labels of a static mapping file, e.g. names of node providers and
+/// subnets.
+pub struct StaticMappingEnricher {
+    file: WatchedFile<StaticMapping>,
+}
+
+impl StaticMappingEnricher {
+    pub fn new(path: PathBuf, log: Logger) -> Self {
+        Self {
+            file: WatchedFile::new(path, log),
+        }
+    }
+}
+
+impl Enricher for StaticMappingEnricher {
+    fn enrich(&self, _definition: &RunningDefinition, targets: &mut [TargetDto]) {
+        let Some(mapping) = self.file.get() else {
+            return;
+        };
+        for target in targets.iter_mut() {
+            let values = [
+                (&mapping.ic_name, Some(target.ic_name.clone())),
+                (&mapping.node_id, Some(target.node_id.to_string())),
+                (&mapping.subnet_id, target.subnet_id.map(|s| s.to_string())),
+                (&mapping.dc_id, Some(target.dc_id.clone())),
+                (&mapping.operator_id, Some(target.operator_id.to_string())),
+                (&mapping.node_provider_id, Some(target.node_provider_id.to_string())),
+            ];
+            let labels = values
+                .into_iter()
+                .filter_map(|(field, value)| value.and_then(|value| field.get(&value)))
+                .flat_map(|labels| labels.clone())
+                .collect::<Vec<_>>();
+            add_labels(target, labels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    use ic_types::PrincipalId;
+    use service_discovery::mainnet_registry::{create_local_store_from_changelog, get_mainnet_delta_6d_c1};
+    use slog::o;
+
+    use crate::definition::{Definition, TestDefinition};
+    use crate::metrics::RunningDefinitionsMetrics;
+
+    use super::*;
+
+    fn target(guest: &str) -> TargetDto {
+        TargetDto {
+            node_id: PrincipalId::new_node_test_id(1).into(),
+            name: "node".to_string(),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_user_test_id(2),
+            dc_id: "zh2".to_string(),
+            targets: BTreeSet::from([SocketAddr::new(guest.parse().unwrap(), 9090)]),
+            jobs: vec![JobType::Replica],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::from([("dc".to_string(), "custom".to_string())]),
+            is_api_bn: false,
+        }
+    }
+
+    #[test]
+    fn registry_enricher() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = Logger::root(slog::Discard, o!());
+        let definition = Definition::new(
+            vec!["https://ic0.app".parse().unwrap()],
+            dir.path().to_path_buf(),
+            "mainnet".to_string(),
+            log.clone(),
+            None,
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+        );
+        create_local_store_from_changelog(definition.registry_path.join("targets"), get_mainnet_delta_6d_c1());
+        definition.ic_discovery.load_new_ics(log.clone()).unwrap();
+        let definition = TestDefinition::new(definition, RunningDefinitionsMetrics::new(), Enrichers::default(), vec![])
+            .unwrap()
+            .running_def;
+        let enricher = RegistryEnricher::new(log);
+
+        let metadata = enricher.metadata(&definition).unwrap();
+        // Read only once per registry version
+        assert!(Arc::ptr_eq(&metadata, &enricher.metadata(&definition).unwrap()));
+
+        let (node_id, node) = metadata
+            .iter()
+            .find(|(_, node)| node.dc_region.is_some() && node.guestos_version.is_some())
+            .unwrap();
+        let mut targets = vec![TargetDto {
+            node_id: *node_id,
+            ..target("2a00:fb01:400:42:6801:aeff:fee0:fc5f")
+        }];
+        enricher.enrich(&definition, &mut targets);
+
+        let labels = &targets[0].custom_labels;
+        assert_eq!(labels.get(GUESTOS_VERSION), node.guestos_version.as_ref());
+        let continent = node.dc_region.as_ref().unwrap().split(',').next().unwrap().trim();
+        assert_eq!(labels.get(DC_CONTINENT).map(|c| c.as_str()), Some(continent));
+        assert_eq!(labels.get("dc"), Some(&"custom".to_string()));
+    }
+
+    #[test]
+    fn file_enrichers() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = Logger::root(slog::Discard, o!());
+        let definition = Definition::new(
+            vec!["https://ic0.app".parse().unwrap()],
+            dir.path().to_path_buf(),
+            "mercury".to_string(),
+            log.clone(),
+            None,
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+        );
+        let definition = TestDefinition::new(definition, RunningDefinitionsMetrics::new(), Enrichers::default(), vec![])
+            .unwrap()
+            .running_def;
+
+        let static_labels = dir.path().join("static-labels.yaml");
+        std::fs::write(
+            &static_labels,
+            format!(
+                "node_provider_id:\n  {}:\n    node_provider_name: Provider\ndc_id:\n  zh2:\n    dc: zh2\n",
+                PrincipalId::new_user_test_id(2)
+            ),
+        )
+        .unwrap();
+        let node_labels = dir.path().join("mainnet.yaml");
+        std::fs::write(
+            &node_labels,
+            "data:\n  v1:\n    2a00:fb01:400:42:6801:aeff:fee0:fc5f:\n      dc: zh2\n      label: dll01\n",
+        )
+        .unwrap();
+        let enrichers = Enrichers::new(vec![
+            Box::new(StaticMappingEnricher::new(static_labels, log.clone())),
+            Box::new(NodeLabelsEnricher::new(node_labels, log)),
+        ]);
+
+        let mut guest = target("2a00:fb01:400:42:6801:aeff:fee0:fc5f");
+        let host = guest
+            .targets
+            .iter()
+            .map(|a| JobType::NodeExporter(NodeOS::Host).sockaddr(*a, false))
+            .collect();
+        let mut targets = vec![
+            guest.clone(),
+            TargetDto {
+                targets: host,
+                ..guest.clone()
+            },
+            target("2a00:fb01:400:42:6801:aeff:fee0:0"),
+        ];
+        enrichers.enrich(&definition, &mut targets);
+
+        guest.custom_labels.extend([
+            ("node_provider_name".to_string(), "Provider".to_string()),
+            (NODE_LABEL.to_string(), "zh2-dll01".to_string()),
+        ]);
+        assert_eq!(targets[0].custom_labels, guest.custom_labels);
+        assert_eq!(targets[1].custom_labels, guest.custom_labels);
+        assert_eq!(targets[2].custom_labels.get(NODE_LABEL), None);
+        assert_eq!(targets[2].custom_labels.get("dc"), Some(&"custom".to_string()));
+    }
+}
@@ -4,8 +4,10 @@ use std::time::Duration;
 use std::vec;
 
 use axum_otel_metrics::HttpMetricsLayerBuilder;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use humantime::parse_duration;
+use service_discovery::job_types::JobDefinition;
 use slog::{info, o, Drain, Logger};
 use tokio::runtime::Runtime;
 use tokio::sync::oneshot::{self};
@@ -15,19 +17,27 @@ use definition::{Definition, DefinitionsSupervisor, StartMode};
 use ic_async_utils::shutdown_signal;
 
 use crate::definition::{RunningDefinition, TargetFilterSpec, TestDefinition};
+use crate::enrichment::{Enricher, Enrichers, NodeLabelsEnricher, RegistryEnricher, StaticMappingEnricher};
 use crate::metrics::{MSDMetrics, RunningDefinitionsMetrics};
+use crate::prober::{ProbeConfig, Prober};
 use crate::server_handlers::export_prometheus_config_handler::serialize_definitions_to_prometheus_config;
 use crate::server_handlers::Server;
+use crate::target_changes::TargetChangeLog;
 
 mod definition;
+mod definition_store;
+mod enrichment;
 mod metrics;
+mod prober;
 mod server_handlers;
+mod target_changes;
 
 fn main() {
     let rt = Runtime::new().unwrap();
     let log = make_logger();
     let shutdown_signal = shutdown_signal(log.clone());
     let cli_args = CliArgs::parse();
+    let custom_jobs = load_custom_jobs(&cli_args.job_definitions).unwrap_or_else(invalid_job_definitions);
 
     fn get_mainnet_definition(cli_args: &CliArgs, log: Logger) -> Definition {
         Definition::new(
@@ -42,9 +52,15 @@ fn main() {
     }
 
     if cli_args.render_prom_targets_to_stdout {
-        async fn sync(cli_args: &CliArgs, log: &Logger, shutdown_signal: impl futures_util::Future<Output = ()>) -> Option<RunningDefinition> {
+        async fn sync(
+            cli_args: &CliArgs,
+            log: &Logger,
+            custom_jobs: Vec<JobDefinition>,
+            shutdown_signal: impl futures_util::Future<Output = ()>,
+        ) -> Option<RunningDefinition> {
             let def = get_mainnet_definition(cli_args, log.clone());
-            let test_def = TestDefinition::new(def, RunningDefinitionsMetrics::new());
+            let test_def = TestDefinition::new(def, RunningDefinitionsMetrics::new(), make_enrichers(cli_args, log.clone()), custom_jobs)
+                .unwrap_or_else(invalid_job_definitions);
             let sync_fut = test_def.sync_and_stop(cli_args.skip_update_local_registry);
             tokio::select! {
                 _ = sync_fut => {
@@ -57,7 +73,7 @@ fn main() {
                 }
             }
         }
-        if let Some(running_def) = rt.block_on(sync(&cli_args, &log, shutdown_signal)) {
+        if let Some(running_def) = rt.block_on(sync(&cli_args, &log, custom_jobs, shutdown_signal)) {
             let mut definitions_ref: BTreeMap<String, RunningDefinition> = BTreeMap::new();
             definitions_ref.insert(running_def.name().clone(), running_def);
             let (_, text) = serialize_definitions_to_prometheus_config(definitions_ref, TargetFilterSpec::empty());
@@ -68,8 +84,11 @@ fn main() {
             rt.handle().clone(),
             cli_args.start_without_mainnet,
             cli_args.networks_state_file.clone(),
+            make_enrichers(&cli_args, log.clone()),
+            custom_jobs,
             make_logger(),
-        );
+        )
+        .unwrap_or_else(invalid_job_definitions);
         let (server_stop, server_stop_receiver) = oneshot::channel();
 
         // Initialize the metrics layer because in the build method the `global::provider`
@@ -89,11 +108,20 @@ fn main() {
                         vec![get_mainnet_definition(&cli_args, log.clone())],
                         StartMode::AddToDefinitions,
                         metrics.running_definition_metrics.clone(),
+                        None,
                     )
                     .await;
             });
         }
 
+        let target_changes = TargetChangeLog::new();
+        rt.spawn(target_changes.clone().run(supervisor.clone(), cli_args.poll_interval));
+
+        let prober = cli_args.probe.probe.then(|| Prober::new(cli_args.probe.clone(), log.clone()));
+        if let Some(prober) = &prober {
+            rt.spawn(prober.clone().run(supervisor.clone()));
+        }
+
         //Configure server
         let server_handle = rt.spawn(
             Server::new(
@@ -103,6 +131,8 @@ fn main() {
                 cli_args.registry_query_timeout,
                 cli_args.targets_dir.clone(),
                 metrics,
+                target_changes,
+                prober,
             )
             .run(server_stop_receiver, metrics_layer),
         );
@@ -121,6 +151,36 @@ fn main() {
     }
 }
 
+fn make_enrichers(cli_args: &CliArgs, log: Logger) -> Enrichers {
+    let mut enrichers: Vec<Box<dyn Enricher>> = vec![];
+    if let Some(path) = &cli_args.static_labels {
+        enrichers.push(Box::new(StaticMappingEnricher::new(path.clone(), log.clone())));
+    }
+    if let Some(path) = &cli_args.node_labels {
+        enrichers.push(Box::new(NodeLabelsEnricher::new(path.clone(), log.clone())));
+    }
+    if cli_args.enrich_from_registry {
+        enrichers.push(Box::new(RegistryEnricher::new(log)));
+    }
+    Enrichers::new(enrichers)
+}
+
+/// The job definitions of the file, which come in addition to the built-in ones
+fn load_custom_jobs(path: &Option<PathBuf>) -> Result<Vec<JobDefinition>, String> {
+    match path {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_yaml::from_str(&content).map_err(|e| e.to_string())),
+        None => Ok(vec![]),
+    }
+}
+
+fn invalid_job_definitions<T>(e: impl std::fmt::Display) -> T {
+    CliArgs::command()
+        .error(ErrorKind::InvalidValue, format!("Invalid job definitions: {}", e))
+        .exit()
+}
+
 fn make_logger() -> Logger {
     let decorator = slog_term::TermDecorator::new().build();
     let drain = slog_term::FullFormat::new(decorator).build().fuse();
@@ -212,8 +272,59 @@ Used for testing: Whether to skip the update of the local mainnet registry.
         action,
         help = r#"
 Preload networks definitions from file path. In case the file does not
-exist, it will be created.
+exist, it will be created. The file is rewritten whenever a definition
+changes, and the changes are appended to '<file>.history'. Definitions
+that can't be loaded are skipped, and the original file is kept as
+'<file>.corrupt'.
 "#
     )]
     networks_state_file: Option<PathBuf>,
+
+    #[clap(
+        long = "static-labels",
+        help = r#"
+Path to a YAML file mapping values of the target fields (ic_name, node_id,
+subnet_id, dc_id, operator_id, node_provider_id) to additional labels,
+e.g. the names of node providers and subnets. The file is read again
+whenever it changes.
+"#
+    )]
+    static_labels: Option<PathBuf>,
+
+    #[clap(
+        long = "node-labels",
+        help = r#"
+Path to a node labels file (node-labels/<network>.yaml) whose labels are
+added to the targets as `node_label`. The file is read again whenever it
+changes.
+"#
+    )]
+    node_labels: Option<PathBuf>,
+
+    #[clap(
+        long = "enrich-from-registry",
+        default_value = "false",
+        action,
+        help = r#"
+Add the data center location and owner, and the GuestOS and HostOS versions
+of the nodes from the registry as labels.
+"#
+    )]
+    enrich_from_registry: bool,
+
+    #[clap(
+        long = "job-definitions",
+        help = r#"
+Path to a YAML file with a list of jobs in addition to the built-in ones,
+e.g. for new services of the API boundary nodes. A job has a name, port,
+path, scheme, the address it's scraped on (guest, host or
+host_for_ic_nodes) and the kinds of nodes it applies to (ic_node,
+api_boundary_node). The targets of a job are served on
+/prom/http_sd?job=<name>.
+"#
+    )]
+    job_definitions: Option<PathBuf>,
+
+    #[clap(flatten)]
+    probe: ProbeConfig,
 }
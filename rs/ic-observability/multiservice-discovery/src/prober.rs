@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::{Parser, ValueEnum};
+use futures_util::future::join_all;
+use humantime::parse_duration;
+use ic_types::PrincipalId;
+use multiservice_discovery_shared::contracts::target::TargetDto;
+use opentelemetry::{global, metrics::Observer, KeyValue};
+use serde::Serialize;
+use slog::{debug, info, Logger};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
+
+use crate::definition::{target_dtos_from_definitions, DefinitionsSupervisor, TargetFilterSpec};
+
+const AXUM_APP: &str = "axum-app";
+
+#[derive(Parser, Clone, Debug)]
+pub struct ProbeConfig {
+    #[clap(
+        long = "probe",
+        default_value = "false",
+        action,
+        help = r#"
+Periodically check whether the endpoints of the discovered targets are
+reachable. The results are served on /targets/health and exported as the
+msd.targets.up metric.
+"#
+    )]
+    pub probe: bool,
+
+    #[clap(
+        long = "probe-mode",
+        value_enum,
+        default_value = "http",
+        help = r#"
+Whether an endpoint is up if a TCP connection can be opened to it, or if
+a GET request to its metrics URL is successful.
+"#
+    )]
+    pub mode: ProbeMode,
+
+    #[clap(
+    long = "probe-interval",
+    default_value = "60s",
+    value_parser = parse_duration,
+    help = r#"
+The interval at which all endpoints are probed. A round that takes longer
+delays the next one.
+"#
+    )]
+    pub interval: Duration,
+
+    #[clap(
+    long = "probe-timeout",
+    default_value = "5s",
+    value_parser = parse_duration,
+    help = r#"
+The time after which an endpoint that didn't respond is down.
+"#
+    )]
+    pub timeout: Duration,
+
+    #[clap(
+        long = "probe-concurrency",
+        default_value = "32",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = r#"
+The maximum number of endpoints probed at the same time.
+"#
+    )]
+    pub concurrency: u32,
+
+    #[clap(
+        long = "probe-rate",
+        default_value = "50",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = r#"
+The maximum number of probes started per second.
+"#
+    )]
+    pub rate: u32,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbeMode {
+    Tcp,
+    Http,
+}
+
+/// The result of the last probe of an endpoint
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TargetHealth {
+    pub(crate) ic_name: String,
+    pub(crate) node_id: String,
+    pub(crate) name: String,
+    pub(crate) job: String,
+    pub(crate) url: String,
+    pub(crate) up: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+    pub(crate) duration_seconds: f64,
+    /// RFC 3339 timestamp of the probe
+    pub(crate) probed_at: String,
+}
+
+/// An endpoint of a target for a single job
+struct Endpoint {
+    target: TargetDto,
+    job: String,
+    address: SocketAddr,
+    url: String,
+}
+
+/// Probes the endpoints of all targets of the definitions, with at most
+/// `concurrency` probes at the same time and `rate` probes started per second.
+#[derive(Clone)]
+pub(crate) struct Prober {
+    config: ProbeConfig,
+    client: reqwest::Client,
+    /// The results of the last round, by network and URL
+    health: Arc<RwLock<BTreeMap<(String, String), TargetHealth>>>,
+    log: Logger,
+}
+
+impl Prober {
+    pub(crate) fn new(config: ProbeConfig, log: Logger) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            // The nodes serve their metrics with self-signed certificates
+            .danger_accept_invalid_certs(true)
+            .build()
+            .expect("Failed to build reqwest client");
+        let prober = Self {
+            config,
+            client,
+            health: Arc::new(RwLock::new(BTreeMap::new())),
+            log,
+        };
+        prober.register_metrics();
+        prober
+    }
+
+    fn register_metrics(&self) {
+        let meter = global::meter(AXUM_APP);
+        let up = meter
+            .clone()
+            .u64_observable_gauge("msd.targets.up")
+            .with_description("Whether the last probe of the endpoint of the target was successful")
+            .init();
+        let duration = meter
+            .clone()
+            .f64_observable_gauge("msd.targets.probe.duration")
+            .with_description("Duration of the last probe of the endpoint of the target in seconds")
+            .init();
+        let instruments = [up.as_any(), duration.as_any()];
+        let health = self.health.clone();
+        let update_instruments = move |observer: &dyn Observer| {
+            for target in health.read().unwrap().values() {
+                let attrs = [
+                    KeyValue::new("network", target.ic_name.clone()),
+                    KeyValue::new("node_id", target.node_id.clone()),
+                    KeyValue::new("name", target.name.clone()),
+                    KeyValue::new("job", target.job.clone()),
+                    KeyValue::new("url", target.url.clone()),
+                ];
+                observer.observe_u64(&up, target.up as u64, &attrs);
+                observer.observe_f64(&duration, target.duration_seconds, &attrs);
+            }
+        };
+        meter.register_callback(&instruments, update_instruments).unwrap();
+    }
+
+    /// The results of the last round of probes
+    pub(crate) fn health(&self) -> Vec<TargetHealth> {
+        self.health.read().unwrap().values().cloned().collect()
+    }
+
+    pub(crate) async fn run(self, supervisor: DefinitionsSupervisor) {
+        let mut interval = tokio::time::interval(self.config.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let targets = {
+                let definitions = supervisor.definitions.lock().await;
+                target_dtos_from_definitions(&definitions, &TargetFilterSpec::empty())
+            };
+            let start = Instant::now();
+            let results = self.probe_all(endpoints(targets)).await;
+            info!(
+                self.log,
+                "Probed {} endpoints in {:?}, {} are down",
+                results.len(),
+                start.elapsed(),
+                results.iter().filter(|h| !h.up).count()
+            );
+            *self.health.write().unwrap() = results.into_iter().map(|h| ((h.ic_name.clone(), h.url.clone()), h)).collect();
+        }
+    }
+
+    async fn probe_all(&self, endpoints: Vec<Endpoint>) -> Vec<TargetHealth> {
+        let semaphore = Arc::new(Semaphore::new(self.config.concurrency as usize));
+        let mut rate = tokio::time::interval(Duration::from_secs(1) / self.config.rate);
+        rate.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut probes = vec![];
+        for endpoint in endpoints {
+            rate.tick().await;
+            let permit = semaphore.clone().acquire_owned().await.unwrap();
+            let prober = self.clone();
+            probes.push(tokio::spawn(async move {
+                let health = prober.probe(endpoint).await;
+                drop(permit);
+                health
+            }));
+        }
+        join_all(probes).await.into_iter().filter_map(Result::ok).collect()
+    }
+
+    async fn probe(&self, endpoint: Endpoint) -> TargetHealth {
+        let probed_at = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        let start = Instant::now();
+        let error = match self.config.mode {
+            ProbeMode::Tcp => match tokio::time::timeout(self.config.timeout, TcpStream::connect(endpoint.address)).await {
+                Ok(Ok(_)) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(_) => Some("timed out".to_string()),
+            },
+            ProbeMode::Http => match self.client.get(&endpoint.url).send().await {
+                Ok(response) if response.status().is_success() => None,
+                Ok(response) => Some(format!("status {}", response.status())),
+                Err(e) => Some(e.to_string()),
+            },
+        };
+        if let Some(error) = &error {
+            debug!(self.log, "Probe of {} failed: {}", endpoint.url, error);
+        }
+
+        TargetHealth {
+            ic_name: endpoint.target.ic_name,
+            node_id: endpoint.target.node_id.to_string(),
+            name: endpoint.target.name,
+            job: endpoint.job,
+            url: endpoint.url,
+            up: error.is_none(),
+            error,
+            duration_seconds: start.elapsed().as_secs_f64(),
+            probed_at,
+        }
+    }
+}
+
+/// The endpoints of the targets per job, as they are scraped
+fn endpoints(targets: Vec<TargetDto>) -> Vec<Endpoint> {
+    let bn_principal_placeholder = PrincipalId::new_anonymous().to_string();
+    targets
+        .into_iter()
+        .flat_map(|target| {
+            let is_boundary_node = target.node_id.to_string() == bn_principal_placeholder;
+            let mut endpoints = vec![];
+            for job in target.job_definitions() {
+                for address in &target.targets {
+                    endpoints.push(Endpoint {
+                        target: target.clone(),
+                        job: job.name.clone(),
+                        address: job.sockaddr(*address, is_boundary_node),
+                        url: job.url(*address, is_boundary_node),
+                    });
+                }
+            }
+            endpoints
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use service_discovery::job_types::{JobDefinition, JobType, NodeKind, NodeOS};
+
+    use super::*;
+
+    #[test]
+    fn endpoints_per_job() {
+        let target = TargetDto {
+            node_id: PrincipalId::new_node_test_id(1).into(),
+            name: "node".to_string(),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "dc1".to_string(),
+            targets: BTreeSet::from(["[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9090".parse().unwrap()]),
+            jobs: vec![JobType::Replica, JobType::NodeExporter(NodeOS::Host)],
+            custom_jobs: vec![JobDefinition {
+                name: "certificate_syncer".to_string(),
+                port: 9322,
+                path: "/metrics".to_string(),
+                scheme: "http".to_string(),
+                address: Default::default(),
+                node_kinds: vec![NodeKind::IcNode],
+                assigned_nodes_only: false,
+            }],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::new(),
+            is_api_bn: false,
+        };
+
+        let urls = endpoints(vec![target]).into_iter().map(|e| (e.job, e.url)).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                ("replica".to_string(), "http://[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9090/".to_string()),
+                (
+                    "host_node_exporter".to_string(),
+                    "https://[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:9100/metrics".to_string()
+                ),
+                (
+                    "certificate_syncer".to_string(),
+                    "http://[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9322/metrics".to_string()
+                ),
+            ]
+        );
+    }
+}
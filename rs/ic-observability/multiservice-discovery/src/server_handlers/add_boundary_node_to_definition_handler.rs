@@ -1,17 +1,18 @@
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
 use axum::http::StatusCode;
 use axum::Json;
 use std::error::Error;
 use std::fmt::{Display, Error as FmtError, Formatter};
+use std::net::SocketAddr;
 
+use crate::definition_store::{Change, ChangeKind};
 use crate::server_handlers::dto::BoundaryNodeDto;
 
 use super::{bad_request, not_found, ok, Server};
 
 #[derive(Debug)]
-
-struct DefinitionNotFound {
-    ic_name: String,
+pub(super) struct DefinitionNotFound {
+    pub(super) ic_name: String,
 }
 
 impl Error for DefinitionNotFound {}
@@ -24,6 +25,7 @@ impl Display for DefinitionNotFound {
 
 pub(super) async fn add_boundary_node(
     State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     Json(boundary_node): Json<BoundaryNodeDto>,
 ) -> Result<String, (StatusCode, String)> {
     let name = boundary_node.name.clone();
@@ -49,7 +51,11 @@ pub(super) async fn add_boundary_node(
     };
 
     match running_definition.add_boundary_node(bn).await {
-        Ok(()) => ok(binding.log, format!("Definition {} added successfully", name)),
+        Ok(()) => {
+            let change = Change::boundary_node(&client.to_string(), ChangeKind::BoundaryNodeAdded, ic_name, name.clone());
+            binding.supervisor.persist(&definitions, &[change]);
+            ok(binding.log, format!("Definition {} added successfully", name))
+        }
         Err(e) => bad_request(binding.log, rejection, e),
     }
 }
@@ -1,13 +1,18 @@
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
 use axum::http::StatusCode;
 use axum::Json;
+use std::net::SocketAddr;
 
 use crate::definition::StartMode;
 use crate::server_handlers::dto::DefinitionDto;
 
 use super::{bad_request, ok, Server};
 
-pub(super) async fn add_definition(State(binding): State<Server>, Json(definition): Json<DefinitionDto>) -> Result<String, (StatusCode, String)> {
+pub(super) async fn add_definition(
+    State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(definition): Json<DefinitionDto>,
+) -> Result<String, (StatusCode, String)> {
     let dname = definition.name.clone();
     let rej = format!("Definition {} could not be added", dname);
     let new_definition = match definition
@@ -28,6 +33,7 @@ pub(super) async fn add_definition(State(binding): State<Server>, Json(definitio
             vec![new_definition],
             StartMode::AddToDefinitions,
             binding.metrics.running_definition_metrics.clone(),
+            Some(&client.to_string()),
         )
         .await
     {
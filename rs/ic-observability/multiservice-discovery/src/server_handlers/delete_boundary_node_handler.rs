@@ -0,0 +1,36 @@
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::StatusCode;
+use std::net::SocketAddr;
+
+use crate::definition_store::{Change, ChangeKind};
+use crate::server_handlers::add_boundary_node_to_definition_handler::DefinitionNotFound;
+
+use super::{not_found, ok, Server};
+
+pub(super) async fn delete_boundary_node(
+    Path((ic_name, name)): Path<(String, String)>,
+    State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Result<String, (StatusCode, String)> {
+    let mut definitions = binding.supervisor.definitions.lock().await;
+
+    let running_definition = match definitions.get_mut(&ic_name) {
+        Some(d) => d,
+        None => {
+            return not_found(
+                binding.log,
+                format!("Couldn't find definition: '{}'", ic_name),
+                DefinitionNotFound { ic_name },
+            )
+        }
+    };
+
+    match running_definition.remove_boundary_node(&name).await {
+        Ok(()) => {
+            let change = Change::boundary_node(&client.to_string(), ChangeKind::BoundaryNodeRemoved, ic_name, name.clone());
+            binding.supervisor.persist(&definitions, &[change]);
+            ok(binding.log, format!("Deleted boundary node {}", name))
+        }
+        Err(e) => not_found(binding.log, format!("Boundary node {} could not be deleted", name), e),
+    }
+}
@@ -1,11 +1,16 @@
 use crate::definition::StopDefinitionError;
-use axum::extract::{Path, State};
+use axum::extract::{ConnectInfo, Path, State};
 use axum::http::StatusCode;
+use std::net::SocketAddr;
 
 use super::{forbidden, not_found, Server};
 
-pub(super) async fn delete_definition(Path(name): Path<String>, State(binding): State<Server>) -> Result<String, (StatusCode, String)> {
-    match binding.supervisor.stop(vec![name.clone()]).await {
+pub(super) async fn delete_definition(
+    Path(name): Path<String>,
+    State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+) -> Result<String, (StatusCode, String)> {
+    match binding.supervisor.stop(vec![name.clone()], &client.to_string()).await {
         Ok(_) => Ok(format!("Deleted definition {}", name)),
         Err(e) => match e.errors.into_iter().next().unwrap() {
             StopDefinitionError::DoesNotExist(e) => not_found(binding.log, format!("Definition with name '{}' doesn't exist", name), e),
@@ -1,7 +1,7 @@
 use base64::{engine::general_purpose as b64, Engine as _};
 use ic_crypto_utils_threshold_sig_der::parse_threshold_sig_key_from_der;
 use ic_registry_client::client::ThresholdSigPublicKey;
-use service_discovery::job_types::{JobType, JobTypeParseError};
+use service_discovery::job_types::{JobType, JobTypeParseError, NodeKind};
 use service_discovery::registry_sync::nns_reachable;
 
 use serde::{Deserialize, Serialize};
@@ -103,6 +103,16 @@ pub struct BoundaryNodeDto {
 }
 
 impl BoundaryNodeDto {
+    pub(crate) fn new(ic_name: String, bn: &BoundaryNode) -> Self {
+        Self {
+            name: bn.name.clone(),
+            ic_name,
+            custom_labels: bn.custom_labels.clone(),
+            targets: bn.targets.clone(),
+            job_type: bn.job_type.to_string(),
+        }
+    }
+
     pub(crate) fn try_into_boundary_node(self) -> Result<BoundaryNode, BadBoundaryNodeDtoError> {
         let job_type = match JobType::from_str(&self.job_type) {
             Err(e) => {
@@ -111,7 +121,7 @@ impl BoundaryNodeDto {
             }
             Ok(jt) => {
                 // Forbid addition of any job type not known to be supported by boundary nodes.
-                if !JobType::all_for_boundary_nodes().contains(&jt) {
+                if !jt.definition().applies_to(NodeKind::BoundaryNode) {
                     return Err(BadBoundaryNodeDtoError::UnsupportedJobType(self.job_type));
                 }
                 jt
@@ -0,0 +1,203 @@
+use super::Server;
+use crate::definition::{boundary_nodes_from_definitions, target_dtos_for_job_from_definitions, RunningDefinition};
+use crate::TargetFilterSpec;
+use axum::{
+    extract::{Query, State},
+    http::header,
+    http::{HeaderMap, HeaderValue, StatusCode},
+};
+use multiservice_discovery_shared::builders::prometheus_config_structure::{
+    map_target_group_http_sd, PrometheusStaticConfig, IC_NAME, JOB, METRICS_PATH, SCHEME,
+};
+use multiservice_discovery_shared::filters::shard_filter::fnv1a;
+use serde::Deserialize;
+use service_discovery::job_types::JobDefinition;
+use std::collections::BTreeMap;
+
+// The filters are a separate query, as flattening them would break parsing
+// the numbers of the query string
+#[derive(Deserialize)]
+pub(super) struct HttpSdQuery {
+    job: String,
+}
+
+pub fn serialize_definitions_to_http_sd(
+    definitions: &BTreeMap<String, RunningDefinition>,
+    job: &JobDefinition,
+    filters: &TargetFilterSpec,
+) -> String {
+    let ic_node_targets = map_target_group_http_sd(target_dtos_for_job_from_definitions(definitions, filters, job));
+
+    let boundary_nodes_targets = boundary_nodes_from_definitions(definitions, filters)
+        .into_iter()
+        .filter(|(_, bn)| bn.job_type.to_string() == job.name)
+        .map(|(definition_name, bn)| PrometheusStaticConfig {
+            targets: bn.targets.iter().map(|g| job.sockaddr(*g, true).to_string()).collect(),
+            labels: BTreeMap::from([
+                (IC_NAME.to_string(), definition_name),
+                ("name".to_string(), bn.name.clone()),
+                (JOB.to_string(), job.name.clone()),
+                (SCHEME.to_string(), job.scheme.clone()),
+                (METRICS_PATH.to_string(), job.path.clone()),
+            ])
+            .into_iter()
+            .chain(bn.custom_labels)
+            .collect(),
+        });
+
+    let targets = ic_node_targets.into_iter().chain(boundary_nodes_targets).collect::<Vec<_>>();
+    serde_json::to_string_pretty(&targets).unwrap()
+}
+
+/// The entity tag of the targets. Unlike `DefaultHasher` the hash doesn't
+/// depend on the Rust release, so all replicas agree on it.
+fn etag(text: &str) -> String {
+    format!("\"{:016x}\"", fnv1a(text.as_bytes()))
+}
+
+/// Whether the `If-None-Match` header of the request matches the entity tag
+fn not_modified(request_headers: &HeaderMap, etag: &str) -> bool {
+    request_headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        })
+}
+
+/// Serves the targets of a single job in the format of Prometheus' `http_sd_configs`.
+/// Unlike `/prom/targets` no targets is not an error, as Prometheus expects an
+/// empty list then.
+pub(super) async fn export_http_sd(
+    State(binding): State<Server>,
+    request_headers: HeaderMap,
+    Query(query): Query<HttpSdQuery>,
+    Query(filters): Query<TargetFilterSpec>,
+) -> Result<(StatusCode, HeaderMap, String), (StatusCode, String)> {
+    let job = binding
+        .supervisor
+        .job_definitions
+        .iter()
+        .find(|job| job.name == query.job)
+        .ok_or((StatusCode::BAD_REQUEST, format!("Unknown job {}", query.job)))?;
+    let definitions = binding.supervisor.definitions.lock().await;
+    let text = serialize_definitions_to_http_sd(&definitions, job, &filters);
+    let etag = etag(&text);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    if not_modified(&request_headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, headers, String::new()));
+    }
+    headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+    Ok((StatusCode::OK, headers, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+    use service_discovery::job_types::{JobType, NodeKind};
+    use service_discovery::mainnet_registry::{create_local_store_from_changelog, get_mainnet_delta_6d_c1};
+
+    use super::*;
+    use crate::definition::target_dtos_from_definitions;
+    use crate::server_handlers::tests::{add_idle_definition, server, server_with_jobs};
+
+    async fn http_sd(query: &str, request_headers: HeaderMap) -> Result<(StatusCode, HeaderMap, String), (StatusCode, String)> {
+        http_sd_of(server(), query, request_headers).await
+    }
+
+    async fn http_sd_of(server: Server, query: &str, request_headers: HeaderMap) -> Result<(StatusCode, HeaderMap, String), (StatusCode, String)> {
+        let uri: Uri = format!("http://localhost/prom/http_sd?{}", query).parse().unwrap();
+        let job = Query::<HttpSdQuery>::try_from_uri(&uri).map_err(|e| (e.status(), e.body_text()))?;
+        let filters = Query::<TargetFilterSpec>::try_from_uri(&uri).map_err(|e| (e.status(), e.body_text()))?;
+        export_http_sd(State(server), request_headers, job, filters).await
+    }
+
+    #[tokio::test]
+    async fn unchanged_targets_are_not_modified() {
+        let (status, headers, text) = http_sd("job=replica", HeaderMap::new()).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(text, "[]");
+        let etag = headers.get(header::ETAG).unwrap().clone();
+        assert_eq!(etag.to_str().unwrap(), super::etag("[]"));
+
+        let request_headers = HeaderMap::from_iter([(header::IF_NONE_MATCH, etag.clone())]);
+        let (status, headers, text) = http_sd("job=replica", request_headers).await.unwrap();
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(headers.get(header::ETAG), Some(&etag));
+        assert!(text.is_empty());
+
+        let request_headers = HeaderMap::from_iter([(header::IF_NONE_MATCH, HeaderValue::from_static("\"0000000000000000\""))]);
+        let (status, _, _) = http_sd("job=replica", request_headers).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn job_is_required() {
+        let (status, _) = http_sd("", HeaderMap::new()).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = http_sd("job=unknown", HeaderMap::new()).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn custom_jobs() {
+        let certificate_syncer = JobDefinition {
+            name: "certificate_syncer".to_string(),
+            port: 9322,
+            path: "/metrics".to_string(),
+            scheme: "http".to_string(),
+            address: Default::default(),
+            node_kinds: vec![NodeKind::IcNode],
+            assigned_nodes_only: false,
+        };
+        let replica = JobDefinition {
+            port: 9095,
+            ..JobType::Replica.definition()
+        };
+        let server = server_with_jobs(vec![certificate_syncer, replica]);
+        let dir = tempfile::tempdir().unwrap();
+        let _store = create_local_store_from_changelog(dir.path().join("mercury").join("mainnet"), get_mainnet_delta_6d_c1());
+        add_idle_definition(&server, dir.path(), "mercury").await;
+
+        let targets = |job: &'static str| {
+            let server = server.clone();
+            async move {
+                let (status, _, text) = http_sd_of(server, &format!("job={}", job), HeaderMap::new()).await.unwrap();
+                assert_eq!(status, StatusCode::OK);
+                serde_json::from_str::<Vec<PrometheusStaticConfig>>(&text).unwrap()
+            }
+        };
+
+        let certificate_syncer_targets = targets("certificate_syncer").await;
+        assert!(!certificate_syncer_targets.is_empty());
+        for config in &certificate_syncer_targets {
+            assert!(config.targets.iter().all(|target| target.ends_with(":9322")));
+            assert_eq!(config.labels[JOB], "certificate_syncer");
+            assert_eq!(config.labels[METRICS_PATH], "/metrics");
+        }
+
+        let replica_targets = targets("replica").await;
+        assert!(!replica_targets.is_empty());
+        assert!(replica_targets
+            .iter()
+            .flat_map(|config| &config.targets)
+            .all(|target| target.ends_with(":9095")));
+        // unassigned nodes are only scraped for the custom job
+        assert!(replica_targets.len() < certificate_syncer_targets.len());
+
+        // the custom jobs are part of the targets of the other endpoints too
+        let definitions = server.supervisor.definitions.lock().await;
+        let jobs = target_dtos_from_definitions(&definitions, &TargetFilterSpec::empty())
+            .iter()
+            .flat_map(|target| target.job_definitions())
+            .collect::<Vec<_>>();
+        assert!(jobs.iter().any(|job| job.name == "certificate_syncer"));
+        assert!(jobs.iter().filter(|job| job.name == "replica").all(|job| job.port == 9095));
+    }
+}
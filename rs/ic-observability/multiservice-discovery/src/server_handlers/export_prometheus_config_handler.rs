@@ -18,19 +18,18 @@ pub fn serialize_definitions_to_prometheus_config(definitions: BTreeMap<String,
 
     let boundary_nodes_targets = boundary_nodes_from_definitions(&definitions, &filters)
         .iter()
-        .map(|(definition_name, bn)| PrometheusStaticConfig {
-            targets: bn.targets.clone().iter().map(|g| bn.job_type.url(*g, true)).collect(),
-            labels: {
-                BTreeMap::from([
-                    ("ic", definition_name.clone()),
-                    ("name", bn.name.clone()),
-                    ("job", bn.job_type.to_string()),
-                ])
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v))
-                .chain(bn.custom_labels.clone())
-                .collect::<BTreeMap<_, _>>()
-            },
+        .map(|(definition_name, bn)| {
+            let job = definitions[definition_name].job_definition(bn.job_type);
+            PrometheusStaticConfig {
+                targets: bn.targets.iter().map(|g| job.url(*g, true)).collect(),
+                labels: {
+                    BTreeMap::from([("ic", definition_name.clone()), ("name", bn.name.clone()), ("job", job.name)])
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .chain(bn.custom_labels.clone())
+                        .collect::<BTreeMap<_, _>>()
+                },
+            }
         })
         .collect();
 
@@ -0,0 +1,27 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::server_handlers::dto::BoundaryNodeDto;
+
+use super::Server;
+
+#[derive(Deserialize)]
+pub(super) struct BoundaryNodesQuery {
+    ic_name: Option<String>,
+}
+
+pub(super) async fn get_boundary_nodes(
+    State(binding): State<Server>,
+    Query(query): Query<BoundaryNodesQuery>,
+) -> Result<Json<Vec<BoundaryNodeDto>>, (StatusCode, String)> {
+    let definitions = binding.supervisor.definitions.lock().await;
+
+    let list = definitions
+        .iter()
+        .filter(|(ic_name, _)| query.ic_name.as_ref().map_or(true, |name| name == *ic_name))
+        .flat_map(|(ic_name, d)| d.definition.boundary_nodes.iter().map(|bn| BoundaryNodeDto::new(ic_name.clone(), bn)))
+        .collect();
+    Ok(Json(list))
+}
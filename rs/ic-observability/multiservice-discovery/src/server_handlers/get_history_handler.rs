@@ -0,0 +1,17 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+
+use crate::definition_store::Change;
+
+use super::Server;
+
+pub(super) async fn get_history(State(binding): State<Server>) -> Result<Json<Vec<Change>>, (StatusCode, String)> {
+    match binding.supervisor.history() {
+        Ok(history) => Ok(Json(history)),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Couldn't read the history of definitions: {}", e),
+        )),
+    }
+}
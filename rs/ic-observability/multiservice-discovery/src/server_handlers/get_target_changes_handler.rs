@@ -0,0 +1,44 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::target_changes::{ChangesUnavailable, VersionedChange};
+use crate::TargetFilterSpec;
+
+use super::Server;
+
+#[derive(Deserialize)]
+pub(super) struct ChangesQuery {
+    since: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ChangesResponse {
+    version: u64,
+    changes: Vec<VersionedChange>,
+}
+
+/// Serves the targets that were added or removed after the `since` version,
+/// as recorded by [TargetChangeLog::run](crate::target_changes::TargetChangeLog::run).
+/// Clients that are too far behind get `410 Gone` and have to fetch all
+/// targets again.
+pub(super) async fn get_target_changes(
+    State(binding): State<Server>,
+    Query(query): Query<ChangesQuery>,
+    Query(filters): Query<TargetFilterSpec>,
+) -> Result<Json<ChangesResponse>, (StatusCode, String)> {
+    match binding.target_changes.since(query.since).await {
+        Ok((version, changes)) => Ok(Json(ChangesResponse {
+            version,
+            changes: changes.into_iter().filter(|c| filters.matches_target(&c.change.target)).collect(),
+        })),
+        Err(ChangesUnavailable { oldest, version }) => Err((
+            StatusCode::GONE,
+            format!(
+                "Only the changes since version {} are available, fetch /targets and continue from version {}",
+                oldest, version
+            ),
+        )),
+    }
+}
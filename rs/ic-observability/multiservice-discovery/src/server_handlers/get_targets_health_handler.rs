@@ -0,0 +1,35 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::prober::TargetHealth;
+
+use super::Server;
+
+#[derive(Deserialize)]
+pub(super) struct HealthQuery {
+    ic_name: Option<String>,
+    node_id: Option<String>,
+    job: Option<String>,
+    up: Option<bool>,
+}
+
+pub(super) async fn get_targets_health(
+    State(binding): State<Server>,
+    Query(query): Query<HealthQuery>,
+) -> Result<Json<Vec<TargetHealth>>, (StatusCode, String)> {
+    let Some(prober) = &binding.prober else {
+        return Err((StatusCode::NOT_FOUND, "Targets are not probed, start with --probe".to_string()));
+    };
+
+    let health = prober
+        .health()
+        .into_iter()
+        .filter(|h| query.ic_name.as_ref().map_or(true, |ic_name| *ic_name == h.ic_name))
+        .filter(|h| query.node_id.as_ref().map_or(true, |node_id| *node_id == h.node_id))
+        .filter(|h| query.job.as_ref().map_or(true, |job| *job == h.job))
+        .filter(|h| query.up.map_or(true, |up| up == h.up))
+        .collect();
+    Ok(Json(health))
+}
@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -10,22 +11,38 @@ use slog::{debug, info, Logger};
 
 use crate::definition::DefinitionsSupervisor;
 use crate::metrics::MSDMetrics;
+use crate::prober::Prober;
 use crate::server_handlers::add_boundary_node_to_definition_handler::add_boundary_node;
 use crate::server_handlers::add_definition_handler::add_definition;
+use crate::server_handlers::delete_boundary_node_handler::delete_boundary_node;
 use crate::server_handlers::delete_definition_handler::delete_definition;
+use crate::server_handlers::export_http_sd_handler::export_http_sd;
 use crate::server_handlers::export_prometheus_config_handler::export_prometheus_config;
 use crate::server_handlers::export_targets_handler::export_targets;
+use crate::server_handlers::get_boundary_nodes_handler::get_boundary_nodes;
 use crate::server_handlers::get_definition_handler::get_definitions;
+use crate::server_handlers::get_history_handler::get_history;
+use crate::server_handlers::get_target_changes_handler::get_target_changes;
+use crate::server_handlers::get_targets_health_handler::get_targets_health;
 use crate::server_handlers::replace_definitions_handler::replace_definitions;
+use crate::server_handlers::update_boundary_node_handler::update_boundary_node;
+use crate::target_changes::TargetChangeLog;
 
 mod add_boundary_node_to_definition_handler;
 mod add_definition_handler;
+mod delete_boundary_node_handler;
 mod delete_definition_handler;
 pub mod dto;
+mod export_http_sd_handler;
 pub mod export_prometheus_config_handler;
 mod export_targets_handler;
+mod get_boundary_nodes_handler;
 mod get_definition_handler;
+mod get_history_handler;
+mod get_target_changes_handler;
+mod get_targets_health_handler;
 mod replace_definitions_handler;
+mod update_boundary_node_handler;
 
 pub type WebResult<T> = Result<T, (StatusCode, String)>;
 
@@ -65,6 +82,8 @@ pub(crate) struct Server {
     registry_query_timeout: Duration,
     registry_path: PathBuf,
     metrics: MSDMetrics,
+    target_changes: TargetChangeLog,
+    prober: Option<Prober>,
 }
 
 impl Server {
@@ -75,6 +94,8 @@ impl Server {
         registry_query_timeout: Duration,
         registry_path: PathBuf,
         metrics: MSDMetrics,
+        target_changes: TargetChangeLog,
+        prober: Option<Prober>,
     ) -> Self {
         Self {
             log,
@@ -83,6 +104,8 @@ impl Server {
             registry_query_timeout,
             registry_path,
             metrics,
+            target_changes,
+            prober,
         }
     }
     pub(crate) async fn run(self, recv: tokio::sync::oneshot::Receiver<()>, metrics_layer: HttpMetricsLayer) {
@@ -93,14 +116,22 @@ impl Server {
             .route("/", get(get_definitions))
             .route("/:name", delete(delete_definition))
             .route("/prom/targets", get(export_prometheus_config))
+            .route("/prom/http_sd", get(export_http_sd))
             .route("/targets", get(export_targets))
+            .route("/targets/changes", get(get_target_changes))
+            .route("/targets/health", get(get_targets_health))
             .route("/add_boundary_node", post(add_boundary_node))
+            .route("/boundary_nodes", get(get_boundary_nodes))
+            .route("/boundary_nodes", post(add_boundary_node))
+            .route("/boundary_nodes", put(update_boundary_node))
+            .route("/boundary_nodes/:ic_name/:name", delete(delete_boundary_node))
+            .route("/history", get(get_history))
             .layer(metrics_layer)
             .with_state(self.clone());
 
         let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
         info!(self.log, "Server started on port {}", 8000);
-        axum::serve(listener, app)
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
             .with_graceful_shutdown(async move {
                 recv.await.unwrap();
             })
@@ -109,3 +140,144 @@ impl Server {
         info!(self.log, "Server stopped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use axum::extract::{ConnectInfo, Path, Query, State};
+    use axum::http::Uri;
+    use axum::Json;
+    use service_discovery::job_types::JobDefinition;
+    use slog::o;
+
+    use super::*;
+    use crate::definition::{Definition, RunningDefinition};
+    use crate::enrichment::Enrichers;
+    use crate::server_handlers::dto::BoundaryNodeDto;
+
+    /// A server without definitions, which has to be created in a runtime
+    pub(super) fn server() -> Server {
+        server_with_jobs(vec![])
+    }
+
+    /// Like [server], with the `custom` jobs in addition to the built-in ones
+    pub(super) fn server_with_jobs(custom: Vec<JobDefinition>) -> Server {
+        let log = Logger::root(slog::Discard, o!());
+        Server::new(
+            log.clone(),
+            DefinitionsSupervisor::new(tokio::runtime::Handle::current(), false, None, Enrichers::default(), custom, log).unwrap(),
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+            PathBuf::from("/tmp"),
+            MSDMetrics::new(),
+            TargetChangeLog::new(),
+            None,
+        )
+    }
+
+    /// Adds an idle definition with the ICs whose registries are in
+    /// `<registry_path>/<name>`
+    pub(super) async fn add_idle_definition(server: &Server, registry_path: &std::path::Path, name: &str) {
+        let definition = Definition::new(
+            vec![],
+            registry_path.to_path_buf(),
+            name.to_string(),
+            server.log.clone(),
+            None,
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+        );
+        definition.ic_discovery.load_new_ics(server.log.clone()).unwrap();
+        let definition = RunningDefinition::idle(definition, &server.supervisor);
+        server.supervisor.definitions.lock().await.insert(name.to_string(), definition);
+    }
+
+    fn boundary_node(ic_name: &str, name: &str, dc: &str) -> BoundaryNodeDto {
+        BoundaryNodeDto {
+            name: name.to_string(),
+            ic_name: ic_name.to_string(),
+            custom_labels: BTreeMap::from([("dc".to_string(), dc.to_string())]),
+            targets: BTreeSet::from(["[2a00:fb01:400:42:5000:aeff:fee0:fc5f]:9100".parse().unwrap()]),
+            job_type: "node_exporter".to_string(),
+        }
+    }
+
+    async fn list(server: &Server, query: &str) -> Vec<(String, String, String)> {
+        let uri: Uri = format!("http://localhost/boundary_nodes?{}", query).parse().unwrap();
+        let Json(list) = get_boundary_nodes(State(server.clone()), Query::try_from_uri(&uri).unwrap())
+            .await
+            .unwrap();
+        list.into_iter().map(|bn| (bn.ic_name, bn.name, bn.custom_labels["dc"].clone())).collect()
+    }
+
+    fn entry(ic_name: &str, name: &str, dc: &str) -> (String, String, String) {
+        (ic_name.to_string(), name.to_string(), dc.to_string())
+    }
+
+    #[tokio::test]
+    async fn boundary_node_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server();
+        for name in ["mercury", "testnet"] {
+            add_idle_definition(&server, dir.path(), name).await;
+        }
+        let client = || ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 8000)));
+
+        for bn in [
+            boundary_node("mercury", "bn-1", "zh2"),
+            boundary_node("mercury", "bn-2", "zh2"),
+            boundary_node("testnet", "bn-1", "sf1"),
+        ] {
+            add_boundary_node(State(server.clone()), client(), Json(bn)).await.unwrap();
+        }
+        assert_eq!(
+            list(&server, "").await,
+            vec![
+                entry("mercury", "bn-1", "zh2"),
+                entry("mercury", "bn-2", "zh2"),
+                entry("testnet", "bn-1", "sf1")
+            ]
+        );
+        assert_eq!(list(&server, "ic_name=testnet").await, vec![entry("testnet", "bn-1", "sf1")]);
+
+        update_boundary_node(State(server.clone()), client(), Json(boundary_node("mercury", "bn-1", "fr1")))
+            .await
+            .unwrap();
+        delete_boundary_node(Path(("mercury".to_string(), "bn-2".to_string())), State(server.clone()), client())
+            .await
+            .unwrap();
+        assert_eq!(
+            list(&server, "").await,
+            vec![entry("mercury", "bn-1", "fr1"), entry("testnet", "bn-1", "sf1")]
+        );
+
+        // Unknown boundary nodes and definitions
+        for (ic_name, name) in [("mercury", "bn-2"), ("unknown", "bn-1")] {
+            let (status, _) = update_boundary_node(State(server.clone()), client(), Json(boundary_node(ic_name, name, "fr1")))
+                .await
+                .unwrap_err();
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            let (status, _) = delete_boundary_node(Path((ic_name.to_string(), name.to_string())), State(server.clone()), client())
+                .await
+                .unwrap_err();
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+
+        // Ended definitions can't be changed
+        let testnet = server.supervisor.definitions.lock().await["testnet"].clone();
+        testnet.end().await;
+        let (status, message) = update_boundary_node(State(server.clone()), client(), Json(boundary_node("testnet", "bn-1", "fr1")))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(message.contains("definition testnet has ended"), "{}", message);
+        let (status, _) = delete_boundary_node(Path(("testnet".to_string(), "bn-1".to_string())), State(server.clone()), client())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(list(&server, "ic_name=testnet").await, vec![entry("testnet", "bn-1", "sf1")]);
+
+        server.supervisor.end().await;
+    }
+}
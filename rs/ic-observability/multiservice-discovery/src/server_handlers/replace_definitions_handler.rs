@@ -1,14 +1,19 @@
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
 use axum::http::StatusCode;
 use axum::Json;
 use futures::future::join_all;
+use std::net::SocketAddr;
 
 use crate::definition::{Definition, StartMode};
 use crate::server_handlers::dto::{BadDtoError, DefinitionDto};
 
 use super::{bad_request, ok, Server, WebResult};
 
-pub(super) async fn replace_definitions(State(binding): State<Server>, Json(definitions): Json<Vec<DefinitionDto>>) -> WebResult<String> {
+pub(super) async fn replace_definitions(
+    State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(definitions): Json<Vec<DefinitionDto>>,
+) -> WebResult<String> {
     // Cache old names if we need to remove them from metrics
     let dnames = definitions.iter().map(|d| d.name.clone()).collect::<Vec<String>>().join(", ");
 
@@ -43,6 +48,7 @@ pub(super) async fn replace_definitions(State(binding): State<Server>, Json(defi
             new_definitions.clone(),
             StartMode::ReplaceExistingDefinitions,
             binding.metrics.running_definition_metrics.clone(),
+            Some(&client.to_string()),
         )
         .await
     {
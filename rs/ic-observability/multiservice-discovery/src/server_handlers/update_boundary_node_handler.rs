@@ -0,0 +1,48 @@
+use axum::extract::{ConnectInfo, State};
+use axum::http::StatusCode;
+use axum::Json;
+use std::net::SocketAddr;
+
+use crate::definition_store::{Change, ChangeKind};
+use crate::server_handlers::add_boundary_node_to_definition_handler::DefinitionNotFound;
+use crate::server_handlers::dto::BoundaryNodeDto;
+
+use super::{bad_request, not_found, ok, Server};
+
+/// Replaces the targets, labels and job type of an existing boundary node
+pub(super) async fn update_boundary_node(
+    State(binding): State<Server>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(boundary_node): Json<BoundaryNodeDto>,
+) -> Result<String, (StatusCode, String)> {
+    let name = boundary_node.name.clone();
+    let ic_name = boundary_node.ic_name.clone();
+    let rejection = format!("Boundary node {} could not be updated", name);
+
+    let mut definitions = binding.supervisor.definitions.lock().await;
+
+    let running_definition = match definitions.get_mut(&ic_name) {
+        Some(d) => d,
+        None => {
+            return not_found(
+                binding.log,
+                format!("Couldn't find definition: '{}'", ic_name),
+                DefinitionNotFound { ic_name },
+            )
+        }
+    };
+
+    let bn = match boundary_node.try_into_boundary_node() {
+        Ok(bn) => bn,
+        Err(e) => return bad_request(binding.log, rejection, e),
+    };
+
+    match running_definition.update_boundary_node(bn).await {
+        Ok(()) => {
+            let change = Change::boundary_node(&client.to_string(), ChangeKind::BoundaryNodeUpdated, ic_name, name.clone());
+            binding.supervisor.persist(&definitions, &[change]);
+            ok(binding.log, format!("Boundary node {} updated successfully", name))
+        }
+        Err(e) => not_found(binding.log, rejection, e),
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use multiservice_discovery_shared::contracts::target::TargetDto;
+use multiservice_discovery_shared::contracts::target_change::{diff_targets, TargetChange};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::definition::{target_dtos_from_definitions, DefinitionsSupervisor, TargetFilterSpec};
+
+/// How many changes are kept at most. Clients that are further behind have to
+/// fetch all targets again.
+const MAX_CHANGES: usize = 100_000;
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct VersionedChange {
+    pub(crate) version: u64,
+    /// RFC 3339 timestamp of when the change was noticed
+    pub(crate) timestamp: String,
+    #[serde(flatten)]
+    pub(crate) change: TargetChange,
+}
+
+/// Records how the targets of all definitions change over time.
+///
+/// Every set of changes that is noticed gets the next version. Versions start
+/// at the startup time in milliseconds, so the versions of an earlier run are
+/// older than the changes that are kept.
+#[derive(Clone)]
+pub(crate) struct TargetChangeLog {
+    state: Arc<Mutex<TargetChangeLogState>>,
+}
+
+struct TargetChangeLogState {
+    version: u64,
+    /// All changes after this version are kept
+    oldest: u64,
+    /// The targets that were observed last, none before the first observation
+    targets: Option<Vec<TargetDto>>,
+    changes: VecDeque<VersionedChange>,
+}
+
+/// The changes since the requested version were dropped
+pub(crate) struct ChangesUnavailable {
+    pub(crate) oldest: u64,
+    pub(crate) version: u64,
+}
+
+impl TargetChangeLog {
+    pub(crate) fn new() -> Self {
+        let version = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        Self {
+            state: Arc::new(Mutex::new(TargetChangeLogState {
+                version,
+                oldest: version,
+                targets: None,
+                changes: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Records the changes from the previously observed targets to `targets`.
+    /// The first observation only seeds the targets, as the targets of before
+    /// the startup aren't known.
+    pub(crate) async fn observe(&self, targets: Vec<TargetDto>) {
+        let mut state = self.state.lock().await;
+        let Some(previous) = state.targets.replace(targets) else {
+            return;
+        };
+        let changes = diff_targets(&previous, state.targets.as_ref().unwrap());
+        if changes.is_empty() {
+            return;
+        }
+
+        state.version += 1;
+        let version = state.version;
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        state.changes.extend(changes.into_iter().map(|change| VersionedChange {
+            version,
+            timestamp: timestamp.clone(),
+            change,
+        }));
+
+        // Only drop whole versions
+        while state.changes.len() > MAX_CHANGES {
+            let dropped = state.changes.pop_front().unwrap();
+            state.oldest = dropped.version;
+        }
+        while state.changes.front().is_some_and(|c| c.version == state.oldest) {
+            state.changes.pop_front();
+        }
+    }
+
+    async fn observe_definitions(&self, supervisor: &DefinitionsSupervisor) {
+        let targets = {
+            let definitions = supervisor.definitions.lock().await;
+            target_dtos_from_definitions(&definitions, &TargetFilterSpec::empty())
+        };
+        self.observe(targets).await;
+    }
+
+    /// Notices the changes of the definitions every `interval`. This is the
+    /// only place the log is updated, requests are served from it as it is.
+    pub(crate) async fn run(self, supervisor: DefinitionsSupervisor, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.observe_definitions(&supervisor).await;
+        }
+    }
+
+    /// The current version and the changes after `since`. Without `since` only
+    /// the current version is returned.
+    pub(crate) async fn since(&self, since: Option<u64>) -> Result<(u64, Vec<VersionedChange>), ChangesUnavailable> {
+        let state = self.state.lock().await;
+        let Some(since) = since else {
+            return Ok((state.version, vec![]));
+        };
+        if since < state.oldest || since > state.version {
+            return Err(ChangesUnavailable {
+                oldest: state.oldest,
+                version: state.version,
+            });
+        }
+        let changes = state.changes.iter().filter(|c| c.version > since).cloned().collect();
+        Ok((state.version, changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use ic_types::PrincipalId;
+    use multiservice_discovery_shared::contracts::target_change::TargetChangeKind;
+    use service_discovery::job_types::JobType;
+
+    use super::*;
+
+    fn target(node: u64) -> TargetDto {
+        TargetDto {
+            node_id: PrincipalId::new_node_test_id(node).into(),
+            name: format!("node-{}", node),
+            ic_name: "mercury".to_string(),
+            subnet_id: None,
+            node_provider_id: PrincipalId::new_anonymous(),
+            dc_id: "dc1".to_string(),
+            targets: BTreeSet::new(),
+            jobs: vec![JobType::Replica],
+            custom_jobs: vec![],
+            operator_id: PrincipalId::new_anonymous(),
+            custom_labels: BTreeMap::new(),
+            is_api_bn: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_since_version() {
+        let log = TargetChangeLog::new();
+        let (start, _) = log.since(None).await.ok().unwrap();
+
+        // The first observation only seeds the targets
+        log.observe(vec![target(1), target(2)]).await;
+        assert_eq!(log.since(None).await.ok().unwrap().0, start);
+
+        log.observe(vec![target(1), target(2), target(3)]).await;
+        log.observe(vec![target(1), target(2), target(3)]).await;
+        log.observe(vec![target(1), target(3)]).await;
+
+        let (version, changes) = log.since(Some(start)).await.ok().unwrap();
+        assert_eq!(version, start + 2);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].change.kind, TargetChangeKind::Added);
+        assert_eq!(changes[0].change.target.name, "node-3");
+
+        let (_, changes) = log.since(Some(start + 1)).await.ok().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change.kind, TargetChangeKind::Removed);
+        assert_eq!(changes[0].change.target.name, "node-2");
+
+        assert!(log.since(Some(start + 2)).await.ok().unwrap().1.is_empty());
+        assert!(log.since(Some(start - 1)).await.is_err());
+        assert!(log.since(Some(start + 3)).await.is_err());
+    }
+}
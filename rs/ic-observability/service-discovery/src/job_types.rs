@@ -17,6 +17,133 @@ pub enum JobType {
     IcBoundary,
 }
 
+/// The kinds of nodes a job can be scraped on
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// Nodes of the registry that are not API boundary nodes
+    IcNode,
+    /// Nodes of the registry that are API boundary nodes
+    ApiBoundaryNode,
+    /// Boundary nodes that were added to a definition by hand
+    BoundaryNode,
+}
+
+/// Which address of a node a job is scraped on
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressMapping {
+    /// The address of the GuestOS, as found in the registry
+    #[default]
+    Guest,
+    /// The address of the HostOS, inferred from the one of the GuestOS
+    Host,
+    /// The address of the HostOS, except for boundary nodes, whose address is
+    /// used unchanged
+    HostForIcNodes,
+}
+
+impl AddressMapping {
+    fn apply(&self, sockaddr: SocketAddr, is_boundary_node: bool) -> SocketAddr {
+        match (self, is_boundary_node) {
+            (Self::Guest, _) => sockaddr,
+            (Self::Host, _) => guest_to_host_address(sockaddr),
+            (Self::HostForIcNodes, true) => sockaddr,
+            (Self::HostForIcNodes, false) => guest_to_host_address(sockaddr),
+        }
+    }
+}
+
+/// How to scrape a job, and on which nodes. The [JobType] variants are the
+/// built-in definitions, further jobs can be defined in configuration, e.g.
+/// for new services of the API boundary nodes.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct JobDefinition {
+    pub name: String,
+    pub port: u16,
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default = "default_scheme")]
+    pub scheme: String,
+    #[serde(default)]
+    pub address: AddressMapping,
+    pub node_kinds: Vec<NodeKind>,
+    /// Whether only nodes that are assigned to a subnet are scraped
+    #[serde(default)]
+    pub assigned_nodes_only: bool,
+}
+
+fn default_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_scheme() -> String {
+    "http".to_string()
+}
+
+impl JobDefinition {
+    pub fn applies_to(&self, node_kind: NodeKind) -> bool {
+        self.node_kinds.contains(&node_kind)
+    }
+
+    /// The socket address with the port of the job, and the host address if
+    /// the job is scraped on the HostOS.
+    pub fn sockaddr(&self, s: SocketAddr, is_boundary_node: bool) -> SocketAddr {
+        let mut ss = s;
+        ss.set_port(self.port);
+        self.address.apply(ss, is_boundary_node)
+    }
+
+    pub fn url(&self, s: SocketAddr, is_boundary_node: bool) -> String {
+        format!(
+            "{}://{}/{}",
+            self.scheme,
+            self.sockaddr(s, is_boundary_node),
+            self.path.trim_start_matches('/'),
+        )
+    }
+
+    /// The built-in job of the same name, if there is one
+    pub fn job_type(&self) -> Option<JobType> {
+        JobType::from_str(&self.name).ok()
+    }
+
+    /// Whether this is the unchanged definition of a built-in job
+    pub fn is_built_in(&self) -> bool {
+        self.job_type().is_some_and(|job_type| job_type.definition() == *self)
+    }
+
+    /// The built-in definitions, followed by the `custom` ones. A custom
+    /// definition with the name of a built-in job replaces it.
+    pub fn with_defaults(custom: Vec<JobDefinition>) -> Result<Vec<JobDefinition>, JobDefinitionError> {
+        let mut definitions = JobType::all().iter().map(JobType::definition).collect::<Vec<_>>();
+        for (i, definition) in custom.iter().enumerate() {
+            if custom[..i].iter().any(|d| d.name == definition.name) {
+                return Err(JobDefinitionError {
+                    name: definition.name.clone(),
+                });
+            }
+            match definitions.iter_mut().find(|d| d.name == definition.name) {
+                Some(built_in) => *built_in = definition.clone(),
+                None => definitions.push(definition.clone()),
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+#[derive(Debug)]
+pub struct JobDefinitionError {
+    name: String,
+}
+impl std::error::Error for JobDefinitionError {}
+
+impl fmt::Display for JobDefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Job {} is defined more than once", self.name)
+    }
+}
+
 /// By convention, the first two bytes of the host-part of the replica's IP
 /// address are 0x6801. The corresponding segment for the host is 0x6800.
 ///
@@ -66,6 +193,40 @@ impl JobType {
         }
     }
 
+    pub fn address(&self) -> AddressMapping {
+        match self {
+            Self::NodeExporter(NodeOS::Host) => AddressMapping::Host,
+            Self::MetricsProxy(NodeOS::Host) => AddressMapping::HostForIcNodes,
+            _ => AddressMapping::Guest,
+        }
+    }
+    pub fn node_kinds(&self) -> Vec<NodeKind> {
+        [
+            (NodeKind::IcNode, Self::all_for_ic_nodes()),
+            (NodeKind::ApiBoundaryNode, Self::all_for_api_boundary_nodes()),
+            (NodeKind::BoundaryNode, Self::all_for_boundary_nodes()),
+        ]
+        .into_iter()
+        .filter(|(_, jobs)| jobs.contains(self))
+        .map(|(node_kind, _)| node_kind)
+        .collect()
+    }
+
+    /// The built-in definition of the job
+    pub fn definition(&self) -> JobDefinition {
+        JobDefinition {
+            name: self.to_string(),
+            port: self.port(),
+            path: self.endpoint().to_string(),
+            scheme: self.scheme().to_string(),
+            address: self.address(),
+            node_kinds: self.node_kinds(),
+            // replica targets are only exposed if they are assigned to a
+            // subnet (i.e. if the subnet id is set)
+            assigned_nodes_only: *self == Self::Replica,
+        }
+    }
+
     // Return the socket address with the correct port and IP address.
     // Any non-guest IP address is returned unchanged.  Any guest IP
     // address that needs changing to host is returned with host IP.
@@ -74,18 +235,7 @@ impl JobType {
     pub fn sockaddr(&self, s: SocketAddr, is_boundary_node: bool) -> SocketAddr {
         let mut ss = s;
         ss.set_port(self.port());
-        if *self == Self::NodeExporter(NodeOS::Host) {
-            guest_to_host_address(ss)
-        } else if *self == Self::MetricsProxy(NodeOS::Host) {
-            match is_boundary_node {
-                // This is a boundary node IP.  Return it unchanged.
-                true => ss,
-                // Change GuestOS IP to HostOS IP.
-                false => guest_to_host_address(ss),
-            }
-        } else {
-            ss
-        }
+        self.address().apply(ss, is_boundary_node)
     }
 
     pub fn ip(&self, s: SocketAddr, is_boundary_node: bool) -> IpAddr {
@@ -104,6 +254,20 @@ impl JobType {
 
 /// This is duplicated in impl Job.
 impl JobType {
+    pub fn all() -> Vec<Self> {
+        [
+            JobType::Replica,
+            JobType::Orchestrator,
+            JobType::NodeExporter(NodeOS::Guest),
+            JobType::NodeExporter(NodeOS::Host),
+            JobType::MetricsProxy(NodeOS::Host),
+            JobType::MetricsProxy(NodeOS::Guest),
+            JobType::IcBoundary,
+        ]
+        .into_iter()
+        .collect::<Vec<Self>>()
+    }
+
     pub fn all_for_ic_nodes() -> Vec<Self> {
         [
             JobType::Replica,
@@ -172,6 +336,7 @@ impl FromStr for JobType {
             "orchestrator" => Ok(JobType::Orchestrator),
             "host_metrics_proxy" => Ok(JobType::MetricsProxy(NodeOS::Host)),
             "guest_metrics_proxy" => Ok(JobType::MetricsProxy(NodeOS::Guest)),
+            "ic_boundary" => Ok(JobType::IcBoundary),
             _ => Err(JobTypeParseError { input: s.to_string() }),
         }
     }
@@ -201,3 +366,66 @@ impl fmt::Display for JobType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUEST_ADDRESS: &str = "[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9090";
+
+    #[test]
+    fn built_in_definitions() {
+        let guest = GUEST_ADDRESS.parse().unwrap();
+        let metrics_proxy = JobType::MetricsProxy(NodeOS::Host).definition();
+        assert_eq!(metrics_proxy.name, "host_metrics_proxy");
+        assert_eq!(metrics_proxy.node_kinds, vec![NodeKind::IcNode]);
+        assert_eq!(
+            metrics_proxy.url(guest, false),
+            "https://[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:19100/metrics"
+        );
+        assert_eq!(
+            metrics_proxy.url(guest, true),
+            "https://[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:19100/metrics"
+        );
+
+        assert_eq!(
+            JobType::Replica.definition().node_kinds,
+            vec![NodeKind::IcNode, NodeKind::ApiBoundaryNode, NodeKind::BoundaryNode]
+        );
+        assert!(JobType::Replica.definition().assigned_nodes_only);
+        assert_eq!(JobType::IcBoundary.definition().node_kinds, vec![NodeKind::ApiBoundaryNode]);
+        assert_eq!(
+            JobType::NodeExporter(NodeOS::Host).definition().url(guest, true),
+            "https://[2a00:fb01:400:42:6800:aeff:fee0:fc5f]:9100/metrics"
+        );
+    }
+
+    #[test]
+    fn custom_definitions() {
+        let guest = GUEST_ADDRESS.parse().unwrap();
+        let custom: Vec<JobDefinition> =
+            serde_json::from_str(r#"[{"name": "certificate_syncer", "port": 9322, "node_kinds": ["api_boundary_node"]}]"#).unwrap();
+        assert_eq!(custom[0].url(guest, false), "http://[2a00:fb01:400:42:6801:aeff:fee0:fc5f]:9322/metrics");
+        assert!(custom[0].applies_to(NodeKind::ApiBoundaryNode));
+        assert!(!custom[0].applies_to(NodeKind::IcNode));
+
+        let definitions = JobDefinition::with_defaults(custom.clone()).unwrap();
+        assert_eq!(definitions.len(), JobType::all().len() + 1);
+
+        assert!(!definitions[JobType::all().len()].is_built_in());
+        assert_eq!(definitions[JobType::all().len()].job_type(), None);
+
+        let replica = JobDefinition {
+            name: "replica".to_string(),
+            ..custom[0].clone()
+        };
+        let definitions = JobDefinition::with_defaults(vec![replica.clone()]).unwrap();
+        assert_eq!(definitions.len(), JobType::all().len());
+        assert_eq!(definitions[0], replica);
+        assert_eq!(replica.job_type(), Some(JobType::Replica));
+        assert!(!replica.is_built_in());
+        assert!(definitions[1..].iter().all(JobDefinition::is_built_in));
+
+        assert!(JobDefinition::with_defaults(vec![custom[0].clone(), custom[0].clone()]).is_err());
+    }
+}
@@ -16,16 +16,20 @@ use std::{
 
 use anyhow::Result;
 use ic_interfaces_registry::{RegistryClient, RegistryClientResult};
-use ic_protobuf::registry::node::v1::NodeRecord;
+use ic_protobuf::registry::{
+    dc::v1::DataCenterRecord, node::v1::NodeRecord, subnet::v1::SubnetRecord, unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
+};
 use ic_registry_client::client::{RegistryClientError, RegistryVersion};
 use ic_registry_client_helpers::{
     api_boundary_node::ApiBoundaryNodeRegistry,
+    deserialize_registry_value,
     node::{NodeId, NodeRegistry, SubnetId},
     node_operator::{NodeOperatorRegistry, PrincipalId},
     subnet::{SubnetListRegistry, SubnetTransportRegistry},
 };
+use ic_registry_keys::{make_subnet_record_key, make_unassigned_nodes_config_record_key, DATA_CENTER_KEY_PREFIX};
 use ic_registry_local_registry::{LocalRegistry, LocalRegistryError};
-use job_types::JobType;
+use job_types::{JobDefinition, JobType, NodeKind};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use slog::{warn, Logger};
@@ -46,7 +50,13 @@ pub trait IcServiceDiscovery: Send + Sync {
     ///
     /// The job name must be one of `replica`, `orchestrator`, `node_exporter`,
     /// or `host_node_exporter`.
-    fn get_target_groups(&self, job_name: JobType, log: Logger) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError>;
+    fn get_target_groups(&self, job_name: JobType, log: Logger) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError> {
+        self.get_target_groups_for_job(&job_name.definition(), log)
+    }
+
+    /// Returns a list of [TargetGroup] containing the targets of the nodes
+    /// the `job` applies to, with the addresses the job is scraped on.
+    fn get_target_groups_for_job(&self, job: &JobDefinition, log: Logger) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError>;
 }
 
 /// A [TargetGroup] associates a set of scrape targets with
@@ -67,6 +77,14 @@ pub struct TargetGroup {
 }
 
 impl TargetGroup {
+    /// The kind of the node in the registry
+    pub fn node_kind(&self) -> NodeKind {
+        match self.is_api_bn {
+            true => NodeKind::ApiBoundaryNode,
+            false => NodeKind::IcNode,
+        }
+    }
+
     pub fn get_ip_as_str(&self) -> Option<String> {
         let regex = Regex::new(r"\[.*\]").unwrap();
 
@@ -86,6 +104,17 @@ impl TargetGroup {
     }
 }
 
+/// Registry data describing a node, rather than how to scrape it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeMetadata {
+    /// `<continent>,<country>,<city>` of the data center
+    pub dc_region: Option<String>,
+    pub dc_owner: Option<String>,
+    /// The version of the subnet, or of the unassigned nodes
+    pub guestos_version: Option<String>,
+    pub hostos_version: Option<String>,
+}
+
 /// Exposes service discovery data for a set of Internet Computers. Manages a
 /// directory containing a registry local store for every Internet Computer
 /// whose discovery data is exposed. Each local store is updated on a regular
@@ -258,6 +287,82 @@ impl IcServiceDiscoveryImpl {
         Ok(node_targets)
     }
 
+    /// Returns the latest version of each registry, by IC name.
+    pub fn get_registry_versions(&self) -> BTreeMap<String, RegistryVersion> {
+        let registries_lock_guard = self.registries.read().unwrap();
+        registries_lock_guard
+            .iter()
+            .map(|(ic_name, registry)| (ic_name.clone(), registry.get_latest_version()))
+            .collect()
+    }
+
+    /// Returns the [NodeMetadata] of all nodes of the registries.
+    pub fn get_node_metadata(&self) -> Result<BTreeMap<NodeId, NodeMetadata>, IcServiceDiscoveryError> {
+        let registries_lock_guard = self.registries.read().unwrap();
+        registries_lock_guard.values().try_fold(BTreeMap::new(), |mut a, registry| {
+            a.append(&mut Self::get_metadata(registry)?);
+            Ok::<_, IcServiceDiscoveryError>(a)
+        })
+    }
+
+    fn get_metadata(reg_client: &dyn RegistryClient) -> Result<BTreeMap<NodeId, NodeMetadata>, IcServiceDiscoveryError> {
+        let latest_version = reg_client.get_latest_version();
+        let mut metadata = BTreeMap::new();
+
+        let subnet_ids = reg_client
+            .get_subnet_ids(latest_version)
+            .map_registry_err(latest_version, "get_subnet_ids")?;
+        for subnet_id in subnet_ids {
+            let subnet_record = deserialize_registry_value::<SubnetRecord>(reg_client.get_value(&make_subnet_record_key(subnet_id), latest_version))?;
+            let node_records = reg_client
+                .get_subnet_node_records(subnet_id, latest_version)
+                .map_registry_err(latest_version, "get_subnet_node_records")?;
+            for (node_id, node_record) in node_records {
+                let guestos_version = subnet_record.as_ref().map(|r| r.replica_version_id.clone());
+                metadata.insert(node_id, Self::node_metadata(reg_client, latest_version, node_record, guestos_version));
+            }
+        }
+
+        let unassigned_config = deserialize_registry_value::<UnassignedNodesConfigRecord>(
+            reg_client.get_value(&make_unassigned_nodes_config_record_key(), latest_version),
+        )?;
+        for node_id in reg_client.get_node_ids(latest_version)? {
+            if metadata.contains_key(&node_id) {
+                continue;
+            }
+            if let Ok(Some(node_record)) = reg_client.get_node_record(node_id, latest_version) {
+                let guestos_version = unassigned_config.as_ref().map(|c| c.replica_version.clone());
+                metadata.insert(node_id, Self::node_metadata(reg_client, latest_version, node_record, guestos_version));
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    fn node_metadata(
+        reg_client: &dyn RegistryClient,
+        latest_version: RegistryVersion,
+        node_record: NodeRecord,
+        guestos_version: Option<String>,
+    ) -> NodeMetadata {
+        let operator_id = PrincipalId::try_from(node_record.node_operator_id).unwrap_or_default();
+        let node_operator = reg_client
+            .get_node_operator_record(operator_id, latest_version)
+            .unwrap_or_default()
+            .unwrap_or_default();
+        let data_center = deserialize_registry_value::<DataCenterRecord>(
+            reg_client.get_value(&format!("{}{}", DATA_CENTER_KEY_PREFIX, node_operator.dc_id), latest_version),
+        )
+        .unwrap_or_default();
+
+        NodeMetadata {
+            dc_region: data_center.as_ref().map(|dc| dc.region.clone()),
+            dc_owner: data_center.map(|dc| dc.owner),
+            guestos_version,
+            hostos_version: node_record.hostos_version_id,
+        }
+    }
+
     fn add_node_to_node_targets(
         node_id: NodeId,
         latest_version: RegistryVersion,
@@ -309,8 +414,8 @@ impl IcServiceDiscoveryImpl {
 }
 
 impl IcServiceDiscovery for IcServiceDiscoveryImpl {
-    fn get_target_groups(&self, job_type: JobType, log: Logger) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError> {
-        let mapping = Box::new(|sockaddr: SocketAddr| job_type.sockaddr(sockaddr, false));
+    fn get_target_groups_for_job(&self, job: &JobDefinition, log: Logger) -> Result<BTreeSet<TargetGroup>, IcServiceDiscoveryError> {
+        let mapping = Box::new(|sockaddr: SocketAddr| job.sockaddr(sockaddr, false));
         let registries_lock_guard = self.registries.read().unwrap();
         let target_list = registries_lock_guard.iter().try_fold(BTreeSet::new(), |mut a, (ic_name, registry)| {
             a.append(&mut Self::get_targets(registry, ic_name, log.clone())?);
@@ -320,9 +425,10 @@ impl IcServiceDiscovery for IcServiceDiscoveryImpl {
         Ok(target_list
             .into_iter()
             .filter_map(|target_group| {
-                // replica targets are only exposed if they are assigned to a
-                // subnet (i.e. if the subnet id is set)
-                if job_type != JobType::Replica || target_group.subnet_id.is_some() {
+                if !job.applies_to(target_group.node_kind()) {
+                    return None;
+                }
+                if !job.assigned_nodes_only || target_group.subnet_id.is_some() {
                     let targets: BTreeSet<_> = target_group.targets.into_iter().map(&mapping).collect();
                     if !targets.is_empty() {
                         return Some(TargetGroup { targets, ..target_group });
@@ -471,4 +577,45 @@ mod tests {
         // there are 29 subnets at version 0x6dc1, and unassigned nodes belong to `None`
         assert_eq!(subnet_count, 29);
     }
+
+    #[test]
+    fn target_groups_for_job_follow_its_node_kinds() {
+        let tempdir = TempDir::new().unwrap();
+        let _store = create_local_store_from_changelog(tempdir.path().join("mainnet"), get_mainnet_delta_6d_c1());
+        let log = slog::Logger::root(slog::Discard, o!());
+        let ic_scraper = IcServiceDiscoveryImpl::new(log.clone(), tempdir.path(), QUERY_TIMEOUT).unwrap();
+        ic_scraper.load_new_ics(log.clone()).unwrap();
+
+        let target_groups = |node_kinds: Vec<NodeKind>, assigned_nodes_only: bool| {
+            let job = JobDefinition {
+                name: "custom".to_string(),
+                port: 9322,
+                path: "/metrics".to_string(),
+                scheme: "http".to_string(),
+                address: Default::default(),
+                node_kinds,
+                assigned_nodes_only,
+            };
+            ic_scraper.get_target_groups_for_job(&job, log.clone()).unwrap()
+        };
+
+        let ic_nodes = target_groups(vec![NodeKind::IcNode], false);
+        assert!(!ic_nodes.is_empty());
+        assert!(ic_nodes.iter().all(|g| !g.is_api_bn));
+        assert!(ic_nodes.iter().flat_map(|g| &g.targets).all(|t| t.port() == 9322));
+
+        let api_boundary_nodes = target_groups(vec![NodeKind::ApiBoundaryNode], false);
+        assert!(api_boundary_nodes.iter().all(|g| g.is_api_bn));
+
+        let all_nodes = target_groups(vec![NodeKind::IcNode, NodeKind::ApiBoundaryNode], false);
+        assert_eq!(all_nodes, ic_nodes.union(&api_boundary_nodes).cloned().collect());
+
+        let assigned_ic_nodes = target_groups(vec![NodeKind::IcNode], true);
+        assert!(assigned_ic_nodes.iter().all(|g| g.subnet_id.is_some()));
+        assert!(assigned_ic_nodes.is_subset(&ic_nodes));
+
+        // boundary nodes are not in the registry
+        assert!(target_groups(vec![NodeKind::BoundaryNode], false).is_empty());
+        assert!(target_groups(vec![], false).is_empty());
+    }
 }
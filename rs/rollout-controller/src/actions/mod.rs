@@ -29,6 +29,43 @@ pub enum SubnetAction {
     WaitForNextWeek {
         subnet_short: String,
     },
+    /// A canary of the stage bakes before the rest of the stage is updated
+    CanaryBaking {
+        subnet_short: String,
+        remaining: Duration,
+    },
+    /// The canaries of the stage baked and are healthy, so the rest of the stage is updated
+    PromoteCanary {
+        canaries: Vec<String>,
+    },
+    /// The rollout, or only the subnet if it's given, can't proceed for now
+    Blocked {
+        subnet_short: Option<String>,
+        reason: String,
+    },
+    /// The subnet failed its health checks and the rollout should not proceed
+    Halt {
+        subnet_short: String,
+        reason: String,
+    },
+    /// The subnet failed its health checks and should go back to the previous version
+    Rollback {
+        subnet_principal: PrincipalId,
+        version: String,
+        reason: String,
+    },
+    HostosBaking {
+        node_group: String,
+        remaining: Duration,
+    },
+    PendingHostosProposal {
+        proposal_id: u64,
+    },
+    PlaceHostosProposal {
+        node_group: String,
+        nodes: Vec<PrincipalId>,
+        version: String,
+    },
 }
 
 impl SubnetAction {
@@ -58,47 +95,153 @@ impl SubnetAction {
             SubnetAction::WaitForNextWeek { subnet_short } => {
                 format!("Waiting for next week to place proposal for '{}'", subnet_short)
             }
+            SubnetAction::CanaryBaking { subnet_short, remaining } => {
+                let humantime = humantime::format_duration(*remaining);
+                format!("Canary subnet '{}' is pending to bake for {}", subnet_short, humantime)
+            }
+            SubnetAction::PromoteCanary { canaries } => {
+                format!(
+                    "Canaries '{}' baked, promoting the version to the rest of the stage",
+                    canaries.join("', '")
+                )
+            }
+            SubnetAction::Blocked { subnet_short, reason } => match subnet_short {
+                Some(subnet_short) => format!("Subnet '{}' is blocked because {}", subnet_short, reason),
+                None => format!("Rollout is blocked because {}", reason),
+            },
+            SubnetAction::Halt { subnet_short, reason } => {
+                format!("Halting the rollout because subnet '{}' failed health checks: {}", subnet_short, reason)
+            }
+            SubnetAction::Rollback {
+                subnet_principal,
+                version,
+                reason,
+            } => format!(
+                "Placing proposal to roll back '{}' to version '{}' because it failed health checks: {}",
+                subnet_principal, version, reason
+            ),
+            SubnetAction::HostosBaking { node_group, remaining } => {
+                let humantime = humantime::format_duration(*remaining);
+                format!("HostOS update of {} is pending to bake for {}", node_group, humantime)
+            }
+            SubnetAction::PendingHostosProposal { proposal_id } => {
+                format!("HostOS update proposal with id '{}' has to be voted on", proposal_id)
+            }
+            SubnetAction::PlaceHostosProposal { node_group, nodes, version } => format!(
+                "Placing proposal to update {} nodes of {} to HostOS version '{}'",
+                nodes.len(),
+                node_group,
+                version
+            ),
         }
     }
+
+    /// Whether the rollout must not proceed after this action
+    pub fn halts_rollout(&self) -> bool {
+        matches!(self, SubnetAction::Halt { .. } | SubnetAction::Rollback { .. })
+    }
+
+    /// Whether executing this action places a proposal
+    pub fn places_proposal(&self) -> bool {
+        matches!(
+            self,
+            SubnetAction::PlaceProposal { .. } | SubnetAction::Rollback { .. } | SubnetAction::PlaceHostosProposal { .. }
+        )
+    }
 }
 
 impl<'a> SubnetAction {
-    async fn execute(&self, executor: &'a ActionExecutor<'_>, blessed_replica_versions: &'a [String]) -> anyhow::Result<()> {
+    async fn execute(&self, executor: &'a ActionExecutor<'_>, elected_versions: &'a [String]) -> anyhow::Result<()> {
         if let Some(logger) = executor.logger {
             info!(logger, "Subnet action: {}", self.print())
         }
-        if let SubnetAction::PlaceProposal {
-            is_unassigned,
-            subnet_principal,
-            version,
-        } = self
-        {
-            if !blessed_replica_versions.contains(version) {
-                return Err(anyhow::anyhow!("GuestOS version '{}' is not elected.", version));
+        match self {
+            SubnetAction::PlaceProposal {
+                is_unassigned,
+                subnet_principal,
+                version,
+            } => {
+                if !elected_versions.contains(version) {
+                    return Err(anyhow::anyhow!("GuestOS version '{}' is not elected.", version));
+                }
+                let principal_string = subnet_principal.to_string();
+
+                let proposal = match is_unassigned {
+                    true => ProposeCommand::DeployGuestosToAllUnassignedNodes {
+                        replica_version: version.to_string(),
+                    },
+                    false => ProposeCommand::DeployGuestosToAllSubnetNodes {
+                        subnet: *subnet_principal,
+                        version: version.to_string(),
+                    },
+                };
+
+                let opts = ProposeOptions {
+                    title: Some(format!(
+                        "Update subnet {} to GuestOS version {}",
+                        principal_string.split_once('-').expect("Should contain '-'").0,
+                        version.split_at(8).0
+                    )),
+                    summary: Some(format!("Update subnet {} to GuestOS version {}", principal_string, version)),
+                    ..Default::default()
+                };
+
+                executor.ic_admin_wrapper.propose_run(proposal, opts, executor.simulate).await?;
             }
-            let principal_string = subnet_principal.to_string();
+            SubnetAction::Rollback {
+                subnet_principal,
+                version,
+                reason,
+            } => {
+                if !elected_versions.contains(version) {
+                    return Err(anyhow::anyhow!("GuestOS version '{}' to roll back to is not elected.", version));
+                }
+                let principal_string = subnet_principal.to_string();
 
-            let proposal = match is_unassigned {
-                true => ProposeCommand::DeployGuestosToAllUnassignedNodes {
-                    replica_version: version.to_string(),
-                },
-                false => ProposeCommand::DeployGuestosToAllSubnetNodes {
+                let proposal = ProposeCommand::DeployGuestosToAllSubnetNodes {
                     subnet: *subnet_principal,
                     version: version.to_string(),
-                },
-            };
-
-            let opts = ProposeOptions {
-                title: Some(format!(
-                    "Update subnet {} to GuestOS version {}",
-                    principal_string.split_once('-').expect("Should contain '-'").0,
-                    version.split_at(8).0
-                )),
-                summary: Some(format!("Update subnet {} to GuestOS version {}", principal_string, version)),
-                ..Default::default()
-            };
-
-            executor.ic_admin_wrapper.propose_run(proposal, opts, executor.simulate).await?;
+                };
+
+                let opts = ProposeOptions {
+                    title: Some(format!(
+                        "Roll back subnet {} to GuestOS version {}",
+                        principal_string.split_once('-').expect("Should contain '-'").0,
+                        version.split_at(8).0
+                    )),
+                    summary: Some(format!(
+                        "Roll back subnet {} to GuestOS version {} after it failed health checks: {}",
+                        principal_string, version, reason
+                    )),
+                    ..Default::default()
+                };
+
+                executor.ic_admin_wrapper.propose_run(proposal, opts, executor.simulate).await?;
+            }
+            SubnetAction::PlaceHostosProposal { node_group, nodes, version } => {
+                if !elected_versions.contains(version) {
+                    return Err(anyhow::anyhow!("HostOS version '{}' is not elected.", version));
+                }
+
+                let proposal = ProposeCommand::DeployHostosToSomeNodes {
+                    nodes: nodes.clone(),
+                    version: version.to_string(),
+                };
+
+                let opts = ProposeOptions {
+                    title: Some(format!("Set HostOS version: {} on {} nodes", version, nodes.len())),
+                    summary: Some(format!(
+                        "Set HostOS version {} on the following nodes of {}:\n{}",
+                        version,
+                        node_group,
+                        nodes.iter().map(|n| format!("- {}", n)).collect::<Vec<_>>().join("\n")
+                    )),
+                    ..Default::default()
+                };
+
+                executor.ic_admin_wrapper.propose_run(proposal, opts, executor.simulate).await?;
+            }
+            _ => {}
         }
 
         Ok(())
@@ -130,7 +273,7 @@ impl<'a> ActionExecutor<'a> {
         })
     }
 
-    pub async fn execute(&self, actions: &[SubnetAction], blessed_replica_versions: &[String]) -> anyhow::Result<()> {
+    pub async fn execute(&self, actions: &[SubnetAction], elected_versions: &[String]) -> anyhow::Result<()> {
         if let Some(logger) = self.logger {
             info!(logger, "Executing following actions: {:?}", actions)
         }
@@ -139,7 +282,7 @@ impl<'a> ActionExecutor<'a> {
             if let Some(logger) = self.logger {
                 info!(logger, "Executing action {}: {:?}", i, action)
             }
-            action.execute(self, blessed_replica_versions).await?;
+            action.execute(self, elected_versions).await?;
         }
 
         Ok(())
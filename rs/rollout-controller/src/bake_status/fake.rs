@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+use ic_management_backend::proposal::SubnetUpdateProposal;
+use ic_management_types::Subnet;
+
+use super::BakeStatusProvider;
+
+/// In-memory bake status for tests that should not depend on Prometheus
+#[derive(Clone, Default)]
+pub struct FakeBakeStatus {
+    pub last_bake_status: BTreeMap<String, f64>,
+    pub since_start: f64,
+}
+
+impl BakeStatusProvider for FakeBakeStatus {
+    async fn last_bake_status(
+        &self,
+        _subnets: &[Subnet],
+        _subnet_update_proposals: &[SubnetUpdateProposal],
+    ) -> anyhow::Result<BTreeMap<String, f64>> {
+        Ok(self.last_bake_status.clone())
+    }
+
+    async fn since_start(&self, _subnet_update_proposals: &[SubnetUpdateProposal], _versions: &[String]) -> anyhow::Result<f64> {
+        Ok(self.since_start)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use ic_management_backend::proposal::SubnetUpdateProposal;
+use ic_management_types::Subnet;
+use prometheus_http_query::Client;
+
+use self::{prometheus::PrometheusBakeStatus, registry::RegistryBakeStatus};
+
+#[cfg(test)]
+pub mod fake;
+pub mod prometheus;
+pub mod registry;
+
+/// Source of the bake status of subnets
+pub trait BakeStatusProvider {
+    /// Seconds that each subnet, keyed by its principal, has been running its
+    /// current version.
+    async fn last_bake_status(&self, subnets: &[Subnet], subnet_update_proposals: &[SubnetUpdateProposal]) -> anyhow::Result<BTreeMap<String, f64>>;
+
+    /// Seconds since the first of `versions` was deployed to a subnet.
+    async fn since_start(&self, subnet_update_proposals: &[SubnetUpdateProposal], versions: &[String]) -> anyhow::Result<f64>;
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum BakeStatusSource {
+    Prometheus,
+    Registry,
+}
+
+pub enum BakeStatusProviderImplementation {
+    Prometheus(PrometheusBakeStatus),
+    Registry(RegistryBakeStatus),
+    #[cfg(test)]
+    Fake(fake::FakeBakeStatus),
+}
+
+pub fn resolve(source: BakeStatusSource, prometheus_client: Client) -> BakeStatusProviderImplementation {
+    match source {
+        BakeStatusSource::Prometheus => BakeStatusProviderImplementation::Prometheus(PrometheusBakeStatus::new(prometheus_client)),
+        BakeStatusSource::Registry => BakeStatusProviderImplementation::Registry(RegistryBakeStatus),
+    }
+}
+
+impl BakeStatusProviderImplementation {
+    pub async fn last_bake_status(
+        &self,
+        subnets: &[Subnet],
+        subnet_update_proposals: &[SubnetUpdateProposal],
+    ) -> anyhow::Result<BTreeMap<String, f64>> {
+        match self {
+            BakeStatusProviderImplementation::Prometheus(implementation) => implementation.last_bake_status(subnets, subnet_update_proposals).await,
+            BakeStatusProviderImplementation::Registry(implementation) => implementation.last_bake_status(subnets, subnet_update_proposals).await,
+            #[cfg(test)]
+            BakeStatusProviderImplementation::Fake(implementation) => implementation.last_bake_status(subnets, subnet_update_proposals).await,
+        }
+    }
+
+    pub async fn since_start(&self, subnet_update_proposals: &[SubnetUpdateProposal], versions: &[String]) -> anyhow::Result<f64> {
+        match self {
+            BakeStatusProviderImplementation::Prometheus(implementation) => implementation.since_start(subnet_update_proposals, versions).await,
+            BakeStatusProviderImplementation::Registry(implementation) => implementation.since_start(subnet_update_proposals, versions).await,
+            #[cfg(test)]
+            BakeStatusProviderImplementation::Fake(implementation) => implementation.since_start(subnet_update_proposals, versions).await,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use ic_management_backend::proposal::SubnetUpdateProposal;
+use ic_management_types::Subnet;
+use prometheus_http_query::Client;
+
+use super::BakeStatusProvider;
+
+/// Bake status derived from the `ic_replica_info` metric of the replicas
+pub struct PrometheusBakeStatus {
+    client: Client,
+}
+
+impl PrometheusBakeStatus {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl BakeStatusProvider for PrometheusBakeStatus {
+    async fn last_bake_status(
+        &self,
+        _subnets: &[Subnet],
+        _subnet_update_proposals: &[SubnetUpdateProposal],
+    ) -> anyhow::Result<BTreeMap<String, f64>> {
+        let mut last_bake_status: BTreeMap<String, f64> = BTreeMap::new();
+        let result = self
+            .client
+            .query(
+                r#"
+                time() - max(last_over_time(
+                    (timestamp(
+                        sum by(ic_active_version,ic_subnet) (ic_replica_info)
+                    ))[21d:1m]
+                ) unless (sum by (ic_active_version, ic_subnet) (ic_replica_info))) by (ic_subnet)
+                "#,
+            )
+            .get()
+            .await?;
+
+        let last = match result.data().clone().into_vector().into_iter().last() {
+            Some(data) => data,
+            None => return Err(anyhow::anyhow!("There should be data regarding ic_replica_info")),
+        };
+
+        for vector in last.iter() {
+            let subnet = vector.metric().get("ic_subnet").expect("To have ic_subnet key");
+            let last_update = vector.sample().value();
+            last_bake_status.insert(subnet.to_string(), last_update);
+        }
+
+        Ok(last_bake_status)
+    }
+
+    async fn since_start(&self, _subnet_update_proposals: &[SubnetUpdateProposal], versions: &[String]) -> anyhow::Result<f64> {
+        let concatenated_versions = versions.join("|");
+        let result = self
+            .client
+            .query(format!(
+                r#"
+    time() - first_over_time((timestamp(group(ic_replica_info{{ic_active_version=~"{concatenated_versions}"}})))[14d:1d])
+    "#
+            ))
+            .get()
+            .await?;
+
+        match result.data().clone().into_vector().into_iter().last() {
+            Some(data) => match data.iter().last() {
+                Some(data) => Ok(data.sample().value()),
+                None => Err(anyhow::anyhow!("There should be data regarding start of releases in response vector")),
+            },
+            None => Err(anyhow::anyhow!("There should be data regarding start of releases")),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
+use ic_management_backend::proposal::SubnetUpdateProposal;
+use ic_management_types::Subnet;
+
+use super::BakeStatusProvider;
+
+/// Bake status derived from the registry: the versions the subnets run and the
+/// execution time of the proposals that deployed them. Useful for testnets that
+/// are not scraped by Prometheus.
+pub struct RegistryBakeStatus;
+
+impl BakeStatusProvider for RegistryBakeStatus {
+    async fn last_bake_status(&self, subnets: &[Subnet], subnet_update_proposals: &[SubnetUpdateProposal]) -> anyhow::Result<BTreeMap<String, f64>> {
+        Ok(last_bake_status(subnets, subnet_update_proposals, Local::now().timestamp() as u64))
+    }
+
+    async fn since_start(&self, subnet_update_proposals: &[SubnetUpdateProposal], versions: &[String]) -> anyhow::Result<f64> {
+        Ok(since_start(subnet_update_proposals, versions, Local::now().timestamp() as u64))
+    }
+}
+
+/// Subnets whose version wasn't deployed by a proposal, e.g. since the genesis
+/// of a testnet, are considered to have baked since the epoch.
+fn last_bake_status(subnets: &[Subnet], proposals: &[SubnetUpdateProposal], now: u64) -> BTreeMap<String, f64> {
+    subnets
+        .iter()
+        .map(|subnet| {
+            let executed = proposals
+                .iter()
+                .filter(|p| p.info.executed && p.payload.subnet_id == subnet.principal && p.payload.replica_version_id == subnet.replica_version)
+                .map(|p| p.info.executed_timestamp_seconds)
+                .max()
+                .unwrap_or_default();
+            (subnet.principal.to_string(), now.saturating_sub(executed) as f64)
+        })
+        .collect()
+}
+
+/// A release none of whose versions was deployed yet starts now.
+fn since_start(proposals: &[SubnetUpdateProposal], versions: &[String], now: u64) -> f64 {
+    proposals
+        .iter()
+        .filter(|p| p.info.executed && versions.contains(&p.payload.replica_version_id))
+        .map(|p| p.info.executed_timestamp_seconds)
+        .min()
+        .map(|executed| now.saturating_sub(executed) as f64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_base_types::PrincipalId;
+    use ic_management_backend::proposal::ProposalInfoInternal;
+    use registry_canister::mutations::do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload;
+
+    use super::*;
+
+    fn proposal(subnet: u64, version: &str, executed: bool, executed_timestamp_seconds: u64) -> SubnetUpdateProposal {
+        SubnetUpdateProposal {
+            info: ProposalInfoInternal {
+                id: 0,
+                proposal_timestamp_seconds: 0,
+                executed_timestamp_seconds,
+                executed,
+            },
+            payload: DeployGuestosToAllSubnetNodesPayload {
+                subnet_id: PrincipalId::new_subnet_test_id(subnet),
+                replica_version_id: version.to_string(),
+            },
+        }
+    }
+
+    fn subnet(id: u64, version: &str) -> Subnet {
+        Subnet {
+            principal: PrincipalId::new_subnet_test_id(id),
+            replica_version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bake_status_from_executed_proposals() {
+        let subnets = vec![subnet(1, "b"), subnet(2, "a")];
+        let proposals = vec![
+            proposal(1, "a", true, 100),
+            proposal(1, "b", true, 500),
+            proposal(1, "c", false, 0),
+            proposal(2, "b", false, 0),
+        ];
+
+        assert_eq!(
+            last_bake_status(&subnets, &proposals, 1000),
+            BTreeMap::from([
+                (PrincipalId::new_subnet_test_id(1).to_string(), 500.0),
+                (PrincipalId::new_subnet_test_id(2).to_string(), 1000.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn release_starts_with_first_executed_proposal() {
+        let proposals = vec![
+            proposal(1, "a", true, 100),
+            proposal(2, "b", true, 300),
+            proposal(1, "b", true, 500),
+            proposal(3, "c", false, 0),
+        ];
+
+        assert_eq!(since_start(&proposals, &["b".to_string(), "c".to_string()], 1000), 700.0);
+        assert_eq!(since_start(&proposals, &["c".to_string()], 1000), 0.0);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use ic_base_types::PrincipalId;
+use ic_management_types::Subnet;
+use prometheus_http_query::Client;
+use serde::Deserialize;
+use slog::{debug, Logger};
+
+use super::{stage_checks::DesiredReleaseVersion, Stage};
+
+/// A PromQL query that gates the rollout of a stage. It is evaluated for the
+/// subnets of the stage once they run the new version, while they bake and
+/// afterwards. The query has to return one sample per subnet, labeled with
+/// `ic_subnet`, for example:
+///
+/// ```yaml
+/// health_checks:
+///   - name: finalization-rate
+///     query: avg by (ic_subnet) (rate(artifact_pool_consensus_height_stat{type="finalization"}[10m]))
+///     min: 0.3
+///   - name: nodes-down
+///     query: count by (ic_subnet) (up{job="replica"} == 0)
+///     max: 1
+/// ```
+///
+/// Subnets without a sample pass the check.
+#[derive(Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct HealthCheck {
+    pub name: String,
+    pub query: String,
+    /// The check fails if the sample of a subnet is lower than this
+    #[serde(default)]
+    pub min: Option<f64>,
+    /// The check fails if the sample of a subnet is higher than this
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+impl HealthCheck {
+    fn violation(&self, value: f64) -> Option<String> {
+        match (self.min, self.max) {
+            (Some(min), _) if value < min => Some(format!("{} is {} (min {})", self.name, value, min)),
+            (_, Some(max)) if value > max => Some(format!("{} is {} (max {})", self.name, value, max)),
+            _ => None,
+        }
+    }
+}
+
+/// Failed health checks of each subnet
+pub type HealthFailures = BTreeMap<PrincipalId, Vec<String>>;
+
+/// Evaluates the health checks of every stage that has subnets on the desired version.
+pub async fn evaluate_health_checks<'a>(
+    logger: &'a Logger,
+    prometheus_client: &'a Client,
+    stages: &'a [Stage],
+    subnets: &'a [Subnet],
+    desired_versions: &'a DesiredReleaseVersion,
+) -> anyhow::Result<HealthFailures> {
+    let mut failures = HealthFailures::new();
+    for stage in stages.iter().filter(|s| !s.health_checks.is_empty()) {
+        let upgraded = upgraded_subnets(stage, subnets, desired_versions);
+        if upgraded.is_empty() {
+            continue;
+        }
+
+        for check in &stage.health_checks {
+            debug!(logger, "Evaluating health check '{}' for subnets {:?}", check.name, stage.subnets);
+            let result = prometheus_client
+                .query(check.query.as_str())
+                .get()
+                .await
+                .map_err(|e| anyhow::anyhow!("Health check '{}' failed to query prometheus: {:?}", check.name, e))?;
+            let samples = result
+                .data()
+                .as_vector()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|v| v.metric().get("ic_subnet").map(|subnet| (subnet.clone(), v.sample().value())))
+                .collect::<Vec<_>>();
+            for (subnet, violation) in check_samples(check, &upgraded, &samples) {
+                failures.entry(subnet).or_default().push(violation);
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Subnets of the stage that run their desired version
+fn upgraded_subnets(stage: &Stage, subnets: &[Subnet], desired_versions: &DesiredReleaseVersion) -> Vec<PrincipalId> {
+    subnets
+        .iter()
+        .filter(|s| stage.subnets.iter().any(|short| s.principal.to_string().starts_with(short)))
+        .filter(|s| desired_versions.subnets.get(&s.principal).is_some_and(|v| v.version == s.replica_version))
+        .map(|s| s.principal)
+        .collect()
+}
+
+fn check_samples(check: &HealthCheck, subnets: &[PrincipalId], samples: &[(String, f64)]) -> Vec<(PrincipalId, String)> {
+    samples
+        .iter()
+        .filter_map(|(subnet, value)| {
+            let subnet = subnets.iter().find(|s| s.to_string() == *subnet)?;
+            check.violation(*value).map(|violation| (*subnet, violation))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(min: Option<f64>, max: Option<f64>) -> HealthCheck {
+        HealthCheck {
+            name: "check".to_string(),
+            query: "query".to_string(),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn violations() {
+        assert_eq!(check(Some(0.3), None).violation(0.1), Some("check is 0.1 (min 0.3)".to_string()));
+        assert_eq!(check(Some(0.3), None).violation(0.3), None);
+        assert_eq!(check(None, Some(1.0)).violation(2.0), Some("check is 2 (max 1)".to_string()));
+        assert_eq!(check(None, Some(1.0)).violation(1.0), None);
+        assert_eq!(check(None, None).violation(f64::MAX), None);
+    }
+
+    #[test]
+    fn only_upgraded_subnets_fail() {
+        let upgraded = vec![PrincipalId::new_subnet_test_id(1)];
+        let samples = vec![
+            (PrincipalId::new_subnet_test_id(1).to_string(), 0.1),
+            (PrincipalId::new_subnet_test_id(2).to_string(), 0.1),
+        ];
+
+        assert_eq!(
+            check_samples(&check(Some(0.3), None), &upgraded, &samples),
+            vec![(PrincipalId::new_subnet_test_id(1), "check is 0.1 (min 0.3)".to_string())]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{Local, Utc};
+use dre::operations::hostos_rollout::{self, HostosRolloutResponse};
+use ic_base_types::PrincipalId;
+use ic_management_backend::{proposal::ProposalAgent, registry::RegistryState};
+use slog::{debug, info, Logger};
+
+use super::{should_proceed::should_proceed, HostosStage, Index, Progress};
+use crate::actions::SubnetAction;
+
+/// Calculates the next step of the HostOS rollout described in the index. Stages
+/// are rolled out in order, each one with a single proposal once the previous
+/// stage baked, and nodes with open proposals are left alone.
+pub async fn calculate_hostos_progress(logger: &Logger, index: Index, registry_state: RegistryState) -> anyhow::Result<Progress> {
+    let plan = match &index.hostos_rollout {
+        Some(plan) => plan.clone(),
+        None => {
+            info!(logger, "The index doesn't contain a HostOS rollout.");
+            return Ok(Progress::default());
+        }
+    };
+    let blocked = match plan.pause {
+        true => Err("the HostOS rollout is paused".to_string()),
+        false => should_proceed(&index, Utc::now()),
+    };
+    if let Err(reason) = blocked {
+        info!(logger, "HostOS rollout should not proceed because {}", reason);
+        return Ok(Progress {
+            actions: vec![SubnetAction::Blocked { subnet_short: None, reason }],
+            ..Default::default()
+        });
+    }
+
+    let subnets = registry_state.subnets();
+    let proposal_agent = ProposalAgent::new(registry_state.get_nns_urls());
+    let rollout = hostos_rollout::HostosRollout::new(
+        registry_state.nodes(),
+        subnets.clone(),
+        &registry_state.network(),
+        proposal_agent.clone(),
+        &plan.version,
+        &None,
+    );
+    let missing = plan
+        .stages
+        .iter()
+        .map(|stage| rollout.nodes_missing(stage.update_group()))
+        .collect::<Vec<_>>();
+    let stage = missing.iter().position(|m| m.values().any(|m| *m > 0));
+    let progress = Progress {
+        release: Some(plan.version.clone()),
+        stage,
+        subnets_updated: subnets
+            .values()
+            .filter(|s| s.nodes.iter().all(|n| n.hostos_version == plan.version))
+            .count(),
+        subnets_total: subnets.len(),
+        ..Default::default()
+    };
+
+    let open_proposals = proposal_agent.list_open_update_nodes_hostos_versions_proposals().await?;
+    if let Some(proposal) = open_proposals.iter().find(|p| p.hostos_version_id == plan.version) {
+        return Ok(Progress {
+            actions: vec![SubnetAction::PendingHostosProposal {
+                proposal_id: proposal.proposal_id,
+            }],
+            ..progress
+        });
+    }
+
+    let last_update = registry_state
+        .nodes_hostos_upgrade_proposals()
+        .await?
+        .into_iter()
+        .filter(|p| p.info.executed && p.payload.hostos_version_id.as_ref() == Some(&plan.version))
+        .map(|p| p.info.executed_timestamp_seconds)
+        .max();
+    let since_last_update = last_update.map(|executed| Duration::from_secs((Local::now().timestamp() as u64).saturating_sub(executed)));
+
+    for (i, (stage, missing)) in plan.stages.iter().zip(missing).enumerate() {
+        if missing.values().all(|m| *m == 0) {
+            debug!(logger, "HostOS stage {} is completed", i);
+            continue;
+        }
+
+        if let Some(action) = baking(&plan.stages[..i], since_last_update) {
+            return Ok(Progress {
+                stage: Some(i),
+                actions: vec![action],
+                ..progress
+            });
+        }
+
+        info!(logger, "Selecting nodes for HostOS stage {}", i);
+        let update_group = stage.update_group();
+        match rollout.execute(update_group).await? {
+            HostosRolloutResponse::Ok(nodes, _) => {
+                let nodes = take_missing(nodes.iter().map(|n| (n.principal, n.subnet_id)), missing);
+                if !nodes.is_empty() {
+                    return Ok(Progress {
+                        stage: Some(i),
+                        actions: vec![SubnetAction::PlaceHostosProposal {
+                            node_group: update_group.node_group.to_string(),
+                            nodes,
+                            version: plan.version.clone(),
+                        }],
+                        ..progress
+                    });
+                }
+                info!(logger, "No more nodes can be updated in HostOS stage {}", i);
+            }
+            HostosRolloutResponse::None(reasons) => {
+                for (group, reason) in reasons {
+                    info!(logger, "No nodes to update in {} because: {}", group, reason);
+                }
+            }
+        }
+    }
+
+    if let Some(action) = baking(&plan.stages, since_last_update) {
+        return Ok(Progress {
+            stage: plan.stages.len().checked_sub(1),
+            actions: vec![action],
+            ..progress
+        });
+    }
+
+    info!(logger, "The HostOS rollout of version '{}' is completed.", plan.version);
+    Ok(Progress { stage: None, ..progress })
+}
+
+/// The last of `stages` is baking if it was updated less than its bake time ago.
+fn baking(stages: &[HostosStage], since_last_update: Option<Duration>) -> Option<SubnetAction> {
+    let stage = stages.last()?;
+    let remaining = stage.bake_time.checked_sub(since_last_update?)?;
+    if remaining.is_zero() {
+        return None;
+    }
+    Some(SubnetAction::HostosBaking {
+        node_group: stage.update_group().node_group.to_string(),
+        remaining,
+    })
+}
+
+/// Takes only as many of the candidate nodes of each subnet, or of the unassigned
+/// nodes, as are still missing to complete the stage.
+fn take_missing(
+    candidates: impl Iterator<Item = (PrincipalId, Option<PrincipalId>)>,
+    mut missing: BTreeMap<Option<PrincipalId>, usize>,
+) -> Vec<PrincipalId> {
+    candidates
+        .filter(|(_, subnet)| match missing.get_mut(subnet) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        })
+        .map(|(node, _)| node)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use dre::cli::hostos::{NodeAssignment, NodeOwner};
+    use dre::operations::hostos_rollout::NumberOfNodes;
+
+    use super::*;
+
+    fn stage(bake_time: &str) -> HostosStage {
+        HostosStage {
+            assignment: NodeAssignment::Unassigned,
+            owner: NodeOwner::Dfinity,
+            nodes: NumberOfNodes::Percentage(10),
+            bake_time: humantime::parse_duration(bake_time).expect("Should be able to parse."),
+        }
+    }
+
+    #[test]
+    fn last_stage_bakes() {
+        let stages = vec![stage("1h"), stage("4h")];
+
+        assert_eq!(baking(&stages[..0], Some(Duration::from_secs(60))), None);
+        assert_eq!(baking(&stages[..1], None), None, "nothing was updated yet");
+        assert_eq!(
+            baking(&stages[..1], Some(Duration::from_secs(60))),
+            Some(SubnetAction::HostosBaking {
+                node_group: stage("1h").update_group().node_group.to_string(),
+                remaining: Duration::from_secs(3540),
+            })
+        );
+        assert_eq!(baking(&stages[..1], Some(Duration::from_secs(3600))), None);
+        assert_eq!(baking(&stages[..1], Some(Duration::from_secs(7200))), None);
+    }
+
+    #[test]
+    fn take_only_missing_nodes() {
+        let subnet = Some(PrincipalId::new_subnet_test_id(1));
+        let candidates = vec![
+            (PrincipalId::new_node_test_id(1), subnet),
+            (PrincipalId::new_node_test_id(2), subnet),
+            (PrincipalId::new_node_test_id(3), subnet),
+            (PrincipalId::new_node_test_id(4), None),
+            (PrincipalId::new_node_test_id(5), None),
+            (PrincipalId::new_node_test_id(6), Some(PrincipalId::new_subnet_test_id(2))),
+        ];
+
+        assert_eq!(
+            take_missing(candidates.into_iter(), BTreeMap::from([(subnet, 2), (None, 1)])),
+            vec![
+                PrincipalId::new_node_test_id(1),
+                PrincipalId::new_node_test_id(2),
+                PrincipalId::new_node_test_id(4)
+            ]
+        );
+    }
+}
@@ -1,24 +1,41 @@
-use std::{collections::BTreeMap, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
 
 use crate::calculation::should_proceed::should_proceed;
-use chrono::{Local, NaiveDate, TimeDelta};
+use chrono::{NaiveDate, NaiveTime, TimeDelta, Utc, Weekday};
+use chrono_tz::Tz;
+use dre::cli::hostos::{NodeAssignment, NodeOwner};
+use dre::operations::hostos_rollout::{NodeGroupUpdate, NumberOfNodes};
+use ic_management_backend::proposal::{SubnetUpdateProposal, UpdateUnassignedNodesProposal};
 use ic_management_backend::registry::RegistryState;
 use ic_management_types::Subnet;
-use itertools::Itertools;
 use prometheus_http_query::Client;
 use serde::Deserialize;
 use slog::{info, Logger};
 
+use self::health_checks::{evaluate_health_checks, HealthCheck};
 use self::stage_checks::{check_stages, desired_rollout_release_version};
 use crate::actions::SubnetAction;
+use crate::bake_status::BakeStatusProviderImplementation;
 
+pub use self::hostos::calculate_hostos_progress;
+pub use self::simulation::{simulate, ScheduledUpdate};
+pub use self::stage_checks::desired_rollout_release_version;
+
+mod health_checks;
+mod hostos;
 mod should_proceed;
+mod simulation;
 mod stage_checks;
 
 #[derive(Deserialize, Clone, Default)]
 pub struct Index {
     pub rollout: Rollout,
     pub releases: Vec<Release>,
+    #[serde(default)]
+    pub hostos_rollout: Option<HostosRollout>,
 }
 
 #[derive(Deserialize, Clone, Default)]
@@ -27,6 +44,42 @@ pub struct Rollout {
     pub pause: bool,
     pub skip_days: Vec<NaiveDate>,
     pub stages: Vec<Stage>,
+    /// Propose to roll subnets that fail their health checks back to the previous release,
+    /// instead of only halting the rollout
+    #[serde(default)]
+    pub rollback_on_health_failure: bool,
+    /// Windows in which the rollout may proceed, at any time if there are none
+    #[serde(default)]
+    pub windows: Vec<RolloutWindow>,
+    /// Time zone of the windows, skipped days and holidays, UTC by default
+    #[serde(default)]
+    pub time_zone: Option<Tz>,
+    /// Paths or urls of iCal files with holidays on which the rollout doesn't proceed
+    #[serde(default)]
+    pub holiday_calendars: Vec<String>,
+    /// Days of the events in `holiday_calendars`, loaded when the index is fetched
+    #[serde(skip)]
+    pub holidays: BTreeSet<NaiveDate>,
+    /// Maximum number of subnets for which update proposals are placed on a single day
+    #[serde(default)]
+    pub max_subnets_per_day: Option<usize>,
+}
+
+/// Time of the day, on some weekdays, in which the rollout may proceed, e.g.
+///
+/// ```yaml
+/// windows:
+///   - weekdays: [Mon, Tue, Wed, Thu]
+///     start: "09:00"
+///     end: "17:00"
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RolloutWindow {
+    /// Any weekday if empty
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
 }
 
 #[derive(Deserialize, Default, Clone)]
@@ -38,6 +91,108 @@ pub struct Stage {
     pub bake_time: Duration,
     pub wait_for_next_week: bool,
     update_unassigned_nodes: bool,
+    pub health_checks: Vec<HealthCheck>,
+    /// The stage doesn't start before this day
+    pub not_before: Option<NaiveDate>,
+    /// Periods in which the stage doesn't proceed
+    pub blackouts: Vec<Blackout>,
+    /// Subnets of the stage that are updated and baked before the rest of it
+    pub canary: Option<Canary>,
+}
+
+/// Days, from `start` to `end` inclusive, on which a stage doesn't proceed, e.g.
+///
+/// ```yaml
+/// - subnets: [tdb26]
+///   bake_time: 8h
+///   blackouts:
+///     - start: 2024-12-20
+///       end: 2025-01-06
+///       reason: end of year freeze
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Blackout {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Blackout {
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.start <= day && day <= self.end
+    }
+}
+
+/// The first `subnets` subnets of a stage are updated first and, once they baked
+/// for `bake_time` and pass the health checks of the stage, the rest of the stage
+/// is updated. For example:
+///
+/// ```yaml
+/// - subnets: [io67a, shefu, uzr34, pjljw]
+///   bake_time: 8h
+///   canary:
+///     subnets: 1
+///     bake_time: 2h
+/// ```
+#[derive(Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct Canary {
+    pub subnets: usize,
+    #[serde(default, with = "humantime_serde")]
+    pub bake_time: Duration,
+}
+
+impl Rollout {
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone.unwrap_or(Tz::UTC)
+    }
+}
+
+impl Stage {
+    pub fn updates_unassigned_nodes(&self) -> bool {
+        self.update_unassigned_nodes
+    }
+}
+
+/// Rollout of an elected HostOS version, used when the controller runs in HostOS mode
+#[derive(Deserialize, Clone, Default)]
+pub struct HostosRollout {
+    #[serde(default)]
+    pub pause: bool,
+    pub version: String,
+    pub stages: Vec<HostosStage>,
+}
+
+/// A group of nodes that is updated with a single proposal
+#[derive(Deserialize, Clone, Debug)]
+pub struct HostosStage {
+    #[serde(default)]
+    pub assignment: NodeAssignment,
+    #[serde(default)]
+    pub owner: NodeOwner,
+    /// How many nodes of the group run the version once the stage is completed, as a number
+    /// or a percentage of the group. Nodes assigned to subnets are counted per subnet.
+    pub nodes: NumberOfNodes,
+    #[serde(default, with = "humantime_serde")]
+    pub bake_time: Duration,
+}
+
+impl HostosStage {
+    pub fn update_group(&self) -> NodeGroupUpdate {
+        NodeGroupUpdate::new(Some(self.assignment), Some(self.owner), self.nodes)
+    }
+}
+
+/// What the controller should do next
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Progress {
+    /// The release, or HostOS version, that is rolled out
+    pub release: Option<String>,
+    /// The stage the rollout is at, `None` if there is nothing to do
+    pub stage: Option<usize>,
+    pub actions: Vec<SubnetAction>,
+    pub subnets_updated: usize,
+    pub subnets_total: usize,
 }
 
 #[derive(Deserialize, Clone, Default, Eq, PartialEq, Hash, Debug)]
@@ -59,65 +214,67 @@ pub struct Version {
 pub async fn calculate_progress<'a>(
     logger: &'a Logger,
     index: Index,
+    bake_status: &'a BakeStatusProviderImplementation,
     prometheus_client: &'a Client,
     registry_state: RegistryState,
-) -> anyhow::Result<Vec<SubnetAction>> {
-    if !should_proceed(&index, Local::now().to_utc().date_naive()) {
-        info!(logger, "Rollout controller paused or should skip this day.");
-        return Ok(vec![]);
+) -> anyhow::Result<Progress> {
+    if let Err(reason) = should_proceed(&index, Utc::now()) {
+        info!(logger, "Rollout controller should not proceed because {}", reason);
+        return Ok(Progress {
+            actions: vec![SubnetAction::Blocked { subnet_short: None, reason }],
+            ..Default::default()
+        });
     }
 
-    let mut last_bake_status: BTreeMap<String, f64> = BTreeMap::new();
-    let result = prometheus_client
-        .query(
-            r#"
-                time() - max(last_over_time(
-                    (timestamp(
-                        sum by(ic_active_version,ic_subnet) (ic_replica_info)
-                    ))[21d:1m]
-                ) unless (sum by (ic_active_version, ic_subnet) (ic_replica_info))) by (ic_subnet)
-                "#,
-        )
-        .get()
-        .await?;
-
-    let last = match result.data().clone().into_vector().into_iter().last() {
-        Some(data) => data,
-        None => return Err(anyhow::anyhow!("There should be data regarding ic_replica_info")),
+    let state = RolloutState {
+        subnets: registry_state.subnets().into_values().collect(),
+        subnet_update_proposals: registry_state.open_subnet_upgrade_proposals().await?,
+        unassigned_nodes_version: registry_state.get_unassigned_nodes_replica_version().await?,
+        unassigned_nodes_proposals: registry_state.open_upgrade_unassigned_nodes_proposals().await?,
     };
 
-    for vector in last.iter() {
-        let subnet = vector.metric().get("ic_subnet").expect("To have ic_subnet key");
-        let last_update = vector.sample().value();
-        last_bake_status.insert(subnet.to_string(), last_update);
-    }
+    calculate_progress_of_state(logger, index, bake_status, prometheus_client, state).await
+}
+
+/// The parts of the registry that the GuestOS rollout depends on
+struct RolloutState {
+    subnets: Vec<Subnet>,
+    subnet_update_proposals: Vec<SubnetUpdateProposal>,
+    unassigned_nodes_version: String,
+    unassigned_nodes_proposals: Vec<UpdateUnassignedNodesProposal>,
+}
+
+async fn calculate_progress_of_state<'a>(
+    logger: &'a Logger,
+    index: Index,
+    bake_status: &'a BakeStatusProviderImplementation,
+    prometheus_client: &'a Client,
+    state: RolloutState,
+) -> anyhow::Result<Progress> {
+    let RolloutState {
+        subnets,
+        subnet_update_proposals,
+        unassigned_nodes_version,
+        unassigned_nodes_proposals,
+    } = state;
+
+    let last_bake_status = bake_status.last_bake_status(&subnets, &subnet_update_proposals).await?;
 
-    let subnets = registry_state.subnets().into_values().collect::<Vec<Subnet>>();
     let desired_versions = desired_rollout_release_version(&subnets, &index.releases);
-    let concatenated_versions = desired_versions.release.versions.iter().map(|v| v.version.clone()).join("|");
-
-    let result = prometheus_client
-        .query(format!(
-            r#"
-    time() - first_over_time((timestamp(group(ic_replica_info{{ic_active_version=~"{concatenated_versions}"}})))[14d:1d])
-    "#
-        ))
-        .get()
-        .await?;
-
-    let since_start = match result.data().clone().into_vector().into_iter().last() {
-        Some(data) => match data.iter().last() {
-            Some(data) => data.sample().value(),
-            None => return Err(anyhow::anyhow!("There should be data regarding start of releases in response vector")),
-        },
-        None => return Err(anyhow::anyhow!("There should be data regarding start of releases")),
-    };
+    let versions = desired_versions.release.versions.iter().map(|v| v.version.clone()).collect::<Vec<_>>();
+    let since_start = bake_status.since_start(&subnet_update_proposals, &versions).await?;
+
+    let health_failures = evaluate_health_checks(logger, prometheus_client, &index.rollout.stages, &subnets, &desired_versions).await?;
 
-    let subnet_update_proposals = registry_state.open_subnet_upgrade_proposals().await?;
-    let unassigned_nodes_version = registry_state.get_unassigned_nodes_replica_version().await?;
-    let unassigned_nodes_proposals = registry_state.open_upgrade_unassigned_nodes_proposals().await?;
+    let release = desired_versions.release.rc_name.clone();
+    // Days are counted in the time zone of the rollout, like its windows
+    let now = Utc::now().with_timezone(&index.rollout.time_zone());
+    let subnets_updated = subnets
+        .iter()
+        .filter(|s| desired_versions.subnets.get(&s.principal).is_some_and(|v| v.version == s.replica_version))
+        .count();
 
-    let actions = check_stages(
+    let progress = check_stages(
         &last_bake_status,
         &subnet_update_proposals,
         &unassigned_nodes_proposals,
@@ -125,13 +282,101 @@ pub async fn calculate_progress<'a>(
         Some(logger),
         &unassigned_nodes_version,
         &subnets,
-        Local::now().date_naive(),
-        Local::now()
-            .checked_sub_signed(TimeDelta::try_seconds(since_start as i64).expect("Should be able to convert to seconds"))
+        now.date_naive(),
+        now.checked_sub_signed(TimeDelta::try_seconds(since_start as i64).expect("Should be able to convert to seconds"))
             .expect("Should be able to sub from now")
             .date_naive(),
         desired_versions,
+        &health_failures,
     )?;
 
-    Ok(actions)
+    Ok(Progress {
+        release: Some(release),
+        subnets_updated,
+        subnets_total: subnets.len(),
+        ..progress
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_base_types::PrincipalId;
+    use slog::{o, Discard};
+
+    use super::*;
+    use crate::bake_status::fake::FakeBakeStatus;
+
+    fn subnet(id: u64, version: &str) -> Subnet {
+        Subnet {
+            principal: PrincipalId::new_subnet_test_id(id),
+            replica_version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn release(rc_name: &str, version: &str) -> Release {
+        Release {
+            rc_name: rc_name.to_string(),
+            versions: vec![Version {
+                version: version.to_string(),
+                name: rc_name.to_string(),
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn rollout_without_prometheus() {
+        let first = PrincipalId::new_subnet_test_id(1);
+        let second = PrincipalId::new_subnet_test_id(2);
+        let index = Index {
+            rollout: Rollout {
+                stages: vec![
+                    Stage {
+                        subnets: vec![first.to_string()],
+                        bake_time: Duration::from_secs(3600),
+                        ..Default::default()
+                    },
+                    Stage {
+                        subnets: vec![second.to_string()],
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            releases: vec![release("rc--2", "new"), release("rc--1", "old")],
+            ..Default::default()
+        };
+        let state = RolloutState {
+            subnets: vec![subnet(1, "new"), subnet(2, "old")],
+            subnet_update_proposals: vec![],
+            unassigned_nodes_version: "old".to_string(),
+            unassigned_nodes_proposals: vec![],
+        };
+        let bake_status = BakeStatusProviderImplementation::Fake(FakeBakeStatus {
+            last_bake_status: BTreeMap::from([(first.to_string(), 7200.0), (second.to_string(), 7200.0)]),
+            since_start: 7200.0,
+        });
+        // Never queried because no stage has health checks
+        let prometheus_client = Client::try_from("http://localhost:9090").expect("Should be able to create client");
+
+        let progress = calculate_progress_of_state(&Logger::root(Discard, o!()), index, &bake_status, &prometheus_client, state)
+            .await
+            .expect("Should calculate progress");
+
+        assert_eq!(
+            progress,
+            Progress {
+                release: Some("rc--2".to_string()),
+                stage: Some(1),
+                actions: vec![SubnetAction::PlaceProposal {
+                    is_unassigned: false,
+                    subnet_principal: second,
+                    version: "new".to_string(),
+                }],
+                subnets_updated: 1,
+                subnets_total: 2,
+            }
+        );
+    }
 }
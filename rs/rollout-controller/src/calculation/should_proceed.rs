@@ -1,36 +1,60 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
 
-use super::Index;
+use super::{Index, RolloutWindow};
+
+/// Checks whether the rollout may proceed at `now`, or returns the reason why it can't.
+pub fn should_proceed(index: &Index, now: DateTime<Utc>) -> Result<(), String> {
+    let rollout = &index.rollout;
 
-pub fn should_proceed(index: &Index, today: NaiveDate) -> bool {
     // Check if the plan is paused
-    if index.rollout.pause {
-        return false;
+    if rollout.pause {
+        return Err("the rollout is paused".to_string());
     }
 
+    let now = now.with_timezone(&rollout.time_zone());
+    let today = now.date_naive();
+
     // Check if this day should be skipped
-    if index.rollout.skip_days.iter().any(|f| f.eq(&today)) {
-        return false;
+    if rollout.skip_days.iter().any(|f| f.eq(&today)) {
+        return Err(format!("{} is skipped", today));
+    }
+
+    if rollout.holidays.contains(&today) {
+        return Err(format!("{} is a holiday", today));
     }
 
-    true
+    // Check if it's within one of the windows
+    if !rollout.windows.is_empty() && !rollout.windows.iter().any(|w| in_window(w, now.weekday(), now.time())) {
+        return Err(format!("{} is outside of the rollout windows", now.format("%a %H:%M %Z")));
+    }
+
+    Ok(())
+}
+
+fn in_window(window: &RolloutWindow, weekday: Weekday, time: NaiveTime) -> bool {
+    (window.weekdays.is_empty() || window.weekdays.contains(&weekday)) && window.start <= time && time < window.end
 }
 
 #[cfg(test)]
 mod should_proceed_tests {
-    use std::str::FromStr;
+    use std::{collections::BTreeSet, str::FromStr};
 
-    use chrono::Local;
+    use chrono::NaiveDate;
+    use chrono_tz::Tz;
 
     use crate::calculation::Rollout;
 
     use super::*;
 
+    fn at(datetime: &str) -> DateTime<Utc> {
+        DateTime::from_str(datetime).unwrap()
+    }
+
     #[test]
     fn should_proceed_not_blocked_and_not_skipped() {
         let index = Index::default();
 
-        assert!(should_proceed(&index, Local::now().to_utc().date_naive()))
+        assert!(should_proceed(&index, Utc::now()).is_ok())
     }
 
     #[test]
@@ -44,6 +68,52 @@ mod should_proceed_tests {
             ..Default::default()
         };
 
-        assert!(!should_proceed(&index, day))
+        assert_eq!(
+            should_proceed(&index, at("2024-03-11T12:00:00Z")),
+            Err("2024-03-11 is skipped".to_string())
+        )
+    }
+
+    #[test]
+    fn should_not_proceed_on_holiday() {
+        let index = Index {
+            rollout: Rollout {
+                holidays: BTreeSet::from([NaiveDate::from_str("2024-12-25").unwrap()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            should_proceed(&index, at("2024-12-25T12:00:00Z")),
+            Err("2024-12-25 is a holiday".to_string())
+        );
+        assert!(should_proceed(&index, at("2024-12-27T12:00:00Z")).is_ok())
+    }
+
+    #[test]
+    fn should_proceed_only_in_windows() {
+        let index = Index {
+            rollout: Rollout {
+                windows: vec![RolloutWindow {
+                    weekdays: vec![Weekday::Mon, Weekday::Tue],
+                    start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                }],
+                time_zone: Some(Tz::Europe__Zurich),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Monday, 09:30 in Zurich
+        assert!(should_proceed(&index, at("2024-03-11T08:30:00Z")).is_ok());
+        // Monday, 08:30 in Zurich
+        assert_eq!(
+            should_proceed(&index, at("2024-03-11T07:30:00Z")),
+            Err("Mon 08:30 CET is outside of the rollout windows".to_string())
+        );
+        // Wednesday, 10:00 in Zurich
+        assert!(should_proceed(&index, at("2024-03-13T09:00:00Z")).is_err());
     }
 }
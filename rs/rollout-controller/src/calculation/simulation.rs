@@ -0,0 +1,245 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use ic_management_backend::proposal::{ProposalInfoInternal, SubnetUpdateProposal};
+use ic_management_types::Subnet;
+use registry_canister::mutations::do_deploy_guestos_to_all_subnet_nodes::DeployGuestosToAllSubnetNodesPayload;
+
+use super::{
+    health_checks::HealthFailures,
+    should_proceed::should_proceed,
+    stage_checks::{check_stages, desired_rollout_release_version},
+    Index, Rollout,
+};
+use crate::actions::SubnetAction;
+
+/// How far the simulation looks ahead before giving up
+const MAX_SIMULATED_DAYS: u64 = 365;
+/// Seconds by which the simulation advances while the rollout is blocked
+const QUARTER: u64 = 15 * 60;
+
+/// A proposal that the rollout is expected to place
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledUpdate {
+    pub at: NaiveDateTime,
+    pub stage: usize,
+    /// Principal of the subnet, `None` for the unassigned nodes
+    pub subnet: Option<String>,
+    pub version: String,
+}
+
+/// Simulates the rollout of the index starting at the beginning of `from`, by
+/// running `check_stages` until the rollout completes. Proposals are assumed to
+/// be executed as soon as they are placed and the time is advanced by the
+/// remaining bake time, or to the next quarter of an hour when the rollout is
+/// blocked or waits for next week. Subnets that already run the desired version
+/// are considered baked.
+pub fn simulate(index: &Index, mut subnets: Vec<Subnet>, mut unassigned_version: String, from: NaiveDate) -> anyhow::Result<Vec<ScheduledUpdate>> {
+    let index = Index {
+        rollout: Rollout {
+            pause: false,
+            ..index.rollout.clone()
+        },
+        ..index.clone()
+    };
+    let desired_versions = desired_rollout_release_version(&subnets, &index.releases);
+    let until = from.checked_add_days(Days::new(MAX_SIMULATED_DAYS)).expect("Should be able to add days");
+
+    let mut clock = from.and_time(NaiveTime::MIN);
+    let mut last_bake_status: BTreeMap<String, f64> = subnets.iter().map(|s| (s.principal.to_string(), f64::MAX)).collect();
+    let mut start_of_release = subnets
+        .iter()
+        .any(|s| desired_versions.subnets.get(&s.principal).is_some_and(|v| v.version == s.replica_version))
+        .then_some(from);
+    let mut schedule = vec![];
+    let mut placed_proposals = vec![];
+
+    let advance = |clock: &mut NaiveDateTime, by: Duration, last_bake_status: &mut BTreeMap<String, f64>| {
+        *clock += TimeDelta::from_std(by).expect("Should be able to convert duration");
+        last_bake_status.values_mut().for_each(|bake| *bake += by.as_secs_f64());
+    };
+    let next_quarter = |clock: &NaiveDateTime| Duration::from_secs(QUARTER - clock.time().num_seconds_from_midnight() as u64 % QUARTER);
+
+    loop {
+        if clock.date() > until {
+            return Err(anyhow::anyhow!("The rollout didn't complete within {} days", MAX_SIMULATED_DAYS));
+        }
+        if should_proceed(&index, clock.and_utc()).is_err() {
+            advance(&mut clock, next_quarter(&clock), &mut last_bake_status);
+            continue;
+        }
+
+        let progress = check_stages(
+            &last_bake_status,
+            &placed_proposals,
+            &[],
+            index.clone(),
+            None,
+            &unassigned_version,
+            &subnets,
+            clock.date(),
+            start_of_release.unwrap_or(clock.date()),
+            desired_versions.clone(),
+            &HealthFailures::new(),
+        )?;
+        if progress.actions.is_empty() {
+            return Ok(schedule);
+        }
+
+        let mut placed = false;
+        let mut remaining_bake = None;
+        for action in progress.actions {
+            match action {
+                SubnetAction::PlaceProposal {
+                    is_unassigned: true,
+                    version,
+                    ..
+                } => {
+                    unassigned_version = version.clone();
+                    schedule.push(ScheduledUpdate {
+                        at: clock,
+                        stage: progress.stage.unwrap_or_default(),
+                        subnet: None,
+                        version,
+                    });
+                    placed = true;
+                }
+                SubnetAction::PlaceProposal {
+                    subnet_principal, version, ..
+                } => {
+                    if let Some(subnet) = subnets.iter_mut().find(|s| s.principal == subnet_principal) {
+                        subnet.replica_version = version.clone();
+                    }
+                    last_bake_status.insert(subnet_principal.to_string(), 0.0);
+                    placed_proposals.push(SubnetUpdateProposal {
+                        info: ProposalInfoInternal {
+                            id: placed_proposals.len() as u64,
+                            proposal_timestamp_seconds: clock.and_utc().timestamp() as u64,
+                            executed_timestamp_seconds: clock.and_utc().timestamp() as u64,
+                            executed: true,
+                        },
+                        payload: DeployGuestosToAllSubnetNodesPayload {
+                            subnet_id: subnet_principal,
+                            replica_version_id: version.clone(),
+                        },
+                    });
+                    schedule.push(ScheduledUpdate {
+                        at: clock,
+                        stage: progress.stage.unwrap_or_default(),
+                        subnet: Some(subnet_principal.to_string()),
+                        version,
+                    });
+                    placed = true;
+                }
+                SubnetAction::Baking { remaining, .. } | SubnetAction::CanaryBaking { remaining, .. } => {
+                    remaining_bake = remaining_bake.max(Some(remaining))
+                }
+                _ => {}
+            }
+        }
+
+        if placed {
+            start_of_release.get_or_insert(clock.date());
+            continue;
+        }
+        match remaining_bake {
+            Some(remaining) => advance(&mut clock, remaining, &mut last_bake_status),
+            None => advance(&mut clock, next_quarter(&clock), &mut last_bake_status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_base_types::PrincipalId;
+
+    use super::*;
+    use crate::calculation::{Release, Stage, Version};
+
+    fn subnet(id: u64, version: &str) -> Subnet {
+        Subnet {
+            principal: PrincipalId::new_subnet_test_id(id),
+            replica_version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn release(rc_name: &str, version: &str) -> Release {
+        Release {
+            rc_name: rc_name.to_string(),
+            versions: vec![Version {
+                version: version.to_string(),
+                name: rc_name.to_string(),
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn update(at: &str, stage: usize, subnet: u64) -> ScheduledUpdate {
+        ScheduledUpdate {
+            at: NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M").expect("Should parse"),
+            stage,
+            subnet: Some(PrincipalId::new_subnet_test_id(subnet).to_string()),
+            version: "new".to_string(),
+        }
+    }
+
+    #[test]
+    fn simulate_rollout() {
+        let stage = |subnet: u64, bake_time: u64, wait_for_next_week: bool| Stage {
+            subnets: vec![PrincipalId::new_subnet_test_id(subnet).to_string()],
+            bake_time: Duration::from_secs(bake_time * 3600),
+            wait_for_next_week,
+            ..Default::default()
+        };
+        let index = Index {
+            rollout: Rollout {
+                // Wednesday
+                skip_days: vec![NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()],
+                stages: vec![stage(1, 30, false), stage(2, 4, false), stage(3, 0, true)],
+                ..Default::default()
+            },
+            releases: vec![release("rc--2", "new"), release("rc--1", "old")],
+            ..Default::default()
+        };
+        let subnets = vec![subnet(1, "old"), subnet(2, "old"), subnet(3, "old")];
+
+        // Starts on a Tuesday
+        let schedule = simulate(&index, subnets, "old".to_string(), NaiveDate::from_ymd_opt(2024, 4, 30).unwrap()).unwrap();
+
+        assert_eq!(
+            schedule,
+            vec![
+                update("2024-04-30 00:00", 0, 1),
+                // Baked on the skipped Wednesday, so the next stage starts on Thursday
+                update("2024-05-02 00:00", 1, 2),
+                update("2024-05-06 00:00", 2, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn simulate_completed_rollout() {
+        let index = Index {
+            rollout: Rollout {
+                stages: vec![Stage {
+                    subnets: vec![PrincipalId::new_subnet_test_id(1).to_string()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            releases: vec![release("rc--2", "new"), release("rc--1", "old")],
+            ..Default::default()
+        };
+
+        let schedule = simulate(
+            &index,
+            vec![subnet(1, "new")],
+            "old".to_string(),
+            NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(schedule, vec![]);
+    }
+}
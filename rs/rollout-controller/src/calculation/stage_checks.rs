@@ -1,15 +1,15 @@
 use std::{collections::BTreeMap, time::Duration};
 
 use crate::actions::SubnetAction;
-use chrono::{Datelike, Days, NaiveDate, Weekday};
+use chrono::{DateTime, Datelike, Days, NaiveDate, Weekday};
 use humantime::format_duration;
 use ic_base_types::PrincipalId;
 use ic_management_backend::proposal::{SubnetUpdateProposal, UpdateUnassignedNodesProposal};
 use ic_management_types::Subnet;
 use itertools::Itertools;
-use slog::{debug, info, Logger};
+use slog::{debug, info, warn, Logger};
 
-use super::{Index, Stage};
+use super::{health_checks::HealthFailures, Index, Progress, Stage};
 
 /// For the set of inputs, generate a vector of `SubnetAction`'s for an arbitrary stage.
 /// All produced actions are always related to the same stage of an index rollout.
@@ -25,7 +25,22 @@ pub fn check_stages<'a>(
     now: NaiveDate,
     start_of_release: NaiveDate,
     desired_versions: DesiredReleaseVersion,
-) -> anyhow::Result<Vec<SubnetAction>> {
+    health_failures: &'a HealthFailures,
+) -> anyhow::Result<Progress> {
+    // Subnets that may still be updated today
+    let mut budget = index.rollout.max_subnets_per_day.map(|max| {
+        let updated_today = subnet_update_proposals
+            .iter()
+            .filter(|p| {
+                DateTime::from_timestamp(p.info.proposal_timestamp_seconds as i64, 0)
+                    .is_some_and(|t| t.with_timezone(&index.rollout.time_zone()).date_naive() == now)
+            })
+            .map(|p| p.payload.subnet_id)
+            .unique()
+            .count();
+        max.saturating_sub(updated_today)
+    });
+
     for (i, stage) in index.rollout.stages.iter().enumerate() {
         if let Some(logger) = logger {
             info!(logger, "Checking stage {}", i)
@@ -39,7 +54,11 @@ pub fn check_stages<'a>(
                     subnet_short: subnet.to_string(),
                 })
                 .collect();
-            return Ok(actions);
+            return Ok(Progress {
+                stage: Some(i),
+                actions,
+                ..Default::default()
+            });
         }
 
         let stage_actions = check_stage(
@@ -51,27 +70,73 @@ pub fn check_stages<'a>(
             unassigned_version,
             subnets,
             desired_versions.clone(),
+            health_failures,
+            index.rollout.rollback_on_health_failure,
+            &mut budget,
         )?;
 
-        if !stage_actions.iter().all(|a| {
+        if stage_actions.iter().all(|a| {
             if let SubnetAction::Noop { subnet_short: _ } = a {
                 return true;
             }
             false
         }) {
-            return Ok(stage_actions);
+            if let Some(logger) = logger {
+                info!(logger, "Stage {} is completed", i)
+            }
+            continue;
         }
 
-        if let Some(logger) = logger {
-            info!(logger, "Stage {} is completed", i)
+        // Only the stage with pending subnets is held back, so later stages
+        // can go ahead once a blacked out stage is done
+        if let Some(not_before) = stage.not_before.filter(|not_before| now < *not_before) {
+            let actions = stage
+                .subnets
+                .iter()
+                .map(|subnet| SubnetAction::Blocked {
+                    subnet_short: Some(subnet.to_string()),
+                    reason: format!("stage {} doesn't start before {}", i, not_before),
+                })
+                .collect();
+            return Ok(Progress {
+                stage: Some(i),
+                actions,
+                ..Default::default()
+            });
+        }
+
+        if let Some(blackout) = stage.blackouts.iter().find(|blackout| blackout.contains(now)) {
+            let reason = match &blackout.reason {
+                Some(reason) => format!("stage {} is blacked out from {} to {}: {}", i, blackout.start, blackout.end, reason),
+                None => format!("stage {} is blacked out from {} to {}", i, blackout.start, blackout.end),
+            };
+            let actions = stage
+                .subnets
+                .iter()
+                .map(|subnet| SubnetAction::Blocked {
+                    subnet_short: Some(subnet.to_string()),
+                    reason: reason.clone(),
+                })
+                .collect();
+            return Ok(Progress {
+                stage: Some(i),
+                actions,
+                ..Default::default()
+            });
         }
+
+        return Ok(Progress {
+            stage: Some(i),
+            actions: stage_actions,
+            ..Default::default()
+        });
     }
 
     if let Some(logger) = logger {
         info!(logger, "The current rollout '{}' is completed.", desired_versions.release.rc_name);
     }
 
-    Ok(vec![])
+    Ok(Progress::default())
 }
 
 fn week_passed(release_start: NaiveDate, now: NaiveDate) -> bool {
@@ -95,6 +160,9 @@ fn check_stage<'a>(
     unassigned_version: &'a String,
     subnets: &'a [Subnet],
     desired_versions: DesiredReleaseVersion,
+    health_failures: &'a HealthFailures,
+    rollback_on_health_failure: bool,
+    budget: &mut Option<usize>,
 ) -> anyhow::Result<Vec<SubnetAction>> {
     let mut stage_actions = vec![];
     if stage.update_unassigned_nodes {
@@ -133,7 +201,87 @@ fn check_stage<'a>(
         return Ok(stage_actions);
     }
 
-    for subnet_short in &stage.subnets {
+    // Canaries are updated and have to bake before the rest of the stage is updated
+    let canaries = match &stage.canary {
+        Some(canary) if canary.subnets < stage.subnets.len() => {
+            let canaries = &stage.subnets[..canary.subnets];
+            let (canary_actions, halt_actions) = check_subnets(
+                last_bake_status,
+                subnet_update_proposals,
+                canaries,
+                canary.bake_time,
+                logger,
+                subnets,
+                &desired_versions,
+                health_failures,
+                rollback_on_health_failure,
+                budget,
+            )?;
+            if !halt_actions.is_empty() {
+                return Ok(halt_actions);
+            }
+            if !canary_actions.iter().all(|a| matches!(a, SubnetAction::Noop { .. })) {
+                return Ok(canary_actions
+                    .into_iter()
+                    .map(|a| match a {
+                        SubnetAction::Baking { subnet_short, remaining } => SubnetAction::CanaryBaking { subnet_short, remaining },
+                        a => a,
+                    })
+                    .collect());
+            }
+            canaries
+        }
+        _ => &[],
+    };
+
+    let (stage_actions, halt_actions) = check_subnets(
+        last_bake_status,
+        subnet_update_proposals,
+        &stage.subnets,
+        stage.bake_time,
+        logger,
+        subnets,
+        &desired_versions,
+        health_failures,
+        rollback_on_health_failure,
+        budget,
+    )?;
+
+    // Unhealthy subnets halt the whole rollout
+    if !halt_actions.is_empty() {
+        return Ok(halt_actions);
+    }
+
+    if !canaries.is_empty() && stage_actions.iter().any(|a| matches!(a, SubnetAction::PlaceProposal { .. })) {
+        if let Some(logger) = logger {
+            info!(logger, "Canaries {:?} baked, promoting the release to the rest of the stage", canaries)
+        }
+        return Ok([SubnetAction::PromoteCanary { canaries: canaries.to_vec() }]
+            .into_iter()
+            .chain(stage_actions)
+            .collect());
+    }
+
+    Ok(stage_actions)
+}
+
+/// Checks the subnets of a stage, returning the actions for the subnets and,
+/// separately, the actions for the ones that failed their health checks.
+fn check_subnets<'a>(
+    last_bake_status: &'a BTreeMap<String, f64>,
+    subnet_update_proposals: &'a [SubnetUpdateProposal],
+    subnet_shorts: &'a [String],
+    bake_time: Duration,
+    logger: Option<&'a Logger>,
+    subnets: &'a [Subnet],
+    desired_versions: &'a DesiredReleaseVersion,
+    health_failures: &'a HealthFailures,
+    rollback_on_health_failure: bool,
+    budget: &mut Option<usize>,
+) -> anyhow::Result<(Vec<SubnetAction>, Vec<SubnetAction>)> {
+    let mut stage_actions = vec![];
+    let mut halt_actions = vec![];
+    for subnet_short in subnet_shorts {
         // Get desired version
         let (subnet_principal, desired_version) = desired_versions
             .subnets
@@ -151,9 +299,38 @@ fn check_stage<'a>(
             );
         }
 
-        // If subnet is on desired version, check bake time
+        // If subnet is on desired version, check its health and bake time
         if *subnet.replica_version == desired_version.version {
-            let remaining = get_remaining_bake_time_for_subnet(last_bake_status, subnet, stage.bake_time.as_secs_f64())?;
+            if let Some(failures) = health_failures.get(&subnet.principal) {
+                let reason = failures.join(", ");
+                if let Some(logger) = logger {
+                    warn!(logger, "Subnet {} failed health checks: {}", subnet_short, reason)
+                }
+                let previous_version = desired_versions
+                    .previous_subnets
+                    .get(&subnet.principal)
+                    .filter(|_| rollback_on_health_failure);
+                halt_actions.push(match previous_version {
+                    Some(previous) => match get_open_proposal_for_subnet(subnet_update_proposals, subnet, &previous.version) {
+                        Some(proposal) => SubnetAction::PendingProposal {
+                            subnet_short: subnet_short.clone(),
+                            proposal_id: proposal.info.id,
+                        },
+                        None => SubnetAction::Rollback {
+                            subnet_principal: subnet.principal,
+                            version: previous.version.clone(),
+                            reason,
+                        },
+                    },
+                    None => SubnetAction::Halt {
+                        subnet_short: subnet_short.clone(),
+                        reason,
+                    },
+                });
+                continue;
+            }
+
+            let remaining = get_remaining_bake_time_for_subnet(last_bake_status, subnet, bake_time.as_secs_f64())?;
             let remaining_duration = Duration::from_secs_f64(remaining);
             let formatted = format_duration(remaining_duration);
 
@@ -191,7 +368,18 @@ fn check_stage<'a>(
             continue;
         }
 
-        // If subnet is not on desired version and there is no open proposal submit it
+        // If subnet is not on desired version and there is no open proposal submit it,
+        // unless enough subnets were updated today
+        if *budget == Some(0) {
+            stage_actions.push(SubnetAction::Blocked {
+                subnet_short: Some(subnet_short.clone()),
+                reason: "the maximum number of subnets updated per day is reached".to_string(),
+            });
+            continue;
+        }
+        if let Some(budget) = budget.as_mut() {
+            *budget -= 1;
+        }
         stage_actions.push(SubnetAction::PlaceProposal {
             is_unassigned: false,
             subnet_principal: subnet.principal,
@@ -199,7 +387,7 @@ fn check_stage<'a>(
         })
     }
 
-    Ok(stage_actions)
+    Ok((stage_actions, halt_actions))
 }
 
 #[derive(Clone, Debug)]
@@ -207,6 +395,8 @@ pub struct DesiredReleaseVersion {
     pub subnets: BTreeMap<PrincipalId, crate::calculation::Version>,
     pub unassigned_nodes: crate::calculation::Version,
     pub release: crate::calculation::Release,
+    /// Versions of the release before the desired one, which unhealthy subnets are rolled back to
+    pub previous_subnets: BTreeMap<PrincipalId, crate::calculation::Version>,
 }
 
 pub fn desired_rollout_release_version<'a>(subnets: &'a [Subnet], releases: &'a [crate::calculation::Release]) -> DesiredReleaseVersion {
@@ -233,26 +423,29 @@ pub fn desired_rollout_release_version<'a>(subnets: &'a [Subnet], releases: &'a
             .expect("release should exist")
             .saturating_sub(1)];
     }
+    let previous_release = releases.iter().skip_while(|r| *r != newest_release).nth(1);
     DesiredReleaseVersion {
         release: newest_release.clone(),
         subnets: subnets
             .iter()
-            .map(|s| {
-                (
-                    s.principal,
-                    newest_release
-                        .versions
-                        .iter()
-                        .find_or_first(|v| v.subnets.iter().any(|vs| s.principal.to_string().starts_with(vs)))
-                        .expect("versions should not be empty so it should return the first element if it doesn't match anything")
-                        .clone(),
-                )
-            })
+            .map(|s| (s.principal, release_version_for_subnet(newest_release, s)))
             .collect(),
         unassigned_nodes: newest_release.versions[0].clone(),
+        previous_subnets: previous_release
+            .map(|release| subnets.iter().map(|s| (s.principal, release_version_for_subnet(release, s))).collect())
+            .unwrap_or_default(),
     }
 }
 
+fn release_version_for_subnet(release: &crate::calculation::Release, subnet: &Subnet) -> crate::calculation::Version {
+    release
+        .versions
+        .iter()
+        .find_or_first(|v| v.subnets.iter().any(|vs| subnet.principal.to_string().starts_with(vs)))
+        .expect("versions should not be empty so it should return the first element if it doesn't match anything")
+        .clone()
+}
+
 fn get_remaining_bake_time_for_subnet(last_bake_status: &BTreeMap<String, f64>, subnet: &Subnet, stage_bake_time: f64) -> anyhow::Result<f64> {
     let bake = match last_bake_status.get(&subnet.principal.to_string()) {
         Some(bake) => bake,
@@ -561,7 +754,7 @@ mod check_stages_tests {
         do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload,
     };
 
-    use crate::calculation::{Index, Rollout};
+    use crate::calculation::{Blackout, Canary, Index, Rollout};
 
     use self::test::release;
 
@@ -609,11 +802,13 @@ mod check_stages_tests {
                 pause: false,
                 skip_days: vec![],
                 stages: vec![stage(&[1], "8h"), stage(&[2, 3], "4h"), stage_unassigned(), stage_next_week(&[4], "4h")],
+                ..Default::default()
             },
             releases: vec![
                 release("rc--2024-02-21_23-01", vec![("b", vec![])]),
                 release("rc--2024-02-14_23-01", vec![("a", vec![])]),
             ],
+            ..Default::default()
         }
     }
 
@@ -652,6 +847,7 @@ mod check_stages_tests {
         expect_outcome_success: bool,
         expect_actions: Vec<SubnetAction>,
         release_start: NaiveDate,
+        health_failures: HealthFailures,
     }
 
     impl Default for TestCase {
@@ -668,6 +864,7 @@ mod check_stages_tests {
                 unassigned_node_version: Default::default(),
                 expect_outcome_success: true,
                 release_start: NaiveDate::parse_from_str("2024-02-26", "%Y-%m-%d").expect("Should parse date"),
+                health_failures: Default::default(),
             }
         }
     }
@@ -765,6 +962,38 @@ mod check_stages_tests {
             self.release_start = NaiveDate::parse_from_str(release_start, "%Y-%m-%d").expect("Should parse date");
             self
         }
+
+        pub fn with_health_failures(mut self, unhealthy_subnets: &[u64]) -> Self {
+            self.health_failures = unhealthy_subnets
+                .iter()
+                .map(|id| (principal(*id), vec!["finalization-rate is 0 (min 0.3)".to_string()]))
+                .collect();
+            self
+        }
+
+        pub fn with_rollback_on_health_failure(mut self) -> Self {
+            self.index.rollout.rollback_on_health_failure = true;
+            self
+        }
+
+        pub fn with_max_subnets_per_day(mut self, max_subnets_per_day: usize) -> Self {
+            self.index.rollout.max_subnets_per_day = Some(max_subnets_per_day);
+            self
+        }
+
+        pub fn with_not_before(mut self, stage: usize, not_before: &'static str) -> Self {
+            self.index.rollout.stages[stage].not_before = Some(NaiveDate::parse_from_str(not_before, "%Y-%m-%d").expect("Should parse date"));
+            self
+        }
+
+        pub fn with_blackout(mut self, stage: usize, start: &'static str, end: &'static str, reason: Option<&'static str>) -> Self {
+            self.index.rollout.stages[stage].blackouts.push(Blackout {
+                start: NaiveDate::parse_from_str(start, "%Y-%m-%d").expect("Should parse date"),
+                end: NaiveDate::parse_from_str(end, "%Y-%m-%d").expect("Should parse date"),
+                reason: reason.map(|r| r.to_string()),
+            });
+            self
+        }
     }
 
     fn principal(id: u64) -> PrincipalId {
@@ -788,7 +1017,7 @@ mod check_stages_tests {
 
     #[test]
     fn test_use_cases_no_feature_builds() {
-        let tests = vec![
+        let mut tests = vec![
             TestCase::new("Beginning of a new rollout").expect_actions(&[SubnetAction::PlaceProposal {
                 is_unassigned: false,
                 subnet_principal: principal(1),
@@ -878,7 +1107,134 @@ mod check_stages_tests {
                         version: "b".to_string(),
                     },
                 ]),
+            TestCase::new("Subnet failed health checks while baking, halting the rollout")
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "3h")])
+                .with_health_failures(&[1])
+                .expect_actions(&[SubnetAction::Halt {
+                    subnet_short: principal(1).to_string(),
+                    reason: "finalization-rate is 0 (min 0.3)".to_string(),
+                }]),
+            TestCase::new("Subnet failed health checks while baking, rolling it back")
+                .with_rollback_on_health_failure()
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "3h")])
+                .with_health_failures(&[1])
+                .expect_actions(&[SubnetAction::Rollback {
+                    subnet_principal: principal(1),
+                    version: "a".to_string(),
+                    reason: "finalization-rate is 0 (min 0.3)".to_string(),
+                }]),
+            TestCase::new("Rollback of an unhealthy subnet is submitted but the proposal wasn't executed")
+                .with_rollback_on_health_failure()
+                .with_subnet_update_proposals(&[(1, true, "b"), (1, false, "a")])
+                .with_last_bake_status(&[(1, "3h")])
+                .with_health_failures(&[1])
+                .expect_actions(&[SubnetAction::PendingProposal {
+                    subnet_short: principal(1).to_string(),
+                    proposal_id: 1,
+                }]),
+            TestCase::new("Subnet of a partially executed stage failed health checks, no more proposals are placed")
+                .with_subnet_update_proposals(&[(1, true, "b"), (2, true, "b")])
+                .with_last_bake_status(&[(1, "9h"), (2, "3h")])
+                .with_health_failures(&[2])
+                .expect_actions(&[SubnetAction::Halt {
+                    subnet_short: principal(2).to_string(),
+                    reason: "finalization-rate is 0 (min 0.3)".to_string(),
+                }]),
+            TestCase::new("Only one subnet is updated per day, the other subnet of the stage is blocked")
+                .with_max_subnets_per_day(1)
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(2),
+                        version: "b".to_string(),
+                    },
+                    SubnetAction::Blocked {
+                        subnet_short: Some(principal(3).to_string()),
+                        reason: "the maximum number of subnets updated per day is reached".to_string(),
+                    },
+                ]),
+            TestCase::new("Stage doesn't start before its day even though the previous stage baked")
+                .with_not_before(1, "2024-02-27")
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::Blocked {
+                        subnet_short: Some(principal(2).to_string()),
+                        reason: "stage 1 doesn't start before 2024-02-27".to_string(),
+                    },
+                    SubnetAction::Blocked {
+                        subnet_short: Some(principal(3).to_string()),
+                        reason: "stage 1 doesn't start before 2024-02-27".to_string(),
+                    },
+                ]),
+            TestCase::new("Stage doesn't proceed during its blackout")
+                .with_blackout(1, "2024-02-26", "2024-02-28", Some("freeze"))
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::Blocked {
+                        subnet_short: Some(principal(2).to_string()),
+                        reason: "stage 1 is blacked out from 2024-02-26 to 2024-02-28: freeze".to_string(),
+                    },
+                    SubnetAction::Blocked {
+                        subnet_short: Some(principal(3).to_string()),
+                        reason: "stage 1 is blacked out from 2024-02-26 to 2024-02-28: freeze".to_string(),
+                    },
+                ]),
+            TestCase::new("Stage proceeds after its blackout")
+                .with_blackout(1, "2024-02-20", "2024-02-25", None)
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(2),
+                        version: "b".to_string(),
+                    },
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(3),
+                        version: "b".to_string(),
+                    },
+                ]),
+            TestCase::new("A blackout of a finished stage doesn't hold back the next stage")
+                .with_blackout(0, "2024-02-26", "2024-02-28", None)
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(2),
+                        version: "b".to_string(),
+                    },
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(3),
+                        version: "b".to_string(),
+                    },
+                ]),
+            TestCase::new("A finished stage doesn't hold back the next stage before its day")
+                .with_not_before(0, "2024-02-27")
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "9h")])
+                .expect_actions(&[
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(2),
+                        version: "b".to_string(),
+                    },
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(3),
+                        version: "b".to_string(),
+                    },
+                ]),
         ];
+        tests.extend(canary_use_cases());
 
         for test in tests {
             let desired_versions = desired_rollout_release_version(&test.subnets, &test.index.releases);
@@ -893,6 +1249,7 @@ mod check_stages_tests {
                 test.now,
                 test.release_start,
                 desired_versions,
+                &test.health_failures,
             );
 
             assert_eq!(maybe_actions.is_ok(), test.expect_outcome_success, "test case '{}' failed", test.name);
@@ -900,7 +1257,7 @@ mod check_stages_tests {
                 continue;
             }
 
-            let actions = maybe_actions.unwrap();
+            let actions = maybe_actions.unwrap().actions;
             assert_eq!(actions, test.expect_actions, "test case '{}' failed", test.name)
         }
     }
@@ -912,11 +1269,13 @@ mod check_stages_tests {
                 pause: false,
                 skip_days: vec![],
                 stages: vec![stage(&[1], "8h"), stage(&[2, 3], "4h"), stage_unassigned(), stage_next_week(&[4], "4h")],
+                ..Default::default()
             },
             releases: vec![
                 release("rc--2024-02-21_23-01", vec![("b", vec![]), ("b.feat", vec![1, 2])]),
                 release("rc--2024-02-14_23-01", vec![("a", vec![])]),
             ],
+            ..Default::default()
         };
         let tests = vec![
             TestCase::new("Beginning of a new rollout")
@@ -957,6 +1316,7 @@ mod check_stages_tests {
                 test.now,
                 test.release_start,
                 desired_versions,
+                &test.health_failures,
             );
 
             assert_eq!(maybe_actions.is_ok(), test.expect_outcome_success, "test case '{}' failed", test.name);
@@ -964,8 +1324,103 @@ mod check_stages_tests {
                 continue;
             }
 
-            let actions = maybe_actions.unwrap();
+            let actions = maybe_actions.unwrap().actions;
             assert_eq!(actions, test.expect_actions, "test case '{}' failed", test.name)
         }
     }
+
+    /// The use cases of stages with a canary, checked along with [test_use_cases_no_feature_builds]
+    fn canary_use_cases() -> Vec<TestCase> {
+        let index_with_canaries = Index {
+            rollout: Rollout {
+                stages: vec![
+                    Stage {
+                        canary: Some(Canary {
+                            subnets: 1,
+                            bake_time: humantime::parse_duration("2h").expect("Should be able to parse."),
+                        }),
+                        ..stage(&[1, 2, 3], "8h")
+                    },
+                    stage(&[4], "4h"),
+                ],
+                ..Default::default()
+            },
+            ..craft_index_state()
+        };
+        vec![
+            TestCase::new("Beginning of a new rollout, only the canary is updated")
+                .with_index(index_with_canaries.clone())
+                .expect_actions(&[SubnetAction::PlaceProposal {
+                    is_unassigned: false,
+                    subnet_principal: principal(1),
+                    version: "b".to_string(),
+                }]),
+            TestCase::new("Canary was updated and is baking")
+                .with_index(index_with_canaries.clone())
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "30m")])
+                .expect_actions(&[SubnetAction::CanaryBaking {
+                    subnet_short: principal(1).to_string(),
+                    remaining: humantime::parse_duration("1h 30m").expect("Should parse duration"),
+                }]),
+            TestCase::new("Canary failed health checks while baking, the rest of the stage isn't updated")
+                .with_index(index_with_canaries.clone())
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "30m")])
+                .with_health_failures(&[1])
+                .expect_actions(&[SubnetAction::Halt {
+                    subnet_short: principal(1).to_string(),
+                    reason: "finalization-rate is 0 (min 0.3)".to_string(),
+                }]),
+            TestCase::new("Canary baked, promoting to the rest of the stage")
+                .with_index(index_with_canaries.clone())
+                .with_subnet_update_proposals(&[(1, true, "b")])
+                .with_last_bake_status(&[(1, "3h")])
+                .expect_actions(&[
+                    SubnetAction::PromoteCanary {
+                        canaries: vec![principal(1).to_string()],
+                    },
+                    SubnetAction::Baking {
+                        subnet_short: principal(1).to_string(),
+                        remaining: humantime::parse_duration("5h").expect("Should parse duration"),
+                    },
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(2),
+                        version: "b".to_string(),
+                    },
+                    SubnetAction::PlaceProposal {
+                        is_unassigned: false,
+                        subnet_principal: principal(3),
+                        version: "b".to_string(),
+                    },
+                ]),
+            TestCase::new("Canary was promoted and the rest of the stage is baking")
+                .with_index(index_with_canaries.clone())
+                .with_subnet_update_proposals(&[(1, true, "b"), (2, true, "b"), (3, true, "b")])
+                .with_last_bake_status(&[(1, "9h"), (2, "3h"), (3, "3h")])
+                .expect_actions(&[
+                    SubnetAction::Noop {
+                        subnet_short: principal(1).to_string(),
+                    },
+                    SubnetAction::Baking {
+                        subnet_short: principal(2).to_string(),
+                        remaining: humantime::parse_duration("5h").expect("Should parse duration"),
+                    },
+                    SubnetAction::Baking {
+                        subnet_short: principal(3).to_string(),
+                        remaining: humantime::parse_duration("5h").expect("Should parse duration"),
+                    },
+                ]),
+            TestCase::new("Whole stage baked, placing proposal for next stage")
+                .with_index(index_with_canaries)
+                .with_subnet_update_proposals(&[(1, true, "b"), (2, true, "b"), (3, true, "b")])
+                .with_last_bake_status(&[(1, "14h"), (2, "8h"), (3, "8h")])
+                .expect_actions(&[SubnetAction::PlaceProposal {
+                    is_unassigned: false,
+                    subnet_principal: principal(4),
+                    version: "b".to_string(),
+                }]),
+        ]
+    }
 }
@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use ic_management_types::Network;
+use prometheus_http_query::Client;
+use slog::{info, warn, Logger};
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    actions::{ActionExecutor, SubnetAction},
+    bake_status::{self, BakeStatusSource},
+    calculation::{calculate_hostos_progress, calculate_progress},
+    fetching::{self, holidays::HolidayCalendars, RolloutScheduleFetcherImplementation},
+    networks::{apply_gates, Gate, NetworkConfig},
+    registry_wrappers::sync_wrap,
+    status::{NetworkHandle, StatusTracker},
+    validation::validate_index,
+    RolloutMode,
+};
+
+/// Settings that are shared by all networks of the controller
+pub struct ControllerSettings {
+    pub mode: RolloutMode,
+    pub poll_interval: Duration,
+    pub bake_status_source: BakeStatusSource,
+    /// Stop the controller once the rollout of the network completed
+    pub exit_on_completion: bool,
+}
+
+/// Rolls out the release index of one network
+pub struct NetworkController {
+    logger: Logger,
+    network: Network,
+    targets_dir: PathBuf,
+    fetcher: RolloutScheduleFetcherImplementation,
+    holidays: HolidayCalendars,
+    client: Client,
+    private_key_pem: Option<String>,
+    neuron_id: u64,
+    gates: Vec<Gate>,
+    handle: NetworkHandle,
+    /// Trackers of all networks, to evaluate the gates
+    trackers: BTreeMap<String, StatusTracker>,
+}
+
+impl NetworkController {
+    pub async fn new(
+        logger: &Logger,
+        config: NetworkConfig,
+        targets_dir: PathBuf,
+        handle: NetworkHandle,
+        trackers: BTreeMap<String, StatusTracker>,
+        token: CancellationToken,
+    ) -> anyhow::Result<Self> {
+        let logger = logger.new(slog::o!("network" => config.name.clone()));
+        let network = Network::new(config.name.clone(), &config.nns_urls)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create network '{}': {}", config.name, e))?;
+        let client = Client::try_from(network.get_prometheus_endpoint().to_string())
+            .map_err(|e| anyhow::anyhow!("Couldn't create prometheus client: {:?}", e))?;
+        let fetcher = fetching::resolve(config.fetcher, logger.clone(), handle.refresh.clone(), token).await?;
+
+        Ok(Self {
+            logger,
+            network,
+            targets_dir,
+            fetcher,
+            holidays: HolidayCalendars::default(),
+            client,
+            private_key_pem: config.private_key_pem,
+            neuron_id: config.neuron_id,
+            gates: config.gates,
+            handle,
+            trackers,
+        })
+    }
+
+    /// Runs the loop of the network until the token is cancelled.
+    pub async fn run(self, settings: &ControllerSettings, token: CancellationToken) -> anyhow::Result<()> {
+        let logger = &self.logger;
+        let tracker = &self.handle.tracker;
+        let bake_status = bake_status::resolve(settings.bake_status_source, self.client.clone());
+        let executor = match &self.private_key_pem {
+            Some(path) => ActionExecutor::new(self.neuron_id, path.clone(), self.network.clone(), false, Some(logger)).await?,
+            None => ActionExecutor::test(self.network.clone(), Some(logger)).await?,
+        };
+
+        let mut interval = tokio::time::interval(settings.poll_interval);
+        let mut should_sleep = false;
+        let mut index_problems = vec![];
+        loop {
+            if should_sleep {
+                select! {
+                    _ = token.cancelled() => break,
+                    tick = interval.tick() => info!(logger, "Running loop @ {:?}", tick),
+                    _ = self.handle.refresh.notified() => info!(logger, "Running loop because the release index changed"),
+                }
+            } else if token.is_cancelled() {
+                break;
+            }
+            should_sleep = true;
+
+            // Otherwise the subnet that was rolled back would be updated again
+            if tracker.snapshot().resume_required {
+                warn!(
+                    logger,
+                    "Rollout is halted because a subnet was rolled back, resume it with POST /resume/{}", self.handle.name
+                );
+                continue;
+            }
+
+            info!(logger, "Syncing registry for network '{}'", self.network);
+            let maybe_registry_state = select! {
+                res = sync_wrap(logger.clone(), self.targets_dir.clone(), self.network.clone()) => res,
+                _ = token.cancelled() => break,
+            };
+            let registry_state = match maybe_registry_state {
+                Ok(state) => {
+                    info!(logger, "Syncing registry completed");
+                    state
+                }
+                Err(e) => {
+                    warn!(logger, "{:?}", e);
+                    should_sleep = false;
+                    continue;
+                }
+            };
+
+            info!(logger, "Fetching rollout index");
+            let mut index = match self.fetcher.fetch().await {
+                Ok(index) => {
+                    info!(logger, "Fetching of new index complete");
+                    index
+                }
+                Err(e) => {
+                    warn!(logger, "{:?}", e);
+                    should_sleep = false;
+                    continue;
+                }
+            };
+
+            index.rollout.holidays = self
+                .holidays
+                .load(logger, &index.rollout.holiday_calendars, index.rollout.time_zone())
+                .await;
+
+            // Get elected versions for later
+            let elected_versions = match settings.mode {
+                RolloutMode::Guestos => registry_state.get_elected_guestos_versions().await,
+                RolloutMode::Hostos => registry_state.get_elected_hostos_versions().await,
+            };
+            let elected_versions = match elected_versions {
+                Ok(versions) => versions,
+                Err(e) => {
+                    warn!(logger, "{:?}", e);
+                    should_sleep = false;
+                    continue;
+                }
+            };
+
+            // A broken index would only be noticed in the middle of the rollout.
+            // Problems are only reported, as some are expected while rolling out,
+            // e.g. versions of later stages that aren't elected yet.
+            if let RolloutMode::Guestos = settings.mode {
+                let subnets = registry_state.subnets().into_values().collect::<Vec<_>>();
+                let problems = validate_index(&index, &subnets, &elected_versions);
+                if problems != index_problems {
+                    for problem in &problems {
+                        warn!(logger, "Problem in the release index: {}", problem);
+                    }
+                    index_problems = problems;
+                }
+            }
+
+            // Calculate what should be done
+            info!(logger, "Calculating the progress of the current release");
+            let releases = index.releases.clone();
+            let progress = match settings.mode {
+                RolloutMode::Guestos => calculate_progress(logger, index, &bake_status, &self.client, registry_state).await,
+                RolloutMode::Hostos => calculate_hostos_progress(logger, index, registry_state).await,
+            };
+            let progress = match progress {
+                Ok(progress) => progress,
+                Err(e) => {
+                    warn!(logger, "{:?}", e);
+                    continue;
+                }
+            };
+            let statuses = self.trackers.iter().map(|(name, tracker)| (name.clone(), tracker.snapshot())).collect();
+            let progress = apply_gates(progress, &self.gates, &releases, &statuses);
+            info!(logger, "Calculating completed");
+            tracker.update(&progress);
+
+            let actions = progress.actions;
+            if actions.is_empty() {
+                info!(logger, "Rollout completed");
+                tracker.loop_succeeded();
+                if settings.exit_on_completion {
+                    token.cancel();
+                    break;
+                }
+                continue;
+            }
+            info!(logger, "Calculated actions: {:#?}", actions);
+            match executor.execute(&actions, &elected_versions).await {
+                Ok(()) => {
+                    info!(logger, "Actions taken successfully");
+                    // A halted rollout proceeds once the health checks pass again
+                    tracker.set_halted(actions.iter().any(|a| a.halts_rollout()));
+                    if actions.iter().any(|a| matches!(a, SubnetAction::Rollback { .. })) {
+                        tracker.require_resume();
+                    }
+                    tracker.proposals_placed(actions.iter().filter(|a| a.places_proposal()).count() as u64);
+                    tracker.loop_succeeded();
+                }
+                Err(e) => warn!(logger, "{:?}", e),
+            };
+        }
+
+        Ok(())
+    }
+}
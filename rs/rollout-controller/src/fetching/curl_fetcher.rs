@@ -1,10 +1,11 @@
 use clap::Parser;
 use reqwest::Client;
+use serde::Deserialize;
 use slog::{debug, Logger};
 
 use super::RolloutScheduleFetcher;
 
-#[derive(Parser, Clone, Debug)]
+#[derive(Parser, Deserialize, Clone, Debug)]
 pub struct CurlFetcherConfig {
     #[clap(
         long = "url",
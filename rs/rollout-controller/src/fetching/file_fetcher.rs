@@ -0,0 +1,117 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use clap::Parser;
+use serde::Deserialize;
+use slog::{debug, info, warn, Logger};
+use tokio::{select, sync::Notify};
+use tokio_util::sync::CancellationToken;
+
+use crate::calculation::Index;
+
+use super::RolloutScheduleFetcher;
+
+/// How often the release index is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Deserialize, Clone, Debug)]
+pub struct FileFetcherConfig {
+    #[clap(
+        long = "release-index",
+        default_value = "release-index.yaml",
+        help = r#"
+Path to a local release index. The controller runs a loop as soon as the
+file changes, which is useful for local development.
+
+"#
+    )]
+    pub release_index: PathBuf,
+}
+
+#[derive(Clone)]
+pub struct FileFetcher {
+    logger: Logger,
+    path: PathBuf,
+}
+
+impl FileFetcher {
+    /// Creates the fetcher and starts watching the file until the token is
+    /// cancelled, notifying `refresh` whenever its modification time changes.
+    pub fn new(logger: Logger, path: PathBuf, refresh: Arc<Notify>, token: CancellationToken) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Release index '{}' doesn't exist", path.display()));
+        }
+        let last_modified = path.metadata().and_then(|m| m.modified()).ok();
+        let fetcher = Self { logger, path };
+        tokio::spawn(fetcher.clone().watch(refresh, last_modified, token));
+
+        Ok(fetcher)
+    }
+
+    async fn watch(self, refresh: Arc<Notify>, mut last_modified: Option<SystemTime>, token: CancellationToken) {
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            select! {
+                _ = token.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            let modified = match tokio::fs::metadata(&self.path).await.and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(e) => {
+                    warn!(self.logger, "Couldn't read the modification time of '{}': {:?}", self.path.display(), e);
+                    continue;
+                }
+            };
+            if last_modified != Some(modified) {
+                info!(self.logger, "Release index '{}' changed", self.path.display());
+                last_modified = Some(modified);
+                refresh.notify_one();
+            }
+        }
+    }
+}
+
+impl RolloutScheduleFetcher for FileFetcher {
+    async fn fetch(&self) -> anyhow::Result<Index> {
+        debug!(self.logger, "Reading rollout index from '{}'", self.path.display());
+
+        let content = tokio::fs::read(&self.path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Error reading '{}': {:?}", self.path.display(), e))?;
+
+        serde_yaml::from_slice(&content).map_err(|e| anyhow::anyhow!("Couldn't parse release index: {:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Write};
+
+    use slog::o;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn notifies_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("release-index.yaml");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"rollout:\n  skip_days: []\n  stages: []\nreleases: []\n").unwrap();
+        let refresh = Arc::new(Notify::new());
+
+        let token = CancellationToken::new();
+        let fetcher = FileFetcher::new(Logger::root(slog::Discard, o!()), path, refresh.clone(), token.clone()).unwrap();
+        let index = fetcher.fetch().await.unwrap();
+        assert!(index.rollout.stages.is_empty());
+        assert!(index.releases.is_empty());
+
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), refresh.notified())
+            .await
+            .expect("Should be notified about the change");
+        token.cancel();
+    }
+}
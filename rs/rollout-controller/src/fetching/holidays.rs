@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use slog::{warn, Logger};
+
+/// Keeps the days of every calendar that was loaded, so that the rollout keeps
+/// respecting the holidays of a calendar that can't be loaded for a while.
+#[derive(Default)]
+pub struct HolidayCalendars {
+    known: Mutex<BTreeMap<(String, Tz), BTreeSet<NaiveDate>>>,
+}
+
+impl HolidayCalendars {
+    /// Loads the days of all events in the calendars, or the days of the last
+    /// successful load of the calendars that can't be loaded.
+    pub async fn load(&self, logger: &Logger, calendars: &[String], time_zone: Tz) -> BTreeSet<NaiveDate> {
+        let mut loaded = vec![];
+        for calendar in calendars {
+            match load_calendar(calendar, time_zone).await {
+                Ok(days) => loaded.push((calendar.clone(), days)),
+                Err(e) => warn!(logger, "{:?}, using the holidays it had the last time it was loaded", e),
+            }
+        }
+
+        let mut known = self.known.lock().unwrap();
+        for (calendar, days) in loaded {
+            known.insert((calendar, time_zone), days);
+        }
+        calendars
+            .iter()
+            .filter_map(|calendar| known.get(&(calendar.clone(), time_zone)))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Loads the days of all events in the iCal files at the given paths or urls,
+/// in the time zone of the rollout.
+pub async fn load_holidays(calendars: &[String], time_zone: Tz) -> anyhow::Result<BTreeSet<NaiveDate>> {
+    let mut holidays = BTreeSet::new();
+    for calendar in calendars {
+        holidays.extend(load_calendar(calendar, time_zone).await?);
+    }
+
+    Ok(holidays)
+}
+
+async fn load_calendar(calendar: &str, time_zone: Tz) -> anyhow::Result<BTreeSet<NaiveDate>> {
+    let content = match calendar.starts_with("http://") || calendar.starts_with("https://") {
+        true => reqwest::get(calendar)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow::anyhow!("Error fetching holiday calendar '{}': {:?}", calendar, e))?
+            .text()
+            .await
+            .map_err(|e| anyhow::anyhow!("Error reading holiday calendar '{}': {:?}", calendar, e))?,
+        false => tokio::fs::read_to_string(calendar)
+            .await
+            .map_err(|e| anyhow::anyhow!("Error reading holiday calendar '{}': {:?}", calendar, e))?,
+    };
+    parse_ical(&content, time_zone).map_err(|e| anyhow::anyhow!("Couldn't parse holiday calendar '{}': {:?}", calendar, e))
+}
+
+/// Days covered by the events of an iCal calendar in the time zone of the
+/// rollout. Only the start and end of events are taken into account,
+/// recurrence rules are not supported.
+fn parse_ical(content: &str, time_zone: Tz) -> anyhow::Result<BTreeSet<NaiveDate>> {
+    // Long lines are folded by starting the continuation with a whitespace
+    let unfolded = content.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+
+    let mut days = BTreeSet::new();
+    let mut event: Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> = None;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Parameters follow the name, e.g. `DTSTART;VALUE=DATE` or `DTSTART;TZID=Europe/Zurich`
+        let (name, parameters) = name.split_once(';').unwrap_or((name, ""));
+        match (name, event.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some((None, None)),
+            ("DTSTART", Some((start, _))) => *start = Some(parse_date_time(parameters, value, time_zone)?),
+            ("DTEND", Some((_, end))) => *end = Some(parse_date_time(parameters, value, time_zone)?),
+            ("END", Some((start, end))) if value == "VEVENT" => {
+                let start = start.ok_or(anyhow::anyhow!("Event without DTSTART"))?;
+                // The end is exclusive, and an event without an end covers only the day it starts on
+                let end = end
+                    .filter(|end| *end > start)
+                    .unwrap_or(start + TimeDelta::try_seconds(1).expect("Should be able to convert to seconds"));
+                days.extend(start.date().iter_days().take_while(|day| day.and_time(Default::default()) < end));
+                event = None;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(days)
+}
+
+/// Dates like `20241225` start at midnight in the time zone of the rollout.
+/// Date-times are in UTC, like `20241225T000000Z`, in the time zone of their
+/// `TZID` parameter, or otherwise in the one of the rollout.
+fn parse_date_time(parameters: &str, value: &str, time_zone: Tz) -> anyhow::Result<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok(date.and_time(Default::default()));
+    }
+
+    let invalid = |e| anyhow::anyhow!("Invalid date '{}': {:?}", value, e);
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date_time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(invalid)?;
+        return Ok(date_time.and_utc().with_timezone(&time_zone).naive_local());
+    }
+
+    let date_time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(invalid)?;
+    let event_time_zone = match parameters.split(';').find_map(|p| p.strip_prefix("TZID=")) {
+        Some(tz) => tz
+            .trim_matches('"')
+            .parse::<Tz>()
+            .map_err(|e| anyhow::anyhow!("Unknown time zone '{}': {:?}", tz, e))?,
+        None => time_zone,
+    };
+    let date_time =
+        event_time_zone
+            .from_local_datetime(&date_time)
+            .earliest()
+            .ok_or(anyhow::anyhow!("Date '{}' doesn't exist in {}", value, event_time_zone))?;
+    Ok(date_time.with_timezone(&time_zone).naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days(days: &[(i32, u32, u32)]) -> BTreeSet<NaiveDate> {
+        days.iter().map(|(y, m, d)| NaiveDate::from_ymd_opt(*y, *m, *d).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_holidays() {
+        let calendar = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20241225\r
+DTEND;VALUE=DATE:20241227\r
+SUMMARY:Christmas\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20250101T000000Z\r
+SUMMARY:New Year's\r
+  Day\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+        assert_eq!(
+            parse_ical(calendar, Tz::UTC).unwrap(),
+            days(&[(2024, 12, 25), (2024, 12, 26), (2025, 1, 1)])
+        );
+    }
+
+    #[test]
+    fn parse_holidays_in_time_zones() {
+        let calendar = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+DTSTART:20241231T230000Z
+DTEND:20250101T230000Z
+SUMMARY:New Year's Day in Zurich
+END:VEVENT
+BEGIN:VEVENT
+DTSTART;TZID=America/New_York:20250120T200000
+DTEND;TZID=America/New_York:20250120T220000
+SUMMARY:Evening in New York
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20250301T090000
+DTEND:20250302T000000
+SUMMARY:Floating time
+END:VEVENT
+END:VCALENDAR
+";
+
+        assert_eq!(
+            parse_ical(calendar, Tz::Europe__Zurich).unwrap(),
+            days(&[(2025, 1, 1), (2025, 1, 21), (2025, 3, 1)])
+        );
+        assert_eq!(
+            parse_ical(calendar, Tz::UTC).unwrap(),
+            days(&[(2024, 12, 31), (2025, 1, 1), (2025, 1, 21), (2025, 3, 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_last_known_holidays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("holidays.ics");
+        std::fs::write(&path, "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20241225\nEND:VEVENT\n").unwrap();
+        let calendars = vec![path.to_string_lossy().to_string()];
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let holidays = HolidayCalendars::default();
+
+        assert_eq!(holidays.load(&logger, &calendars, Tz::UTC).await, days(&[(2024, 12, 25)]));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(holidays.load(&logger, &calendars, Tz::UTC).await, days(&[(2024, 12, 25)]));
+    }
+}
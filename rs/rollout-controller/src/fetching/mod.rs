@@ -1,34 +1,63 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
 use slog::Logger;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
 
-use crate::{calculation::Index, Commands};
+use crate::calculation::Index;
 
 use self::{
     curl_fetcher::{CurlFetcher, CurlFetcherConfig},
+    file_fetcher::{FileFetcher, FileFetcherConfig},
     sparse_checkout_fetcher::{SparseCheckoutFetcher, SparseCheckoutFetcherConfig},
 };
 
 pub mod curl_fetcher;
+pub mod file_fetcher;
+pub mod holidays;
 pub mod sparse_checkout_fetcher;
 
 pub trait RolloutScheduleFetcher {
     async fn fetch(&self) -> anyhow::Result<Index>;
 }
 
+/// Where the release index of a network is fetched from
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FetcherConfig {
+    Git(SparseCheckoutFetcherConfig),
+    Curl(CurlFetcherConfig),
+    File(FileFetcherConfig),
+}
+
 pub enum RolloutScheduleFetcherImplementation {
     Curl(CurlFetcher),
     Git(SparseCheckoutFetcher),
+    File(FileFetcher),
 }
 
-pub async fn resolve(subcmd: Commands, logger: Logger) -> anyhow::Result<RolloutScheduleFetcherImplementation> {
-    match subcmd {
-        Commands::Git(SparseCheckoutFetcherConfig {
+/// Resolves the fetcher of the config. Fetchers that notice changes of the
+/// release index themselves notify `refresh` about them until the token is
+/// cancelled.
+pub async fn resolve(
+    config: FetcherConfig,
+    logger: Logger,
+    refresh: Arc<Notify>,
+    token: CancellationToken,
+) -> anyhow::Result<RolloutScheduleFetcherImplementation> {
+    match config {
+        FetcherConfig::Git(SparseCheckoutFetcherConfig {
             repo_url,
             release_index,
             repo_path,
         }) => SparseCheckoutFetcher::new(logger, repo_path, repo_url, release_index)
             .await
             .map(RolloutScheduleFetcherImplementation::Git),
-        Commands::Curl(CurlFetcherConfig { url }) => CurlFetcher::new(logger, url).map(RolloutScheduleFetcherImplementation::Curl),
+        FetcherConfig::Curl(CurlFetcherConfig { url }) => CurlFetcher::new(logger, url).map(RolloutScheduleFetcherImplementation::Curl),
+        FetcherConfig::File(FileFetcherConfig { release_index }) => {
+            FileFetcher::new(logger, release_index, refresh, token).map(RolloutScheduleFetcherImplementation::File)
+        }
     }
 }
 
@@ -37,6 +66,7 @@ impl RolloutScheduleFetcherImplementation {
         match self {
             RolloutScheduleFetcherImplementation::Curl(implementation) => implementation.fetch().await,
             RolloutScheduleFetcherImplementation::Git(implementation) => implementation.fetch().await,
+            RolloutScheduleFetcherImplementation::File(implementation) => implementation.fetch().await,
         }
     }
 }
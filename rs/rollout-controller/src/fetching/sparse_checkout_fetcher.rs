@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use clap::Parser;
+use serde::Deserialize;
 use slog::{debug, info, Logger};
 use tokio::{
     fs::{create_dir_all, File},
@@ -12,7 +13,7 @@ use crate::calculation::Index;
 
 use super::RolloutScheduleFetcher;
 
-#[derive(Parser, Clone, Debug)]
+#[derive(Parser, Deserialize, Clone, Debug)]
 pub struct SparseCheckoutFetcherConfig {
     #[clap(
         long = "repo-path",
@@ -1,31 +1,59 @@
-use std::{path::PathBuf, time::Duration};
-
-use clap::{Parser, Subcommand};
-use fetching::{curl_fetcher::CurlFetcherConfig, sparse_checkout_fetcher::SparseCheckoutFetcherConfig};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, time::Duration};
+
+use axum_otel_metrics::HttpMetricsLayerBuilder;
+use bake_status::BakeStatusSource;
+use clap::{Parser, Subcommand, ValueEnum};
+use controller::{ControllerSettings, NetworkController};
+use fetching::{
+    curl_fetcher::CurlFetcherConfig, file_fetcher::FileFetcherConfig, sparse_checkout_fetcher::SparseCheckoutFetcherConfig, FetcherConfig,
+};
+use futures::future::join_all;
 use humantime::parse_duration;
-use prometheus_http_query::Client;
-use slog::{info, o, warn, Drain, Level, Logger};
+use ic_management_types::Network;
+use networks::{NetworkConfig, NetworksConfig};
+use simulation::SimulateConfig;
+use slog::{error, info, o, Drain, Level, Logger};
+use status::{NetworkHandle, Secret, StatusTracker};
 use tokio::select;
 use tokio_util::sync::CancellationToken;
 use url::Url;
-
-use crate::{actions::ActionExecutor, calculation::calculate_progress, registry_wrappers::sync_wrap};
+use validation::ValidateConfig;
 
 mod actions;
+mod bake_status;
 mod calculation;
+mod controller;
 mod fetching;
+mod networks;
 mod registry_wrappers;
+mod simulation;
+mod status;
+mod validation;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
-    let target_network = ic_management_types::Network::new(args.network.clone(), &args.nns_urls)
-        .await
-        .expect("Failed to create network");
     let logger = make_logger(args.log_level.clone().into());
-    let prometheus_endpoint = target_network.get_prometheus_endpoint();
 
-    let client = Client::try_from(prometheus_endpoint.to_string()).map_err(|e| anyhow::anyhow!("Couldn't create prometheus client: {:?}", e))?;
+    let single_network = |fetcher: FetcherConfig| {
+        vec![NetworkConfig {
+            name: args.network.clone(),
+            nns_urls: args.nns_urls.clone(),
+            targets_dir: Some(args.targets_dir.clone()),
+            fetcher,
+            private_key_pem: args.private_key_pem.clone(),
+            neuron_id: args.neuron_id,
+            gates: vec![],
+        }]
+    };
+    let networks = match args.subcommand.clone() {
+        Commands::Git(config) => single_network(FetcherConfig::Git(config)),
+        Commands::Curl(config) => single_network(FetcherConfig::Curl(config)),
+        Commands::File(config) => single_network(FetcherConfig::File(config)),
+        Commands::Networks(config) => config.load().await?,
+        Commands::Simulate(config) => return simulation::run(logger, config, args.targets_dir.clone(), target_network(&args).await?).await,
+        Commands::Validate(config) => return validation::run(logger, config, args.targets_dir.clone(), target_network(&args).await?).await,
+    };
 
     let shutdown = tokio::signal::ctrl_c();
     let token = CancellationToken::new();
@@ -41,94 +69,82 @@ async fn main() -> anyhow::Result<()> {
         info!(shutdown_logger, "Received shutdown");
     });
 
-    let fetcher = fetching::resolve(args.subcommand, logger.clone()).await?;
+    // Initialize the metrics layer because in the build method the `global::provider`
+    // is set. The instruments of the status trackers are created from that provider.
+    let metrics_layer = HttpMetricsLayerBuilder::new().build();
+    let handles = networks.iter().map(|n| NetworkHandle::new(&n.name)).collect::<Vec<_>>();
+    let trackers = handles.iter().map(|h| (h.name.clone(), h.tracker.clone())).collect::<BTreeMap<_, _>>();
+    let server = status::serve(
+        logger.clone(),
+        args.listen_address,
+        handles.clone(),
+        args.secret.clone(),
+        metrics_layer,
+        token.clone(),
+    );
+    let server_token = token.clone();
+    let server_handle = tokio::spawn(async move {
+        // Without the server rollbacks can't be resumed, so all networks stop
+        let result = server.await;
+        if result.is_err() {
+            server_token.cancel();
+        }
+        result
+    });
 
-    let executor = match args.private_key_pem {
-        Some(path) => ActionExecutor::new(args.neuron_id, path, target_network.clone(), false, Some(&logger)).await?,
-        None => ActionExecutor::test(target_network.clone(), Some(&logger)).await?,
+    let settings = ControllerSettings {
+        mode: args.mode,
+        poll_interval: args.poll_interval,
+        bake_status_source: args.bake_status_source,
+        exit_on_completion: networks.len() == 1,
     };
+    let results = join_all(networks.into_iter().zip(handles).map(|(config, handle)| {
+        let targets_dir = config.targets_dir.clone().unwrap_or_else(|| args.targets_dir.join(&config.name));
+        run_network(&logger, config, targets_dir, handle, trackers.clone(), &settings, token.clone())
+    }))
+    .await;
+    // Nothing is left to serve once all networks stopped
+    token.cancel();
 
-    let mut interval = tokio::time::interval(args.poll_interval);
-    let mut should_sleep = false;
-    loop {
-        if should_sleep {
-            select! {
-                _ = token.cancelled() => break,
-                tick = interval.tick() => info!(logger, "Running loop @ {:?}", tick),
-            }
-        } else if token.is_cancelled() {
-            break;
-        }
-        should_sleep = true;
-
-        info!(logger, "Syncing registry for network '{}'", target_network);
-        let maybe_registry_state = select! {
-            res = sync_wrap(logger.clone(), args.targets_dir.clone(), target_network.clone()) => res,
-            _ = token.cancelled() => break,
-        };
-        let registry_state = match maybe_registry_state {
-            Ok(state) => {
-                info!(logger, "Syncing registry completed");
-                state
-            }
-            Err(e) => {
-                warn!(logger, "{:?}", e);
-                should_sleep = false;
-                continue;
-            }
-        };
-
-        info!(logger, "Fetching rollout index");
-        let index = match fetcher.fetch().await {
-            Ok(index) => {
-                info!(logger, "Fetching of new index complete");
-                index
-            }
-            Err(e) => {
-                warn!(logger, "{:?}", e);
-                should_sleep = false;
-                continue;
-            }
-        };
-
-        // Get elected GuestOS versions for later
-        let elected_guestos_versions = match registry_state.get_elected_guestos_versions().await {
-            Ok(versions) => versions,
-            Err(e) => {
-                warn!(logger, "{:?}", e);
-                should_sleep = false;
-                continue;
-            }
-        };
-
-        // Calculate what should be done
-        info!(logger, "Calculating the progress of the current release");
-        let actions = match calculate_progress(&logger, index, &client, registry_state).await {
-            Ok(actions) => actions,
-            Err(e) => {
-                warn!(logger, "{:?}", e);
-                continue;
-            }
-        };
-        info!(logger, "Calculating completed");
-
-        if actions.is_empty() {
-            info!(logger, "Rollout completed");
-            token.cancel();
-            break;
-        }
-        info!(logger, "Calculated actions: {:#?}", actions);
-        match executor.execute(&actions, &elected_guestos_versions).await {
-            Ok(()) => info!(logger, "Actions taken successfully"),
-            Err(e) => warn!(logger, "{:?}", e),
-        };
-    }
     info!(logger, "Shutdown complete");
     shutdown_handle.await.unwrap();
+    server_handle.await??;
 
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    if failed > 0 {
+        anyhow::bail!("The rollout of {} of the networks failed", failed);
+    }
     Ok(())
 }
 
+/// Runs the controller of a network until the token is cancelled. An error
+/// only stops this network, the others keep rolling out.
+async fn run_network(
+    logger: &Logger,
+    config: NetworkConfig,
+    targets_dir: PathBuf,
+    handle: NetworkHandle,
+    trackers: BTreeMap<String, StatusTracker>,
+    settings: &ControllerSettings,
+    token: CancellationToken,
+) -> anyhow::Result<()> {
+    let name = config.name.clone();
+    let result = match NetworkController::new(logger, config, targets_dir, handle, trackers, token.clone()).await {
+        Ok(controller) => controller.run(settings, token).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        error!(logger, "The rollout of network {} stopped: {:?}", name, e);
+    }
+    result
+}
+
+async fn target_network(args: &Cli) -> anyhow::Result<Network> {
+    Network::new(args.network.clone(), &args.nns_urls)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create network: {}", e))
+}
+
 fn make_logger(level: Level) -> Logger {
     let decorator = slog_term::TermDecorator::new().build();
     let full_format = slog_term::FullFormat::new(decorator).build().fuse();
@@ -212,6 +228,59 @@ By default is 0.
     )]
     neuron_id: u64,
 
+    #[clap(
+        long,
+        value_enum,
+        default_value = "guestos",
+        help = r#"
+Which operating system to roll out. In 'hostos' mode the controller follows
+the 'hostos_rollout' section of the index.
+
+"#
+    )]
+    mode: RolloutMode,
+
+    #[clap(
+        long = "listen-address",
+        default_value = "0.0.0.0:8080",
+        help = r#"
+The address on which the status of the rollout of the first network is served
+on '/status', the one of all networks on '/status/all', the one of a network on
+'/status/<network>' and the metrics on '/metrics'. A POST to '/webhook' with the
+secret, e.g. a push event of the repository with the release index, runs the
+loop immediately.
+
+"#
+    )]
+    listen_address: SocketAddr,
+
+    #[clap(
+        long,
+        env = "ROLLOUT_CONTROLLER_SECRET",
+        help = r#"
+Secret that has to be passed as 'Authorization: Bearer <secret>' to POST to
+'/webhook' and '/resume/<network>'. The webhook also accepts it as the secret
+of a GitHub webhook, which signs the payload in 'X-Hub-Signature-256', or as
+the token of a GitLab webhook in 'X-Gitlab-Token'. Without it both are
+forbidden.
+
+"#
+    )]
+    secret: Option<Secret>,
+
+    #[clap(
+        long = "bake-status-source",
+        value_enum,
+        default_value = "prometheus",
+        help = r#"
+Where to take the bake time of subnets from. 'registry' derives it from the
+versions in the registry and the execution time of the proposals that deployed
+them, which works for testnets without metrics.
+
+"#
+    )]
+    bake_status_source: BakeStatusSource,
+
     #[clap(subcommand)]
     pub(crate) subcommand: Commands,
 }
@@ -220,6 +289,20 @@ By default is 0.
 enum Commands {
     Git(SparseCheckoutFetcherConfig),
     Curl(CurlFetcherConfig),
+    /// Read the release index from a local file and run the loop whenever it changes
+    File(FileFetcherConfig),
+    /// Roll out several networks, each with its own release index and neuron
+    Networks(NetworksConfig),
+    /// Print the expected schedule of the rollout instead of running it
+    Simulate(SimulateConfig),
+    /// Check the release index against the schema and the registry
+    Validate(ValidateConfig),
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(crate) enum RolloutMode {
+    Guestos,
+    Hostos,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,254 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::Parser;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    actions::SubnetAction,
+    calculation::{Progress, Release},
+    fetching::FetcherConfig,
+    status::RolloutStatus,
+};
+
+#[derive(Parser, Clone, Debug)]
+pub struct NetworksConfig {
+    #[clap(
+        long = "config",
+        default_value = "networks.yaml",
+        help = r#"
+Path to the config of the networks that the controller rolls out. The
+network, NNS urls, private key and neuron are taken from the config instead
+of the arguments. The registry of each network is stored in
+'<targets-dir>/<name>' unless the config says otherwise.
+
+"#
+    )]
+    pub config: PathBuf,
+}
+
+/// One network of the config, for example:
+///
+/// ```yaml
+/// networks:
+///   - name: staging
+///     fetcher:
+///       curl:
+///         url: https://raw.githubusercontent.com/dfinity/dre/main/release-index.yaml
+///     private_key_pem: /secrets/staging.pem
+///   - name: mainnet
+///     fetcher:
+///       git:
+///         repo_path: /var/lib/rollout-controller/dre
+///         repo_url: git@github.com:dfinity/dre.git
+///         release_index: release-index.yaml
+///     private_key_pem: /secrets/mainnet.pem
+///     neuron_id: 40
+///     gates:
+///       - stage: 1
+///         network: staging
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct NetworkConfig {
+    /// Can be one of "mainnet", "staging", or an arbitrary testnet name
+    pub name: String,
+    /// Mandatory for testnets
+    #[serde(default)]
+    pub nns_urls: Vec<Url>,
+    /// Where the registry is stored, `<targets-dir>/<name>` by default
+    #[serde(default)]
+    pub targets_dir: Option<PathBuf>,
+    pub fetcher: FetcherConfig,
+    /// The network is rolled out in dry-run mode without a key
+    #[serde(default)]
+    pub private_key_pem: Option<String>,
+    #[serde(default)]
+    pub neuron_id: u64,
+    #[serde(default)]
+    pub gates: Vec<Gate>,
+}
+
+#[derive(Deserialize)]
+struct Networks {
+    networks: Vec<NetworkConfig>,
+}
+
+/// Proposals for `stage` and the stages after it are only placed once `network`
+/// rolled out the release completely, or moved on to a newer one.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Gate {
+    pub stage: usize,
+    pub network: String,
+}
+
+impl NetworksConfig {
+    pub async fn load(&self) -> anyhow::Result<Vec<NetworkConfig>> {
+        let content = tokio::fs::read(&self.config)
+            .await
+            .map_err(|e| anyhow::anyhow!("Couldn't read '{}': {:?}", self.config.display(), e))?;
+        let Networks { networks } = serde_yaml::from_slice(&content).map_err(|e| anyhow::anyhow!("Couldn't parse networks config: {:?}", e))?;
+        check_networks(&networks)?;
+
+        Ok(networks)
+    }
+}
+
+fn check_networks(networks: &[NetworkConfig]) -> anyhow::Result<()> {
+    if networks.is_empty() {
+        return Err(anyhow::anyhow!("No networks are configured"));
+    }
+    for (i, network) in networks.iter().enumerate() {
+        if networks[..i].iter().any(|n| n.name == network.name) {
+            return Err(anyhow::anyhow!("Network '{}' is configured more than once", network.name));
+        }
+        for gate in &network.gates {
+            if gate.network == network.name || !networks.iter().any(|n| n.name == gate.network) {
+                return Err(anyhow::anyhow!("Network '{}' can't be gated by network '{}'", network.name, gate.network));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Blocks the proposals of the gated stages while the gating networks haven't
+/// rolled out the release yet. `releases` are the releases of the index, newest
+/// first.
+pub fn apply_gates(progress: Progress, gates: &[Gate], releases: &[Release], statuses: &BTreeMap<String, RolloutStatus>) -> Progress {
+    let (Some(release), Some(stage)) = (&progress.release, progress.stage) else {
+        return progress;
+    };
+    let Some(gate) = gates
+        .iter()
+        .filter(|g| g.stage <= stage)
+        .find(|g| !rolled_out(release, statuses.get(&g.network), releases))
+    else {
+        return progress;
+    };
+
+    let reason = format!("stage {} waits for network '{}' to roll out '{}'", gate.stage, gate.network, release);
+    let actions = progress
+        .actions
+        .iter()
+        .map(|action| match action {
+            SubnetAction::PlaceProposal {
+                is_unassigned,
+                subnet_principal,
+                ..
+            } => SubnetAction::Blocked {
+                subnet_short: Some(match is_unassigned {
+                    true => "unassigned-nodes".to_string(),
+                    false => subnet_principal.to_string(),
+                }),
+                reason: reason.clone(),
+            },
+            SubnetAction::PlaceHostosProposal { node_group, .. } => SubnetAction::Blocked {
+                subnet_short: Some(node_group.clone()),
+                reason: reason.clone(),
+            },
+            action => action.clone(),
+        })
+        .collect();
+
+    Progress { actions, ..progress }
+}
+
+/// Whether the network of `status` completed the rollout of `release` or rolls
+/// out a newer release.
+fn rolled_out(release: &str, status: Option<&RolloutStatus>, releases: &[Release]) -> bool {
+    let Some(status) = status else {
+        return false;
+    };
+    let position = |rc_name: &str| releases.iter().position(|r| r.rc_name == rc_name);
+    match &status.release {
+        Some(current) if current == release => status.stage.is_none() && status.actions.is_empty(),
+        Some(current) => matches!((position(current), position(release)), (Some(current), Some(release)) if current < release),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_base_types::PrincipalId;
+
+    use super::*;
+
+    fn release(rc_name: &str) -> Release {
+        Release {
+            rc_name: rc_name.to_string(),
+            versions: vec![],
+        }
+    }
+
+    fn status(release: &str, stage: Option<usize>) -> BTreeMap<String, RolloutStatus> {
+        BTreeMap::from([(
+            "staging".to_string(),
+            RolloutStatus {
+                release: Some(release.to_string()),
+                stage,
+                ..Default::default()
+            },
+        )])
+    }
+
+    #[test]
+    fn gated_stages_are_blocked() {
+        let releases = vec![release("rc--3"), release("rc--2"), release("rc--1")];
+        let gates = vec![Gate {
+            stage: 1,
+            network: "staging".to_string(),
+        }];
+        let progress = |stage: usize| Progress {
+            release: Some("rc--2".to_string()),
+            stage: Some(stage),
+            actions: vec![SubnetAction::PlaceProposal {
+                is_unassigned: false,
+                subnet_principal: PrincipalId::new_subnet_test_id(1),
+                version: "new".to_string(),
+            }],
+            ..Default::default()
+        };
+        let blocked = Progress {
+            actions: vec![SubnetAction::Blocked {
+                subnet_short: Some(PrincipalId::new_subnet_test_id(1).to_string()),
+                reason: "stage 1 waits for network 'staging' to roll out 'rc--2'".to_string(),
+            }],
+            ..progress(1)
+        };
+
+        assert_eq!(apply_gates(progress(0), &gates, &releases, &BTreeMap::new()), progress(0));
+        assert_eq!(apply_gates(progress(1), &gates, &releases, &BTreeMap::new()), blocked);
+        assert_eq!(apply_gates(progress(1), &gates, &releases, &status("rc--1", None)), blocked);
+        assert_eq!(apply_gates(progress(1), &gates, &releases, &status("rc--2", Some(3))), blocked);
+        assert_eq!(apply_gates(progress(1), &gates, &releases, &status("rc--2", None)), progress(1));
+        assert_eq!(apply_gates(progress(2), &gates, &releases, &status("rc--3", Some(0))), progress(2));
+    }
+
+    #[test]
+    fn gates_refer_to_other_networks() {
+        let networks: Networks = serde_yaml::from_str(
+            r#"
+networks:
+  - name: staging
+    fetcher:
+      file:
+        release_index: release-index.yaml
+  - name: mainnet
+    fetcher:
+      curl:
+        url: https://raw.githubusercontent.com/dfinity/dre/main/release-index.yaml
+    gates:
+      - stage: 1
+        network: staging
+"#,
+        )
+        .unwrap();
+        assert!(check_networks(&networks.networks).is_ok());
+
+        let mut gated_by_itself = networks.networks.clone();
+        gated_by_itself[1].gates[0].network = "mainnet".to_string();
+        assert!(check_networks(&gated_by_itself).is_err());
+
+        let duplicated = [networks.networks.clone(), networks.networks].concat();
+        assert!(check_networks(&duplicated).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDate};
+use clap::Parser;
+use ic_management_types::Network;
+use slog::{info, Logger};
+
+use crate::{
+    calculation::{simulate, Index},
+    fetching::holidays::load_holidays,
+    registry_wrappers::sync_wrap,
+};
+
+#[derive(Parser, Clone, Debug)]
+pub struct SimulateConfig {
+    #[clap(
+        long = "from",
+        help = r#"
+The day on which the simulated rollout starts. Defaults to today.
+
+"#
+    )]
+    pub from: Option<NaiveDate>,
+
+    #[clap(
+        long = "release-index",
+        default_value = "release-index.yaml",
+        help = r#"
+Path to the release index whose rollout should be simulated.
+
+"#
+    )]
+    pub release_index: PathBuf,
+}
+
+/// Prints the schedule in which the rollout of the index would update the subnets
+/// of the network, starting from their current versions.
+pub async fn run(logger: Logger, config: SimulateConfig, targets_dir: PathBuf, network: Network) -> anyhow::Result<()> {
+    let mut index: Index = serde_yaml::from_slice(&tokio::fs::read(&config.release_index).await?)
+        .map_err(|e| anyhow::anyhow!("Couldn't parse release index: {:?}", e))?;
+    index.rollout.holidays = load_holidays(&index.rollout.holiday_calendars, index.rollout.time_zone()).await?;
+
+    info!(logger, "Syncing registry for network '{}'", network);
+    let registry_state = sync_wrap(logger.clone(), targets_dir, network).await?;
+    let subnets = registry_state.subnets().into_values().collect::<Vec<_>>();
+    let unassigned_version = registry_state.get_unassigned_nodes_replica_version().await?;
+
+    let from = config.from.unwrap_or_else(|| Local::now().date_naive());
+    let schedule = simulate(&index, subnets, unassigned_version, from)?;
+
+    let mut current_day = None;
+    for update in schedule {
+        if current_day != Some(update.at.date()) {
+            current_day = Some(update.at.date());
+            println!("{}", update.at.format("%A, %Y-%m-%d"));
+        }
+        println!(
+            "  {}  stage {:<3} {:<63}  {}",
+            update.at.format("%H:%M"),
+            update.stage,
+            update.subnet.unwrap_or_else(|| "unassigned nodes".to_string()),
+            update.version
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,649 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use axum_otel_metrics::HttpMetricsLayer;
+use opentelemetry::{
+    global,
+    metrics::{Counter, Observer},
+    KeyValue,
+};
+use ring::hmac;
+use serde::Serialize;
+use slog::{info, Logger};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::{actions::SubnetAction, calculation::Progress};
+
+const ROLLOUT_CONTROLLER: &str = "rollout-controller";
+
+/// Headers in which the common forges pass the type of a webhook event
+const EVENT_HEADERS: [&str; 2] = ["x-github-event", "x-gitlab-event"];
+
+/// Header in which GitHub passes the HMAC-SHA256 of a webhook payload, as `sha256=<hex>`
+const GITHUB_SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// Header in which GitLab passes the secret token of a webhook
+const GITLAB_TOKEN_HEADER: &str = "x-gitlab-token";
+
+/// The plan computed in the last loop of the controller
+#[derive(Serialize, Clone, Default, Debug, PartialEq)]
+pub struct RolloutStatus {
+    pub release: Option<String>,
+    pub stage: Option<usize>,
+    pub halted: bool,
+    /// A subnet was rolled back, so the rollout only continues once it's resumed
+    pub resume_required: bool,
+    pub subnets_updated: usize,
+    pub subnets_total: usize,
+    pub last_successful_loop_seconds: Option<u64>,
+    pub actions: Vec<ActionStatus>,
+}
+
+#[derive(Serialize, Clone, Default, Debug, PartialEq)]
+pub struct ActionStatus {
+    pub stage: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subnet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_group: Option<String>,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_bake_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposal_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocking_reason: Option<String>,
+}
+
+impl ActionStatus {
+    fn new(stage: Option<usize>, action: &SubnetAction) -> Self {
+        let status = Self { stage, ..Default::default() };
+        match action {
+            SubnetAction::Noop { subnet_short } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "noop".to_string(),
+                ..status
+            },
+            SubnetAction::Baking { subnet_short, remaining } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "baking".to_string(),
+                remaining_bake_seconds: Some(remaining.as_secs()),
+                blocking_reason: Some("subnet is baking".to_string()),
+                ..status
+            },
+            SubnetAction::PendingProposal { subnet_short, proposal_id } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "pending_proposal".to_string(),
+                proposal_id: Some(*proposal_id),
+                blocking_reason: Some("proposal has to be voted on".to_string()),
+                ..status
+            },
+            SubnetAction::PlaceProposal {
+                is_unassigned,
+                subnet_principal,
+                ..
+            } => Self {
+                subnet: Some(match is_unassigned {
+                    true => "unassigned".to_string(),
+                    false => subnet_principal.to_string(),
+                }),
+                action: "place_proposal".to_string(),
+                ..status
+            },
+            SubnetAction::WaitForNextWeek { subnet_short } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "wait_for_next_week".to_string(),
+                blocking_reason: Some("the stage is rolled out next week".to_string()),
+                ..status
+            },
+            SubnetAction::CanaryBaking { subnet_short, remaining } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "canary_baking".to_string(),
+                remaining_bake_seconds: Some(remaining.as_secs()),
+                blocking_reason: Some("canary subnet is baking".to_string()),
+                ..status
+            },
+            SubnetAction::PromoteCanary { canaries } => Self {
+                subnet: Some(canaries.join(",")),
+                action: "promote_canary".to_string(),
+                ..status
+            },
+            SubnetAction::Blocked { subnet_short, reason } => Self {
+                subnet: subnet_short.clone(),
+                action: "blocked".to_string(),
+                blocking_reason: Some(reason.clone()),
+                ..status
+            },
+            SubnetAction::Halt { subnet_short, reason } => Self {
+                subnet: Some(subnet_short.clone()),
+                action: "halt".to_string(),
+                blocking_reason: Some(reason.clone()),
+                ..status
+            },
+            SubnetAction::Rollback {
+                subnet_principal, reason, ..
+            } => Self {
+                subnet: Some(subnet_principal.to_string()),
+                action: "rollback".to_string(),
+                blocking_reason: Some(reason.clone()),
+                ..status
+            },
+            SubnetAction::HostosBaking { node_group, remaining } => Self {
+                node_group: Some(node_group.clone()),
+                action: "hostos_baking".to_string(),
+                remaining_bake_seconds: Some(remaining.as_secs()),
+                blocking_reason: Some("nodes are baking".to_string()),
+                ..status
+            },
+            SubnetAction::PendingHostosProposal { proposal_id } => Self {
+                action: "pending_hostos_proposal".to_string(),
+                proposal_id: Some(*proposal_id),
+                blocking_reason: Some("proposal has to be voted on".to_string()),
+                ..status
+            },
+            SubnetAction::PlaceHostosProposal { node_group, .. } => Self {
+                node_group: Some(node_group.clone()),
+                action: "place_hostos_proposal".to_string(),
+                ..status
+            },
+        }
+    }
+}
+
+/// Keeps the latest status of the rollout of a network for the status endpoint
+/// and the metrics, which are labeled with the network.
+#[derive(Clone)]
+pub struct StatusTracker {
+    status: Arc<RwLock<RolloutStatus>>,
+    proposals_placed: Counter<u64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl StatusTracker {
+    /// Has to be created after the metrics layer, which sets the global meter provider.
+    pub fn new(network: &str) -> Self {
+        let attributes = vec![KeyValue::new("network", network.to_string())];
+        let status = Arc::new(RwLock::new(RolloutStatus::default()));
+        let meter = global::meter(ROLLOUT_CONTROLLER);
+        let proposals_placed = meter
+            .u64_counter("rollout_controller.proposals.placed")
+            .with_description("Total number of proposals placed by the controller")
+            .init();
+        let stage = meter
+            .u64_observable_gauge("rollout_controller.stage")
+            .with_description("Index of the stage the rollout is at")
+            .init();
+        let subnets_updated = meter
+            .u64_observable_gauge("rollout_controller.subnets.updated")
+            .with_description("Number of subnets that run the version that is rolled out")
+            .init();
+        let subnets_total = meter
+            .u64_observable_gauge("rollout_controller.subnets.total")
+            .with_description("Total number of subnets")
+            .init();
+        let last_successful_loop = meter
+            .u64_observable_gauge("rollout_controller.last_successful_loop")
+            .with_description("Unix timestamp of the last loop that executed its actions successfully")
+            .init();
+        let instruments = [
+            stage.as_any(),
+            subnets_updated.as_any(),
+            subnets_total.as_any(),
+            last_successful_loop.as_any(),
+        ];
+        let s = status.clone();
+        let a = attributes.clone();
+        let update_instruments = move |observer: &dyn Observer| {
+            let status = s.read().unwrap();
+            if let Some(stage_index) = status.stage {
+                observer.observe_u64(&stage, stage_index as u64, &a);
+            }
+            observer.observe_u64(&subnets_updated, status.subnets_updated as u64, &a);
+            observer.observe_u64(&subnets_total, status.subnets_total as u64, &a);
+            if let Some(seconds) = status.last_successful_loop_seconds {
+                observer.observe_u64(&last_successful_loop, seconds, &a);
+            }
+        };
+        meter.register_callback(&instruments, update_instruments).unwrap();
+
+        Self {
+            status,
+            proposals_placed,
+            attributes,
+        }
+    }
+
+    pub fn update(&self, progress: &Progress) {
+        let mut status = self.status.write().unwrap();
+        *status = RolloutStatus {
+            release: progress.release.clone(),
+            stage: progress.stage,
+            halted: status.halted,
+            resume_required: status.resume_required,
+            subnets_updated: progress.subnets_updated,
+            subnets_total: progress.subnets_total,
+            last_successful_loop_seconds: status.last_successful_loop_seconds,
+            actions: progress.actions.iter().map(|a| ActionStatus::new(progress.stage, a)).collect(),
+        };
+    }
+
+    pub fn set_halted(&self, halted: bool) {
+        self.status.write().unwrap().halted = halted;
+    }
+
+    pub fn require_resume(&self) {
+        self.status.write().unwrap().resume_required = true;
+    }
+
+    pub fn resume(&self) {
+        let mut status = self.status.write().unwrap();
+        status.resume_required = false;
+        status.halted = false;
+    }
+
+    pub fn proposals_placed(&self, count: u64) {
+        self.proposals_placed.add(count, &self.attributes);
+    }
+
+    pub fn loop_succeeded(&self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.status.write().unwrap().last_successful_loop_seconds = Some(now);
+    }
+
+    pub fn snapshot(&self) -> RolloutStatus {
+        self.status.read().unwrap().clone()
+    }
+}
+
+/// What the server needs of each network the controller rolls out
+#[derive(Clone)]
+pub struct NetworkHandle {
+    pub name: String,
+    pub tracker: StatusTracker,
+    /// Notified to run the loop of the network without waiting for the next poll
+    pub refresh: Arc<Notify>,
+}
+
+impl NetworkHandle {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tracker: StatusTracker::new(name),
+            refresh: Arc::new(Notify::new()),
+        }
+    }
+}
+
+/// The secret that authorizes the requests that change the rollout, passed as
+/// `Authorization: Bearer <secret>`, or as the secret of a GitHub or GitLab
+/// webhook. It's redacted when printed.
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+impl Secret {
+    /// Compares in constant time, so the secret can't be guessed from the response times
+    fn matches(&self, given: &str) -> bool {
+        let (secret, given) = (self.0.as_bytes(), given.as_bytes());
+        secret.len() == given.len() && secret.iter().zip(given).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    /// Whether the `sha256=<hex>` signature is the HMAC-SHA256 of the payload
+    /// with the secret, as GitHub signs webhooks. Verified in constant time too.
+    fn signed(&self, payload: &[u8], signature: &str) -> bool {
+        let Some(Ok(tag)) = signature.strip_prefix("sha256=").map(hex::decode) else {
+            return false;
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.0.as_bytes());
+        hmac::verify(&key, payload, &tag).is_ok()
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    header(headers, header::AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer "))
+}
+
+#[derive(Clone)]
+struct ServerState {
+    logger: Logger,
+    networks: Vec<NetworkHandle>,
+    /// Without a secret the requests that change the rollout are forbidden
+    secret: Option<Secret>,
+}
+
+impl ServerState {
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let secret = self.secret.as_ref().ok_or(StatusCode::FORBIDDEN)?;
+        match bearer(headers) {
+            Some(given) if secret.matches(given) => Ok(()),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    /// Like [ServerState::authorize], also accepting the signature of a GitHub
+    /// webhook or the token of a GitLab webhook
+    fn authorize_webhook(&self, headers: &HeaderMap, payload: &[u8]) -> Result<(), StatusCode> {
+        let secret = self.secret.as_ref().ok_or(StatusCode::FORBIDDEN)?;
+        let authorized = bearer(headers).is_some_and(|given| secret.matches(given))
+            || header(headers, GITHUB_SIGNATURE_HEADER).is_some_and(|signature| secret.signed(payload, signature))
+            || header(headers, GITLAB_TOKEN_HEADER).is_some_and(|token| secret.matches(token));
+        match authorized {
+            true => Ok(()),
+            false => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+/// The status of the rollouts of all networks, by name
+/// The status of the first network, which `/status` served before several
+/// networks could be rolled out
+async fn get_status(State(state): State<ServerState>) -> Result<Json<RolloutStatus>, StatusCode> {
+    state.networks.first().map(|n| Json(n.tracker.snapshot())).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_all_statuses(State(state): State<ServerState>) -> Json<BTreeMap<String, RolloutStatus>> {
+    Json(state.networks.iter().map(|n| (n.name.clone(), n.tracker.snapshot())).collect())
+}
+
+async fn get_network_status(State(state): State<ServerState>, Path(network): Path<String>) -> Result<Json<RolloutStatus>, StatusCode> {
+    state
+        .networks
+        .iter()
+        .find(|n| n.name == network)
+        .map(|n| Json(n.tracker.snapshot()))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Resumes the rollout of a network that was halted by a rollback
+async fn resume(State(state): State<ServerState>, headers: HeaderMap, Path(network): Path<String>) -> StatusCode {
+    if let Err(status) = state.authorize(&headers) {
+        return status;
+    }
+    match state.networks.iter().find(|n| n.name == network) {
+        Some(network) => {
+            info!(state.logger, "Resuming the rollout of network {}", network.name);
+            network.tracker.resume();
+            network.refresh.notify_one();
+            StatusCode::ACCEPTED
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+/// Accepts any payload, e.g. the push event of a repository, and runs the
+/// loops of all networks without waiting for the next poll. The payload is only
+/// read to verify its signature, and the type of the event is logged.
+async fn webhook(State(state): State<ServerState>, headers: HeaderMap, payload: Bytes) -> StatusCode {
+    if let Err(status) = state.authorize_webhook(&headers, &payload) {
+        return status;
+    }
+    let event = EVENT_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown");
+    info!(state.logger, "Received {} webhook, refreshing the release index", event);
+    for network in &state.networks {
+        network.refresh.notify_one();
+    }
+    StatusCode::ACCEPTED
+}
+
+fn routes() -> Router<ServerState> {
+    Router::new()
+        .route("/status", get(get_status))
+        .route("/status/all", get(get_all_statuses))
+        .route("/status/:network", get(get_network_status))
+        .route("/webhook", post(webhook))
+        .route("/resume/:network", post(resume))
+}
+
+/// Serves the status of the rollout of the first network on `/status`, of all
+/// networks on `/status/all` and of a single network on `/status/<network>`, the webhook that refreshes all networks
+/// on `/webhook`, resumes networks on `/resume/<network>` and the metrics on `/metrics` until the token is cancelled.
+/// The webhook and the resumes require the secret.
+pub async fn serve(
+    logger: Logger,
+    addr: SocketAddr,
+    networks: Vec<NetworkHandle>,
+    secret: Option<Secret>,
+    metrics_layer: HttpMetricsLayer,
+    token: CancellationToken,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .merge(metrics_layer.routes())
+        .merge(routes())
+        .layer(metrics_layer)
+        .with_state(ServerState {
+            logger: logger.clone(),
+            networks,
+            secret,
+        });
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(logger, "Status server started on {}", addr);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { token.cancelled().await })
+        .await?;
+    info!(logger, "Status server stopped");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use slog::o;
+
+    use super::*;
+
+    #[test]
+    fn action_status() {
+        assert_eq!(
+            ActionStatus::new(
+                Some(1),
+                &SubnetAction::Baking {
+                    subnet_short: "shefu".to_string(),
+                    remaining: Duration::from_secs(90),
+                }
+            ),
+            ActionStatus {
+                stage: Some(1),
+                subnet: Some("shefu".to_string()),
+                action: "baking".to_string(),
+                remaining_bake_seconds: Some(90),
+                blocking_reason: Some("subnet is baking".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            ActionStatus::new(None, &SubnetAction::PendingHostosProposal { proposal_id: 42 }),
+            ActionStatus {
+                action: "pending_hostos_proposal".to_string(),
+                proposal_id: Some(42),
+                blocking_reason: Some("proposal has to be voted on".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    const SECRET: &str = "s3cret";
+
+    async fn start(networks: Vec<NetworkHandle>, secret: Option<&str>) -> String {
+        let app = routes().with_state(ServerState {
+            logger: Logger::root(slog::Discard, o!()),
+            networks,
+            secret: secret.map(|s| Secret::from(s.to_string())),
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn status_of_all_networks() {
+        let networks = vec![NetworkHandle::new("mainnet"), NetworkHandle::new("staging")];
+        networks[1].tracker.set_halted(true);
+        let url = start(networks, None).await;
+        let client = reqwest::Client::new();
+
+        let status: serde_json::Value = client.get(format!("{}/status", url)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(status["halted"], false);
+
+        let status: serde_json::Value = client.get(format!("{}/status/all", url)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(status["mainnet"]["halted"], false);
+        assert_eq!(status["staging"]["halted"], true);
+
+        let status: serde_json::Value = client.get(format!("{}/status/staging", url)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(status["halted"], true);
+
+        let response = client.get(format!("{}/status/unknown", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn webhook_refreshes() {
+        let networks = vec![NetworkHandle::new("mainnet"), NetworkHandle::new("staging")];
+        let url = format!("{}/webhook", start(networks.clone(), Some(SECRET)).await);
+
+        // Stands in for the push notification of the repository
+        let response = reqwest::Client::new()
+            .post(&url)
+            .bearer_auth(SECRET)
+            .header("X-GitHub-Event", "push")
+            .json(&serde_json::json!({ "ref": "refs/heads/main" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        for network in networks {
+            tokio::time::timeout(Duration::from_secs(5), network.refresh.notified())
+                .await
+                .expect("Should be notified by the webhook");
+        }
+    }
+
+    #[tokio::test]
+    async fn webhooks_of_forges() {
+        let network = NetworkHandle::new("mainnet");
+        let url = format!("{}/webhook", start(vec![network.clone()], Some(SECRET)).await);
+        let client = reqwest::Client::new();
+        let (payload, other_payload) = (r#"{"ref":"refs/heads/main"}"#, r#"{"ref":"refs/heads/other"}"#);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+        let signature = format!("sha256={}", hex::encode(hmac::sign(&key, payload.as_bytes())));
+
+        for (name, value, payload, authorized) in [
+            (GITHUB_SIGNATURE_HEADER, signature.as_str(), payload, true),
+            (GITHUB_SIGNATURE_HEADER, signature.as_str(), other_payload, false),
+            (GITHUB_SIGNATURE_HEADER, "sha256=00", payload, false),
+            (GITHUB_SIGNATURE_HEADER, SECRET, payload, false),
+            (GITLAB_TOKEN_HEADER, SECRET, payload, true),
+            (GITLAB_TOKEN_HEADER, "guess", payload, false),
+        ] {
+            let response = client.post(&url).header(name, value).body(payload).send().await.unwrap();
+            let status = match authorized {
+                true => reqwest::StatusCode::ACCEPTED,
+                false => reqwest::StatusCode::UNAUTHORIZED,
+            };
+            assert_eq!(response.status(), status, "{}: {} for {}", name, value, payload);
+        }
+        tokio::time::timeout(Duration::from_secs(5), network.refresh.notified())
+            .await
+            .expect("Should be notified by the webhook");
+    }
+
+    #[tokio::test]
+    async fn resume_after_rollback() {
+        let network = NetworkHandle::new("mainnet");
+        network.tracker.set_halted(true);
+        network.tracker.require_resume();
+        let url = format!("{}/resume", start(vec![network.clone()], Some(SECRET)).await);
+        let client = reqwest::Client::new();
+
+        let response = client.post(format!("{}/unknown", url)).bearer_auth(SECRET).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert!(network.tracker.snapshot().resume_required);
+
+        let response = client.post(format!("{}/mainnet", url)).bearer_auth(SECRET).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        let status = network.tracker.snapshot();
+        assert!(!status.resume_required);
+        assert!(!status.halted);
+        tokio::time::timeout(Duration::from_secs(5), network.refresh.notified())
+            .await
+            .expect("Should be notified by the resume");
+    }
+
+    #[tokio::test]
+    async fn changes_require_the_secret() {
+        let network = NetworkHandle::new("mainnet");
+        network.tracker.require_resume();
+        let client = reqwest::Client::new();
+
+        let url = start(vec![network.clone()], Some(SECRET)).await;
+        for path in ["webhook", "resume/mainnet"] {
+            let response = client.post(format!("{}/{}", url, path)).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+            let response = client.post(format!("{}/{}", url, path)).bearer_auth("guess").send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        }
+
+        let url = start(vec![network.clone()], None).await;
+        for path in ["webhook", "resume/mainnet"] {
+            let response = client.post(format!("{}/{}", url, path)).bearer_auth(SECRET).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        }
+        assert!(network.tracker.snapshot().resume_required);
+    }
+
+    #[test]
+    fn secret_is_redacted() {
+        let secret = Secret::from(SECRET.to_string());
+        assert!(!format!("{:?}", secret).contains(SECRET));
+        assert!(secret.matches(SECRET));
+        assert!(!secret.matches("s3cre"));
+        assert!(!secret.matches("s3creT"));
+    }
+
+    #[test]
+    fn github_signature() {
+        // The example of the GitHub documentation on validating webhook deliveries
+        let secret = Secret::from("It's a Secret to Everybody".to_string());
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(secret.signed(b"Hello, World!", signature));
+        assert!(!secret.signed(b"Hello, World", signature));
+        assert!(!secret.signed(b"Hello, World!", signature.trim_start_matches("sha256=")));
+        assert!(!secret.signed(b"Hello, World!", "sha256=not hex"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf, sync::OnceLock};
+
+use clap::Parser;
+use ic_base_types::PrincipalId;
+use ic_management_types::{Network, Subnet};
+use jsonschema::JSONSchema;
+use slog::{info, Logger};
+
+use crate::{
+    calculation::{desired_rollout_release_version, Index},
+    registry_wrappers::sync_wrap,
+};
+
+const SCHEMA: &str = include_str!("../../../release-index-schema.json");
+
+#[derive(Parser, Clone, Debug)]
+pub struct ValidateConfig {
+    #[clap(
+        long = "release-index",
+        default_value = "release-index.yaml",
+        help = r#"
+Path to the release index that should be validated.
+
+"#
+    )]
+    pub release_index: PathBuf,
+}
+
+/// A problem with the release index and where in the index it is
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// JSON pointer to the offending value, e.g. `/rollout/stages/0/subnets/1`
+    pub location: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(location: impl ToString, message: impl ToString) -> Self {
+        Self {
+            location: location.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Validates the release index against the schema and, if it matches the schema,
+/// against the subnets and elected GuestOS versions of the network.
+pub fn validate(release_index: &str, subnets: &[Subnet], elected_versions: &[String]) -> Vec<Problem> {
+    let instance: serde_json::Value = match serde_yaml::from_str(release_index) {
+        Ok(instance) => instance,
+        Err(e) => return vec![Problem::new("/", format!("Couldn't parse release index: {}", e))],
+    };
+
+    let problems = validate_schema(&instance);
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    match serde_json::from_value::<Index>(instance) {
+        Ok(index) => validate_index(&index, subnets, elected_versions),
+        Err(e) => vec![Problem::new("/", format!("Couldn't parse release index: {}", e))],
+    }
+}
+
+/// The schema, compiled once
+fn schema() -> &'static JSONSchema {
+    static COMPILED: OnceLock<JSONSchema> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).expect("The schema should be valid JSON");
+        JSONSchema::compile(&schema).expect("The schema should be valid")
+    })
+}
+
+fn validate_schema(instance: &serde_json::Value) -> Vec<Problem> {
+    match schema().validate(instance) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .map(|e| {
+                let location = e.instance_path.to_string();
+                Problem::new(if location.is_empty() { "/".to_string() } else { location }, e)
+            })
+            .collect(),
+    }
+}
+
+/// Checks what the schema can't: that the index refers to existing subnets,
+/// only once and in non-empty stages, that every subnet runs a version of the
+/// index and that the versions to roll out are elected.
+pub fn validate_index(index: &Index, subnets: &[Subnet], elected_versions: &[String]) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    let mut staged: BTreeMap<PrincipalId, usize> = BTreeMap::new();
+    for (i, stage) in index.rollout.stages.iter().enumerate() {
+        if stage.subnets.is_empty() && !stage.updates_unassigned_nodes() {
+            problems.push(Problem::new(format!("/rollout/stages/{}", i), "Stage is empty"));
+        }
+        if let Some(canary) = stage.canary.as_ref().filter(|c| c.subnets >= stage.subnets.len()) {
+            problems.push(Problem::new(
+                format!("/rollout/stages/{}/canary/subnets", i),
+                format!("Stage has {} subnets, so it can't have {} canaries", stage.subnets.len(), canary.subnets),
+            ));
+        }
+        for (j, blackout) in stage.blackouts.iter().enumerate().filter(|(_, b)| b.end < b.start) {
+            problems.push(Problem::new(
+                format!("/rollout/stages/{}/blackouts/{}", i, j),
+                format!("Blackout ends on {} before it starts on {}", blackout.end, blackout.start),
+            ));
+        }
+        for (j, subnet_short) in stage.subnets.iter().enumerate() {
+            let location = format!("/rollout/stages/{}/subnets/{}", i, j);
+            let Some(principal) = find_subnet(subnets, subnet_short, &location, &mut problems) else {
+                continue;
+            };
+            match staged.get(&principal) {
+                Some(stage) => problems.push(Problem::new(location, format!("Subnet '{}' is already in stage {}", subnet_short, stage))),
+                None => {
+                    staged.insert(principal, i);
+                }
+            }
+        }
+    }
+
+    for (i, window) in index.rollout.windows.iter().enumerate() {
+        if window.start >= window.end {
+            problems.push(Problem::new(format!("/rollout/windows/{}", i), "Window doesn't end after it starts"));
+        }
+    }
+
+    for (i, release) in index.releases.iter().enumerate() {
+        for (j, version) in release.versions.iter().enumerate() {
+            for (k, subnet_short) in version.subnets.iter().enumerate() {
+                find_subnet(
+                    subnets,
+                    subnet_short,
+                    format!("/releases/{}/versions/{}/subnets/{}", i, j, k),
+                    &mut problems,
+                );
+            }
+        }
+    }
+
+    let mut unknown_versions = false;
+    for subnet in subnets {
+        if !index
+            .releases
+            .iter()
+            .any(|r| r.versions.iter().any(|v| v.version == subnet.replica_version))
+        {
+            unknown_versions = true;
+            problems.push(Problem::new(
+                "/releases",
+                format!(
+                    "Subnet '{}' runs version '{}' which isn't in any release",
+                    subnet.principal, subnet.replica_version
+                ),
+            ));
+        }
+    }
+
+    // The release to roll out can only be found if all subnets run versions of the index
+    if !unknown_versions && !subnets.is_empty() {
+        let desired = desired_rollout_release_version(subnets, &index.releases);
+        if let Some(i) = index.releases.iter().position(|r| *r == desired.release) {
+            for (j, version) in desired.release.versions.iter().enumerate() {
+                if !elected_versions.contains(&version.version) {
+                    problems.push(Problem::new(
+                        format!("/releases/{}/versions/{}/version", i, j),
+                        format!("Version '{}' of release '{}' is not elected", version.version, desired.release.rc_name),
+                    ));
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+/// The principal of the only subnet starting with `subnet_short`
+fn find_subnet(subnets: &[Subnet], subnet_short: &str, location: impl ToString, problems: &mut Vec<Problem>) -> Option<PrincipalId> {
+    let matching = subnets
+        .iter()
+        .filter(|s| s.principal.to_string().starts_with(subnet_short))
+        .map(|s| s.principal)
+        .collect::<Vec<_>>();
+    match matching.as_slice() {
+        [principal] => Some(*principal),
+        [] => {
+            problems.push(Problem::new(location, format!("Unknown subnet '{}'", subnet_short)));
+            None
+        }
+        _ => {
+            problems.push(Problem::new(
+                location,
+                format!("Subnet '{}' matches {} subnets", subnet_short, matching.len()),
+            ));
+            None
+        }
+    }
+}
+
+/// Prints all problems of the release index and fails if there are any.
+pub async fn run(logger: Logger, config: ValidateConfig, targets_dir: PathBuf, network: Network) -> anyhow::Result<()> {
+    let release_index = tokio::fs::read_to_string(&config.release_index).await?;
+
+    info!(logger, "Syncing registry for network '{}'", network);
+    let registry_state = sync_wrap(logger.clone(), targets_dir, network).await?;
+    let subnets = registry_state.subnets().into_values().collect::<Vec<_>>();
+    let elected_versions = registry_state.get_elected_guestos_versions().await?;
+
+    let problems = validate(&release_index, &subnets, &elected_versions);
+    for problem in &problems {
+        println!("{}: {}", config.release_index.display(), problem);
+    }
+
+    match problems.len() {
+        0 => Ok(()),
+        count => Err(anyhow::anyhow!("Found {} problems in the release index", count)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subnet(id: u64, version: &str) -> Subnet {
+        Subnet {
+            principal: PrincipalId::new_subnet_test_id(id),
+            replica_version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn release_index(stages: &str) -> String {
+        format!(
+            r#"
+rollout:
+  skip_days: []
+  stages:
+{}
+releases:
+  - rc_name: rc--2
+    versions:
+      - name: rc--2
+        version: new
+  - rc_name: rc--1
+    versions:
+      - name: rc--1
+        version: old
+"#,
+            stages
+        )
+    }
+
+    #[test]
+    fn valid_index() {
+        let first = PrincipalId::new_subnet_test_id(1).to_string();
+        let second = PrincipalId::new_subnet_test_id(2).to_string();
+        let index = release_index(&format!(
+            "    - subnets: [{}]\n      bake_time: 8h\n    - subnets: [{}]\n    - update_unassigned_nodes: true",
+            first, second
+        ));
+
+        assert_eq!(
+            validate(&index, &[subnet(1, "old"), subnet(2, "old")], &["old".to_string(), "new".to_string()]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn schema_problems() {
+        let index = release_index("    - subnets: [abc]\n      bake: 8h");
+
+        let problems = validate(&index, &[], &[]);
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].location, "/rollout/stages/0");
+    }
+
+    #[test]
+    fn index_problems() {
+        let first = PrincipalId::new_subnet_test_id(1).to_string();
+        let index = release_index(&format!(
+            "    - subnets: [{}, unknown]\n      blackouts: [{{start: 2024-03-02, end: 2024-03-01}}]\n    - subnets: []\n    - subnets: [{}]",
+            first, first
+        ));
+
+        assert_eq!(
+            validate(&index, &[subnet(1, "old")], &["old".to_string()]),
+            vec![
+                Problem::new(
+                    "/rollout/stages/0/blackouts/0",
+                    "Blackout ends on 2024-03-01 before it starts on 2024-03-02"
+                ),
+                Problem::new("/rollout/stages/0/subnets/1", "Unknown subnet 'unknown'"),
+                Problem::new("/rollout/stages/1", "Stage is empty"),
+                Problem::new("/rollout/stages/2/subnets/0", format!("Subnet '{}' is already in stage 0", first)),
+                Problem::new("/releases/0/versions/0/version", "Version 'new' of release 'rc--2' is not elected"),
+            ]
+        );
+    }
+}
@@ -97,6 +97,17 @@ parser.add_argument(
     default="$HOME/Downloads/release-notes.html",
     help="path to where the output should be generated",
 )
+parser.add_argument(
+    "--repo-path",
+    dest="repo_path",
+    default=os.environ.get("IC_REPO_PATH", str(pathlib.Path.home() / ".cache/git/ic")),
+    help="path to an existing clone of the IC repo, cloned there if missing",
+)
+parser.add_argument(
+    "--offline",
+    action="store_true",
+    help="use the IC repo at --repo-path as is, without fetching new commits",
+)
 parser.add_argument("rc_name", type=str, help="name of the release i.e. 'rc--2023-01-12_18-31'")
 args = parser.parse_args()
 
@@ -134,8 +145,8 @@ def get_ancestry_path(repo_dir, commit_hash, branch):
         subprocess.check_output(
             [
                 "git",
-                "--git-dir",
-                "{}/.git".format(repo_dir),
+                "-C",
+                repo_dir,
                 "rev-list",
                 "{}..{}".format(commit_hash, branch),
                 "--ancestry-path",
@@ -152,8 +163,8 @@ def get_first_parent(repo_dir, commit_hash, branch):
         subprocess.check_output(
             [
                 "git",
-                "--git-dir",
-                "{}/.git".format(repo_dir),
+                "-C",
+                repo_dir,
                 "rev-list",
                 "{}..{}".format(commit_hash, branch),
                 "--ancestry-path",
@@ -171,7 +182,8 @@ def get_commits(repo_dir, first_commit, last_commit):
             subprocess.check_output(
                 [
                     "git",
-                    "--git-dir={}/.git".format(repo_dir),
+                    "-C",
+                    repo_dir,
                     "log",
                     "--format={}".format(git_commit_format),
                     "--no-merges",
@@ -278,23 +290,32 @@ def main():
 
     ci_patterns = ["/**/*.lock", "/**/*.bzl"]
 
-    ic_repo_path = pathlib.Path.home() / ".cache/git/ic"
+    ic_repo_path = pathlib.Path(args.repo_path)
 
-    if ic_repo_path.exists():
+    if args.offline:
+        if not ic_repo_path.exists():
+            print("IC repo {} does not exist, can't run offline".format(ic_repo_path), file=sys.stderr)
+            exit(1)
+        print("Using {} without fetching new commits".format(ic_repo_path))
+    elif ic_repo_path.exists():
         print("Fetching new commits in {}".format(ic_repo_path))
-        subprocess.check_call(
+        fetch = subprocess.run(
             ["git", "fetch"],
             cwd=ic_repo_path,
             stdout=subprocess.DEVNULL,
             stderr=subprocess.DEVNULL,
+            check=False,
         )
-        print("Resetting HEAD to latest origin/master.")
-        subprocess.check_call(
-            ["git", "reset", "--hard", "origin/master"],
-            cwd=ic_repo_path,
-            stdout=subprocess.DEVNULL,
-            stderr=subprocess.DEVNULL,
-        )
+        if fetch.returncode != 0:
+            print("WARNING: failed to fetch new commits, using the local ones", file=sys.stderr)
+        else:
+            print("Resetting HEAD to latest origin/master.")
+            subprocess.check_call(
+                ["git", "reset", "--hard", "origin/master"],
+                cwd=ic_repo_path,
+                stdout=subprocess.DEVNULL,
+                stderr=subprocess.DEVNULL,
+            )
     else:
         print("Cloning IC repo to {}".format(ic_repo_path))
         subprocess.check_call(